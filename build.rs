@@ -1,6 +1,8 @@
 use std::env;
 use std::fs;
 use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
@@ -8,10 +10,17 @@ fn main() {
     println!("cargo:rerun-if-env-changed=TARIUM_EMBED_GITHUB_INSTALLATION_ID");
     println!("cargo:rerun-if-env-changed=TARIUM_EMBED_GITHUB_PRIVATE_KEY_PATH");
     println!("cargo:rerun-if-env-changed=TARIUM_EMBED_GITHUB_PRIVATE_KEY");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    emit_build_info();
 
     let should_embed = env::var("TARIUM_EMBED_CREDENTIALS").unwrap_or_default() == "1"
         || env::var("TARIUM_EMBED_GITHUB_APP_ID").is_ok();
 
+    println!(
+        "cargo:rustc-env=TARIUM_EMBEDDED_CREDENTIALS={}",
+        if should_embed { "yes" } else { "no" }
+    );
     if should_embed {
         embed_github_credentials();
     } else {
@@ -73,3 +82,59 @@ fn embed_github_credentials() {
 
     println!("cargo:warning=Credentials embedded successfully");
 }
+
+/// Emit the commit, date, and target of this build for `tarium --version`
+fn emit_build_info() {
+    // Rebuild when the checked out commit changes
+    for path in [".git/HEAD", ".git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .filter(|commit| !commit.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=TARIUM_GIT_COMMIT={}", commit);
+
+    // Reproducible builds set the date themselves
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    println!(
+        "cargo:rustc-env=TARIUM_BUILD_DATE={:04}-{:02}-{:02}",
+        year, month, day
+    );
+
+    println!(
+        "cargo:rustc-env=TARIUM_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+}
+
+/// The year, month, and day of `days` since the Unix epoch,
+/// from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -1,13 +1,21 @@
 use crate::{
     config::{
         filters::{Filter, ReleaseChannel},
-        structs::{ModIdentifier, Profile},
+        structs::{Mod, ModIdentifier, Profile},
     },
-    extract_versions, is_spt_version,
+    extract_versions,
+    host_limit::{self, GITHUB_API_HOST},
+    is_spt_version, is_supported_archive,
     iter_ext::IterExt as _,
-    upgrade::{check, Metadata},
-    GITHUB_API,
+    upgrade::{
+        check, cmp_preference, fetch_gh_releases, fetch_gh_tags, from_gh_releases, from_gh_tags,
+        gh_release_channel, source_archive, Metadata,
+    },
 };
+use log::debug;
+use octocrab::{models::Repository, Octocrab};
+use regex::Regex;
+use std::collections::BTreeMap;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -25,119 +33,431 @@ pub enum Error {
     DoesNotExist,
     #[error("The project is not a mod")]
     NotAMod,
+    #[error("The project has no release asset with the ID {0}")]
+    NoPinnedAsset(i32),
     #[error("GitHub: {0}")]
     GitHubError(String),
     #[error("GitHub: {0:#?}")]
-    OctocrabError(#[from] octocrab::Error),
+    OctocrabError(octocrab::Error),
+    #[error("GitHub's secondary rate limit is still in effect after retrying, try again in a few minutes or pass `--wait`")]
+    SecondaryRateLimit(#[source] octocrab::Error),
+    #[error("'{pattern}' matches none of the assets of {tag}: {}", assets.iter().display(", "))]
+    NoMatchingAsset {
+        pattern: String,
+        tag: String,
+        assets: Vec<String>,
+    },
+    #[error("'{pattern}' matches more than one asset of {tag}: {}", matches.iter().display(", "))]
+    AmbiguousAsset {
+        pattern: String,
+        tag: String,
+        matches: Vec<String>,
+    },
+    #[error("There is no asset {index}, {tag} has {} archive assets: {}", assets.len(), assets.iter().display(", "))]
+    NoAssetAtIndex {
+        index: usize,
+        tag: String,
+        assets: Vec<String>,
+    },
+}
+
+impl From<octocrab::Error> for Error {
+    fn from(err: octocrab::Error) -> Self {
+        if host_limit::is_secondary_rate_limit(&err) {
+            Self::SecondaryRateLimit(err)
+        } else {
+            Self::OctocrabError(err)
+        }
+    }
 }
 type Result<T> = std::result::Result<T, Error>;
 
-pub fn parse_id(id: String) -> ModIdentifier {
-    let split = id.split('/').collect_vec();
-    ModIdentifier::GitHubRepository(split[0].to_owned(), split[1].to_owned())
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("Invalid identifier '{0}', expected the format 'owner/repo'")]
+    Invalid(String),
+    #[error(
+        "Unknown alias '{name}', {}",
+        match suggestion {
+            Some(alias) => format!("did you mean '{alias}'?"),
+            None => format!("use 'owner/repo' or set it with `tarium alias set {name} <owner/repo>`"),
+        }
+    )]
+    UnknownAlias {
+        name: String,
+        /// The most similar alias, if there's one close enough
+        suggestion: Option<String>,
+    },
+}
+
+/// Parse a GitHub repository identifier in the format `owner/repo`,
+/// or a name from `aliases` that expands to one
+pub fn parse_id(
+    id: String,
+    aliases: &BTreeMap<String, String>,
+) -> std::result::Result<ModIdentifier, ParseError> {
+    let name = id.trim();
+    if !name.is_empty() && !name.contains('/') {
+        return match aliases.get(&name.to_lowercase()) {
+            // Aliases can't refer to other aliases
+            Some(target) => parse_id(target.clone(), &BTreeMap::new()),
+            None => Err(ParseError::UnknownAlias {
+                suggestion: closest_alias(name, aliases).map(ToOwned::to_owned),
+                name: name.to_owned(),
+            }),
+        };
+    }
+
+    let split = id
+        .trim()
+        .trim_end_matches('/')
+        .split('/')
+        .map(str::trim)
+        .collect_vec();
+    match split[..] {
+        [owner, repo] if !owner.is_empty() && !repo.is_empty() => Ok(
+            ModIdentifier::GitHubRepository(owner.to_owned(), repo.to_owned()),
+        ),
+        _ => Err(ParseError::Invalid(id)),
+    }
 }
 
-/// Adds mods from `identifiers`, and returns successful mods with their names, and unsuccessful mods with an error.
-/// Currently does not batch requests when adding multiple pinned mods.
+/// The alias in `aliases` closest to `name`, if it's at most 2 edits away
+fn closest_alias<'a>(name: &str, aliases: &'a BTreeMap<String, String>) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    aliases
+        .keys()
+        .map(|alias| (edit_distance(&name, alias), alias))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, alias)| alias.as_str())
+}
+
+/// The Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect_vec();
+    let mut previous = (0..=b.len()).collect_vec();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Find the index of `owner/repo` in `profile`'s mods, ignoring case
+fn find_added(profile: &Profile, owner: &str, repo: &str) -> Option<usize> {
+    profile.mods.iter().position(|mod_| {
+        mod_.name.eq_ignore_ascii_case(repo)
+            || matches!(
+                &mod_.identifier,
+                ModIdentifier::GitHubRepository(o, r) | ModIdentifier::PinnedGitHubRepository((o, r), _)
+                    if o.eq_ignore_ascii_case(owner) && r.eq_ignore_ascii_case(repo),
+            )
+    })
+}
+
+/// Whether a mod was newly added or an existing one was updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Added {
+    New,
+    Updated,
+}
+
+/// The outcome of adding a single mod in [`add_batch`]
+#[derive(Debug)]
+pub struct AddOutcome {
+    pub identifier: ModIdentifier,
+    /// The name the mod is displayed with, `owner/repo` for GitHub repositories
+    pub name: String,
+    pub result: Result<Added>,
+    /// Something the user should know about the mod even though it was added,
+    /// e.g. that its repository is archived
+    pub warning: Option<String>,
+}
+
+/// Which of a release's archive assets a mod is added with, instead of the one preferred by default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetSelection {
+    /// The only asset whose filename contains this or matches it as a regex
+    Pattern(String),
+    /// The asset at this position in the release's assets, counting from 1
+    Index(usize),
+}
+
+/// How [`add_batch`] adds mods, see it for what each option does
+#[derive(Debug, Clone)]
+pub struct AddOptions {
+    pub perform_checks: bool,
+    pub fetch_metadata: bool,
+    pub override_profile: bool,
+    pub filters: Vec<Filter>,
+    pub replace: bool,
+    pub source_archives: bool,
+    pub asset: Option<AssetSelection>,
+    pub max_releases: usize,
+}
+
+impl Default for AddOptions {
+    /// Check the mods and fetch their details, scanning [`DEFAULT_MAX_RELEASES`](crate::DEFAULT_MAX_RELEASES) releases,
+    /// without any filters of their own
+    fn default() -> Self {
+        Self {
+            perform_checks: true,
+            fetch_metadata: true,
+            override_profile: false,
+            filters: vec![],
+            replace: false,
+            source_archives: false,
+            asset: None,
+            max_releases: crate::DEFAULT_MAX_RELEASES,
+        }
+    }
+}
+
+/// Adds mods from `identifiers`, and returns the names of added mods, the names of updated mods,
+/// the names of mods that were added with a warning along with the warning, and unsuccessful mods with an error.
 ///
-/// Classifies the `identifiers` into the appropriate platforms, sends batch requests to get the necessary information,
-/// checks details about the projects, and adds them to `profile` if suitable.
-/// Performs checks on the mods to see whether they're compatible with the profile if `perform_checks` is true
+/// A thin wrapper around [`add_batch`], see it for details.
 pub async fn add(
+    github_api: &Octocrab,
     profile: &mut Profile,
     identifiers: Vec<ModIdentifier>,
-    perform_checks: bool,
-) -> Result<(Vec<String>, Vec<(String, Error)>)> {
-    // Adding identifiers
-    let mut gh_ids = Vec::new();
+    options: &AddOptions,
+) -> Result<(
+    Vec<String>,
+    Vec<String>,
+    Vec<(String, String)>,
+    Vec<(String, Error)>,
+)> {
+    Ok(partition(
+        add_batch(github_api, profile, identifiers, options).await,
+    ))
+}
+
+/// Split `outcomes` into the names of added mods, the names of updated mods,
+/// the names of mods that were added with a warning along with the warning, and unsuccessful mods with an error,
+/// like [`add`] returns them
+pub fn partition(
+    outcomes: impl IntoIterator<Item = AddOutcome>,
+) -> (
+    Vec<String>,
+    Vec<String>,
+    Vec<(String, String)>,
+    Vec<(String, Error)>,
+) {
+    let mut success_names = Vec::new();
+    let mut updated_names = Vec::new();
+    let mut warnings = Vec::new();
     let mut errors = Vec::new();
 
-    for id in identifiers {
-        match id {
-            ModIdentifier::GitHubRepository(o, r) => gh_ids.push((o, r)),
-            ModIdentifier::PinnedGitHubRepository((owner, repo), asset_id) => todo!(),
+    for outcome in outcomes {
+        if let (Ok(_), Some(warning)) = (&outcome.result, outcome.warning) {
+            warnings.push((outcome.name.clone(), warning));
+        }
+        match outcome.result {
+            Ok(Added::New) => success_names.push(outcome.name),
+            Ok(Added::Updated) => updated_names.push(outcome.name),
+            Err(err) => errors.push((outcome.name, err)),
         }
     }
 
-    let mut success_names = Vec::new();
+    (success_names, updated_names, warnings, errors)
+}
 
-    if perform_checks {
-        // Fetch releases and perform compatibility checks
-        let gh_repos = {
-            let mut repos_data = Vec::new();
+/// Adds mods from `identifiers`, and returns the outcome for each of them in order.
+/// Currently does not batch requests when adding multiple pinned mods.
+///
+/// Checks details about the projects, and adds them to `profile` if suitable, as the fields of `options` say.
+/// Performs checks on the mods to see whether they're compatible with the profile if `perform_checks` is true,
+/// scanning at most `max_releases` of each repository's most recent releases fetched with `github_api`.
+/// The checks also warn about repositories that won't receive updates, see [`repository_warning`].
+///
+/// New mods are named after their repository, with its description, if `fetch_metadata` or `perform_checks` is true.
+/// This is a single request per mod, which doesn't fail the mod if checks are skipped.
+///
+/// The mods are given `filters`, which replace the profile's filters if `override_profile` is true.
+/// If `source_archives` is true, the mods fall back to the source code of releases without archive assets.
+/// If an `asset` is selected, it's picked from the latest release and the mods are given a filename filter
+/// that keeps picking it in later releases, see [`select_asset`].
+/// Pinned mods always install the asset they're pinned to, so they're only checked for it existing, and aren't given an `asset`.
+/// Mods that have already been added have their filters updated if `replace` is true,
+/// otherwise they fail with [`Error::AlreadyAdded`].
+pub async fn add_batch(
+    github_api: &Octocrab,
+    profile: &mut Profile,
+    identifiers: Vec<ModIdentifier>,
+    options: &AddOptions,
+) -> Vec<AddOutcome> {
+    let AddOptions {
+        perform_checks,
+        fetch_metadata,
+        override_profile,
+        ref filters,
+        replace,
+        source_archives,
+        ref asset,
+        max_releases,
+    } = *options;
+    let mut outcomes = Vec::new();
 
-            // Process each repository using REST API instead of GraphQL
-            for (owner, name) in &gh_ids {
-                match fetch_repo_releases_rest(owner, name).await {
-                    Ok(metadata) => {
-                        repos_data.push(((owner.clone(), name.clone()), metadata));
-                    }
-                    Err(err) => {
-                        errors.push((format!("{}/{}", owner, name), err));
-                    }
-                }
+    for identifier in identifiers {
+        let (owner, name, pin) = match &identifier {
+            ModIdentifier::GitHubRepository(o, r) => (o.clone(), r.clone(), None),
+            ModIdentifier::PinnedGitHubRepository((owner, repo), asset_id) => {
+                (owner.clone(), repo.clone(), Some(*asset_id))
             }
-
-            repos_data
         };
+        let display_name = format!("{owner}/{name}");
+        let mut warning = None;
 
-        for (repo, asset_names) in gh_repos {
-            match github(&repo, profile, Some(asset_names)).await {
-                Ok(_) => success_names.push(format!("{}/{}", repo.0, repo.1)),
-                Err(err) => errors.push((format!("{}/{}", repo.0, repo.1), err)),
-            }
-        }
-    } else {
-        // Skip release fetching and checks, just add repositories directly
-        for (owner, name) in &gh_ids {
-            // Check if project has already been added
-            if profile.mods.iter().any(|mod_| {
-                mod_.name.eq_ignore_ascii_case(name)
-                    || matches!(
-                        &mod_.identifier,
-                        ModIdentifier::GitHubRepository(o, r) if o == owner && r == name,
+        let result = async {
+            let repository = if perform_checks || fetch_metadata {
+                match get_repository(github_api, &owner, &name).await {
+                    Ok(repository) => Some(repository),
+                    Err(err) if !perform_checks => {
+                        warning = Some(format!("Couldn't fetch the repository's details: {err}"));
+                        None
+                    }
+                    Err(err) => return Err(err),
+                }
+            } else {
+                None
+            };
+
+            let mut filters = filters.clone();
+            let selected = match asset.as_ref().filter(|_| pin.is_none()) {
+                Some(selection) => {
+                    let (filter, metadata) = select_asset(
+                        github_api,
+                        &owner,
+                        &name,
+                        selection,
+                        max_releases,
+                        source_archives,
                     )
-            }) {
-                errors.push((format!("{}/{}", owner, name), Error::AlreadyAdded));
-                continue;
+                    .await?;
+                    filters.push(filter);
+                    Some(metadata)
+                }
+                None => None,
+            };
+
+            // A pinned asset is installed whatever the filters say, so it only has to exist
+            if let (Some(pin), true) = (pin, perform_checks) {
+                warning = repository.as_ref().and_then(repository_warning);
+                check_pinned_asset(github_api, &owner, &name, pin).await?;
             }
 
-            // Add it to the profile directly without any other checks
-            profile.push_mod(
-                name.trim().to_string(),
-                ModIdentifier::GitHubRepository(owner.clone(), name.clone()),
-                name.trim().to_string(),
-            );
+            // Skip release fetching and checks if they aren't needed
+            let need_checks = if perform_checks && pin.is_none() {
+                warning = repository.as_ref().and_then(repository_warning);
+                Some(match selected {
+                    // Check the selected asset rather than the one preferred by default
+                    Some(metadata) => metadata,
+                    None => match fetch_repo_releases_rest(
+                        github_api,
+                        &owner,
+                        &name,
+                        max_releases,
+                        source_archives,
+                    )
+                    .await
+                    {
+                        // Some repositories push tags without ever publishing a release
+                        Err(Error::Incompatible(check::Error::NoReleases)) => {
+                            let metadata =
+                                latest_tag_archive(github_api, &owner, &name, max_releases).await?;
+                            let tags_warning = format!("It has no releases, so the source code of its {} tag will be installed, which isn't packaged for installation", metadata.tag);
+                            warning = Some(match warning.take() {
+                                Some(warning) => format!("{warning}. {tags_warning}"),
+                                None => tags_warning,
+                            });
+                            metadata
+                        }
+                        result => result?,
+                    },
+                })
+            } else {
+                None
+            };
 
-            success_names.push(format!("{}/{}", owner, name));
+            let updated = github(
+                &(owner.as_str(), name.as_str()),
+                profile,
+                need_checks,
+                &AddOptions {
+                    filters,
+                    ..options.clone()
+                },
+            )
+            .await?;
+            if let Some(index) = pin.and_then(|_| find_added(profile, &owner, &name)) {
+                let mod_ = &mut profile.mods[index];
+                mod_.identifier = identifier.clone();
+                mod_.force_added = !perform_checks;
+            }
+            if let (false, Some(repository), Some(mod_)) =
+                (updated, repository, profile.mods.last_mut())
+            {
+                mod_.name.clone_from(&repository.name);
+                mod_.slug = Some(repository.name);
+                mod_.description = repository.description;
+            }
+            Ok::<_, Error>(if updated { Added::Updated } else { Added::New })
         }
+        .await;
+
+        outcomes.push(AddOutcome {
+            identifier,
+            name: display_name,
+            result,
+            warning,
+        });
     }
 
-    Ok((success_names, errors))
+    outcomes
 }
 
 /// Check if the repo of `repo_handler` exists, releases mods, and is compatible with `profile`.
-/// If so, add it to the `profile`.
+/// If so, add it to the `profile` with the `options`' filters.
 ///
-/// Returns the name of the repository to display to the user
+/// If the mod has already been added and `options.replace` is true, its filters are updated in place instead.
+/// Without `need_checks`, the mod is marked as [`force_added`](crate::config::structs::Mod::force_added).
+/// The options that only matter for fetching, like `perform_checks` and `asset`, are ignored.
+/// Returns whether an existing mod was updated
 pub async fn github(
     id: &(impl AsRef<str> + ToString, impl AsRef<str> + ToString),
     profile: &mut Profile,
     need_checks: Option<Metadata>,
-) -> Result<()> {
+    options: &AddOptions,
+) -> Result<bool> {
+    let AddOptions {
+        override_profile,
+        ref filters,
+        replace,
+        source_archives,
+        ..
+    } = *options;
     // Check if project has already been added
-    if profile.mods.iter().any(|mod_| {
-        mod_.name.eq_ignore_ascii_case(id.1.as_ref())
-            || matches!(
-                &mod_.identifier,
-                ModIdentifier::GitHubRepository(owner, repo) if owner == id.0.as_ref() && repo == id.1.as_ref(),
-            )
-    }) {
+    let existing = find_added(profile, id.0.as_ref(), id.1.as_ref());
+    if existing.is_some() && !replace {
         return Err(Error::AlreadyAdded);
     }
 
+    let force_added = need_checks.is_none();
     if let Some(download_files) = need_checks {
-        let applied_filters = profile.filters.clone();
+        // Check with the same filters the mod will be resolved with when upgrading
+        let mut candidate = Mod::new(
+            id.1.to_string(),
+            ModIdentifier::GitHubRepository(id.0.to_string(), id.1.to_string()),
+            filters.clone(),
+        );
+        candidate.override_filters = override_profile;
+        let applied_filters = candidate.effective_filters(&profile.filters);
 
         match check::select_latest(vec![download_files.clone()].iter(), applied_filters).await {
             Ok(_) => { /* compatible */ }
@@ -157,30 +477,241 @@ pub async fn github(
         }
     }
 
+    if let Some(index) = existing {
+        // Update the existing mod in place, keeping its name and pin
+        let mod_ = &mut profile.mods[index];
+        mod_.filters = filters.clone();
+        mod_.override_filters = override_profile;
+        mod_.source_archives = source_archives;
+        mod_.force_added = force_added;
+        return Ok(true);
+    }
+
     // Add it to the profile
     profile.push_mod(
         id.1.as_ref().trim().to_string(),
         ModIdentifier::GitHubRepository(id.0.to_string(), id.1.to_string()),
         id.1.as_ref().trim().to_string(),
+        override_profile,
+        filters.clone(),
     );
+    if let Some(mod_) = profile.mods.last_mut() {
+        mod_.source_archives = source_archives;
+        mod_.force_added = force_added;
+    }
+
+    Ok(false)
+}
+
+/// Pick the archive asset of `owner/repo`'s latest release selected by `selection`, fetched using `github_api`
+/// from at most `max_releases` of its most recent releases like when upgrading.
+/// Returns the filename filter to give the mod so it keeps picking the matching asset, and the asset's metadata.
+///
+/// A pattern is used as the filter as is, or escaped if it isn't a valid regex, and must match exactly one asset.
+/// An asset picked by index is filtered by its filename with any numbers in it allowed to change,
+/// since they're usually the mod's or SPT's version.
+pub async fn select_asset(
+    github_api: &Octocrab,
+    owner: &str,
+    repo: &str,
+    selection: &AssetSelection,
+    max_releases: usize,
+    source_archives: bool,
+) -> Result<(Filter, Metadata)> {
+    let releases = fetch_gh_releases(github_api, owner, repo, max_releases).await?;
+    let has_releases = !releases.is_empty();
+    let candidates = from_gh_releases(releases, source_archives.then_some(repo));
+    let Some((latest, _)) = candidates.first() else {
+        return Err(if has_releases {
+            check::Error::NoArchiveAssets
+        } else {
+            check::Error::NoReleases
+        }
+        .into());
+    };
+    let tag = latest.tag.clone();
+    let mut assets = candidates
+        .into_iter()
+        .map(|(metadata, _)| metadata)
+        .filter(|metadata| metadata.tag == tag)
+        .collect::<Vec<_>>();
+    let names = || assets.iter().map(|asset| asset.filename.clone()).collect();
+
+    let (regex, index) = match selection {
+        AssetSelection::Pattern(pattern) => {
+            let regex = Regex::new(pattern)
+                .or_else(|_| Regex::new(&regex::escape(pattern)))
+                .map_err(check::Error::from)?;
+            let matches = assets
+                .iter()
+                .enumerate()
+                .filter(|(_, asset)| regex.is_match(&asset.filename))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            match matches.as_slice() {
+                [index] => (regex, *index),
+                [] => {
+                    return Err(Error::NoMatchingAsset {
+                        pattern: pattern.clone(),
+                        tag,
+                        assets: names(),
+                    })
+                }
+                _ => {
+                    return Err(Error::AmbiguousAsset {
+                        pattern: pattern.clone(),
+                        tag,
+                        matches: matches
+                            .into_iter()
+                            .map(|index| assets[index].filename.clone())
+                            .collect(),
+                    })
+                }
+            }
+        }
+        &AssetSelection::Index(index) => {
+            let Some(asset) = index.checked_sub(1).and_then(|i| assets.get(i)) else {
+                return Err(Error::NoAssetAtIndex {
+                    index,
+                    tag,
+                    assets: names(),
+                });
+            };
+            let regex = filename_regex(&asset.filename);
+            // Fall back to the exact filename if the asset's siblings only differ in numbers
+            let regex = if assets
+                .iter()
+                .filter(|asset| regex.is_match(&asset.filename))
+                .count()
+                == 1
+            {
+                regex
+            } else {
+                exact_filename_regex(&asset.filename)
+            };
+            (regex, index - 1)
+        }
+    };
+
+    debug!(SCOPE = "libarov::add", owner, repo, tag = tag.as_str(), filename = assets[index].filename.as_str(), pattern = regex.as_str(); "selected asset");
+    Ok((
+        Filter::Filename(regex.as_str().to_owned()),
+        assets.swap_remove(index),
+    ))
+}
+
+/// A regex matching `filename` with any of the numbers in it changed
+fn filename_regex(filename: &str) -> Regex {
+    let mut pattern = "^".to_owned();
+    let mut rest = filename;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        let end = rest[start..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest.len(), |len| start + len);
+        pattern.push_str(&regex::escape(&rest[..start]));
+        pattern.push_str(r"\d+");
+        rest = &rest[end..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    Regex::new(&pattern).expect("escaped filenames are valid regexes")
+}
+
+/// A regex matching exactly `filename`
+fn exact_filename_regex(filename: &str) -> Regex {
+    Regex::new(&format!("^{}$", regex::escape(filename)))
+        .expect("escaped filenames are valid regexes")
+}
+
+/// Check that `owner/repo` has a release asset with the ID `pin`, failing with [`Error::NoPinnedAsset`] if it doesn't
+async fn check_pinned_asset(
+    github_api: &Octocrab,
+    owner: &str,
+    repo: &str,
+    pin: i32,
+) -> Result<()> {
+    match host_limit::send_retrying(GITHUB_API_HOST, || async {
+        github_api
+            .repos(owner, repo)
+            .release_assets()
+            .get(pin as u64)
+            .await
+    })
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => {
+            Err(Error::NoPinnedAsset(pin))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Get the repository `owner/repo`, failing with [`Error::DoesNotExist`] if there isn't one
+async fn get_repository(github_api: &Octocrab, owner: &str, repo: &str) -> Result<Repository> {
+    match host_limit::send_retrying(GITHUB_API_HOST, || async {
+        github_api.repos(owner, repo).get().await
+    })
+    .await
+    {
+        Ok(repository) => Ok(repository),
+        Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => {
+            Err(Error::DoesNotExist)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// A warning if `repository` won't receive updates because it's archived or disabled
+fn repository_warning(repository: &Repository) -> Option<String> {
+    if repository.archived == Some(true) {
+        Some("This repository is archived and won't receive updates".to_owned())
+    } else if repository.disabled == Some(true) {
+        Some("This repository is disabled and won't receive updates".to_owned())
+    } else {
+        None
+    }
+}
 
-    Ok(())
+/// The source code archive of the latest of `owner/repo`'s tags, fetched using `github_api`,
+/// for repositories that push tags without publishing releases
+///
+/// Fails with [`check::Error::NoReleases`] if there are no tags either.
+async fn latest_tag_archive(
+    github_api: &Octocrab,
+    owner: &str,
+    repo: &str,
+    max_releases: usize,
+) -> Result<Metadata> {
+    let tags = fetch_gh_tags(github_api, owner, repo, max_releases).await?;
+    let (metadata, _) = from_gh_tags(tags, repo)
+        .into_iter()
+        .next()
+        .ok_or(check::Error::NoReleases)?;
+    debug!(SCOPE = "libarov::add", owner, repo, tag = metadata.tag.as_str(); "no releases, using the source archive of the latest tag");
+    Ok(metadata)
 }
 
 /// Fetch repository releases using REST API instead of GraphQL to avoid authentication requirement
-async fn fetch_repo_releases_rest(owner: &str, repo: &str) -> Result<Metadata> {
-    // Get all releases for the repository using REST API
-    let page = GITHUB_API
-        .repos(owner, repo)
-        .releases()
-        .list()
-        .per_page(10) // Get more releases per page
-        .send()
-        .await?;
+///
+/// If `source_archives` is true, releases without archive assets are represented by their source code archive.
+/// Fails with [`check::Error::NoReleases`] if there are no releases, see [`latest_tag_archive`].
+async fn fetch_repo_releases_rest(
+    github_api: &Octocrab,
+    owner: &str,
+    repo: &str,
+    max_releases: usize,
+    source_archives: bool,
+) -> Result<Metadata> {
+    // Get the most recent releases for the repository using REST API
+    let releases = fetch_gh_releases(github_api, owner, repo, max_releases).await?;
+    if releases.is_empty() {
+        return Err(check::Error::NoReleases.into());
+    }
 
     let mut all_metadata = Vec::new();
 
-    for release in page.items {
+    for release in releases {
         // TODO: check both release name and assets name for SPT version (here: game_versions)
         // Release.name (is Some() here)
         // Release.assets.<index>(Asset).name (is NOT Some() here)
@@ -190,7 +721,8 @@ async fn fetch_repo_releases_rest(owner: &str, repo: &str) -> Result<Metadata> {
         }
 
         // Get release assets for this release
-        let assets_page = GITHUB_API
+        let _permit = host_limit::acquire(GITHUB_API_HOST).await;
+        let assets_page = github_api
             .repos(owner, repo)
             .releases()
             .assets(release.id.0)
@@ -199,8 +731,10 @@ async fn fetch_repo_releases_rest(owner: &str, repo: &str) -> Result<Metadata> {
             .await?;
 
         // Convert each asset to Metadata
+        let mut has_archives = false;
         for asset in assets_page.items {
-            if asset.name.ends_with(".zip") || asset.name.ends_with(".7z") {
+            if is_supported_archive(&asset.name) {
+                has_archives = true;
                 found_versions.push(extract_versions(asset.name.as_str()));
                 let game_versions = if found_versions.is_empty() {
                     None
@@ -220,37 +754,229 @@ async fn fetch_repo_releases_rest(owner: &str, repo: &str) -> Result<Metadata> {
                         Some(filtered_versions)
                     }
                 };
-                // found candidate game versions (pre cutoff / may be empty)
-
-                // DEV: temporarily cut off patch version here too
-                let game_versions = game_versions
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|v| {
-                        if let Some(pos) = v.rfind('.') {
-                            v[..pos].to_string()
-                        } else {
-                            v
-                        }
-                    })
-                    .collect::<Vec<_>>();
-                // after trimming patch segments
+                // Kept with their patch like when upgrading, see `game_version_matches`
+                let game_versions = game_versions.unwrap_or_default();
 
-                all_metadata.push(Metadata::new(
+                let mut metadata = Metadata::new(
                     release.name.as_ref().unwrap_or(&release.tag_name).clone(),
+                    release.tag_name.clone(),
                     release.body.as_ref().cloned().unwrap_or_default(),
                     asset.name.clone(),
                     release.created_at.as_ref().cloned().unwrap_or_default(),
                     Some(game_versions),
-                ));
+                );
+                metadata.channel = gh_release_channel(&release);
+                all_metadata.push(metadata);
             }
         }
+        if !has_archives && source_archives {
+            all_metadata.extend(source_archive(&release, repo).map(|(metadata, _)| metadata));
+        }
     }
     // Assets fetched
+    if all_metadata.is_empty() {
+        return Err(check::Error::NoArchiveAssets.into());
+    }
 
-    all_metadata.sort_by(|a, b| b.release_date.cmp(&a.release_date));
+    all_metadata.sort_by(cmp_preference);
     let latest_release = all_metadata.first().cloned();
 
     // TODO: change this...
     Ok(latest_release.or(None).ok_or(Error::DoesNotExist)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn test_profile() -> Profile {
+        Profile::new(
+            "Test".to_owned(),
+            PathBuf::new(),
+            vec!["3.11".to_owned()],
+            true,
+        )
+    }
+
+    #[test]
+    fn parse_id_trims_slashes_and_whitespace() {
+        assert_eq!(
+            parse_id(" Solarint/SAIN/ \n".to_owned(), &BTreeMap::new()),
+            Ok(ModIdentifier::GitHubRepository(
+                "Solarint".to_owned(),
+                "SAIN".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_id_rejects_invalid() {
+        for id in ["", "a/b/c", "owner/", "/repo"] {
+            assert_eq!(
+                parse_id(id.to_owned(), &BTreeMap::new()),
+                Err(ParseError::Invalid(id.to_owned()))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_id_expands_aliases() {
+        let aliases = BTreeMap::from([("sain".to_owned(), "Solarint/SAIN".to_owned())]);
+        assert_eq!(
+            parse_id("SAIN".to_owned(), &aliases),
+            Ok(ModIdentifier::GitHubRepository(
+                "Solarint".to_owned(),
+                "SAIN".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse_id("sian".to_owned(), &aliases),
+            Err(ParseError::UnknownAlias {
+                name: "sian".to_owned(),
+                suggestion: Some("sain".to_owned())
+            })
+        );
+        assert_eq!(
+            parse_id("waypoints".to_owned(), &aliases),
+            Err(ParseError::UnknownAlias {
+                name: "waypoints".to_owned(),
+                suggestion: None
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn already_added_ignores_case() {
+        let mut profile = test_profile();
+
+        github(
+            &("DrakiaXYZ", "SPT-Waypoints"),
+            &mut profile,
+            None,
+            &AddOptions::default(),
+        )
+        .await
+        .unwrap();
+        // Names get replaced by the repository's display name, so the identifier must match too
+        profile.mods[0].name = "Waypoints".to_owned();
+        assert!(matches!(
+            github(
+                &("drakiaxyz", "spt-waypoints"),
+                &mut profile,
+                None,
+                &AddOptions::default()
+            )
+            .await,
+            Err(Error::AlreadyAdded)
+        ));
+        assert_eq!(profile.mods.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn replace_updates_filters_in_place() {
+        let mut profile = test_profile();
+        let filters = vec![Filter::ReleaseChannel(ReleaseChannel::Beta)];
+
+        assert!(!github(
+            &("Solarint", "SAIN"),
+            &mut profile,
+            None,
+            &AddOptions::default()
+        )
+        .await
+        .unwrap());
+        assert!(github(
+            &("solarint", "sain"),
+            &mut profile,
+            None,
+            &AddOptions {
+                override_profile: true,
+                filters,
+                replace: true,
+                ..AddOptions::default()
+            }
+        )
+        .await
+        .unwrap());
+        assert_eq!(profile.mods.len(), 1);
+        assert!(matches!(
+            profile.mods[0].filters[..],
+            [Filter::ReleaseChannel(ReleaseChannel::Beta)]
+        ));
+        assert!(profile.mods[0].override_filters);
+    }
+
+    #[test]
+    fn filename_regex_allows_numbers_to_change() {
+        let regex = filename_regex("SAIN-3.2.0-SPT-3.11.zip");
+
+        assert!(regex.is_match("SAIN-3.2.0-SPT-3.11.zip"));
+        assert!(regex.is_match("SAIN-3.10.1-SPT-4.0.zip"));
+        assert!(!regex.is_match("SAIN-3.2.0-SPT-3.11.7z"));
+        assert!(!regex.is_match("SAIN-Lite-3.2.0-SPT-3.11.zip"));
+        assert!(!regex.is_match("SAIN-3.2.0-beta-SPT-3.11.zip"));
+    }
+
+    #[tokio::test]
+    async fn add_batch_returns_outcome_per_identifier() {
+        let mut profile = test_profile();
+        let sain = ModIdentifier::GitHubRepository("Solarint".to_owned(), "SAIN".to_owned());
+        // Checks are skipped, so the client never sends a request
+        let outcomes = add_batch(
+            &Octocrab::default(),
+            &mut profile,
+            vec![sain.clone(), sain.clone()],
+            &AddOptions {
+                perform_checks: false,
+                fetch_metadata: false,
+                ..AddOptions::default()
+            },
+        )
+        .await;
+
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].identifier, sain);
+        assert_eq!(outcomes[0].name, "Solarint/SAIN");
+        assert!(matches!(outcomes[0].result, Ok(Added::New)));
+        assert!(matches!(outcomes[1].result, Err(Error::AlreadyAdded)));
+    }
+
+    #[tokio::test]
+    async fn mod_channel_filter_takes_precedence() {
+        let mut profile = test_profile();
+        profile.filters = vec![Filter::ReleaseChannel(ReleaseChannel::Release)];
+        let mut prerelease = Metadata::new(
+            "Nightly".to_owned(),
+            "v2.0.0-nightly".to_owned(),
+            String::new(),
+            "Mod-nightly.zip".to_owned(),
+            Default::default(),
+            None,
+        );
+        prerelease.channel = ReleaseChannel::Beta;
+
+        assert!(matches!(
+            github(
+                &("Solarint", "SAIN"),
+                &mut profile,
+                Some(prerelease.clone()),
+                &AddOptions::default()
+            )
+            .await,
+            Err(Error::Incompatible(_))
+        ));
+        github(
+            &("Solarint", "SAIN"),
+            &mut profile,
+            Some(prerelease),
+            &AddOptions {
+                filters: vec![Filter::Prerelease],
+                ..AddOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(profile.mods.len(), 1);
+    }
+}
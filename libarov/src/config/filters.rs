@@ -2,7 +2,7 @@ use crate::iter_ext::IterExt as _;
 use derive_more::derive::Display;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Debug, Display, Clone)]
+#[derive(Deserialize, Serialize, Debug, Display, Clone, PartialEq, Eq)]
 pub enum Filter {
     /// Selects files strictly compatible with the versions specified
     #[display("Game Version ({})", _0.iter().display(", "))]
@@ -17,6 +17,10 @@ pub enum Filter {
     #[display("Release Channel ({_0})")]
     ReleaseChannel(ReleaseChannel),
 
+    /// Selects files from prereleases only
+    #[display("Prerelease")]
+    Prerelease,
+
     /// Selects the files with filenames matching the provided regex
     #[display("Filename ({_0})")]
     Filename(String),
@@ -28,6 +32,84 @@ pub enum Filter {
     /// Selects files with descriptions matching the provided regex
     #[display("Description ({_0})")]
     Description(String),
+
+    /// Selects files that pass at least one of the filters provided
+    #[display("Any ({})", _0.iter().display(" | "))]
+    Any(Vec<Filter>),
+}
+
+impl Filter {
+    /// Whether this filter selects files by their release channel
+    ///
+    /// A mod's channel filters take precedence over the profile's,
+    /// so the profile's are dropped when the two are combined.
+    pub fn is_channel(&self) -> bool {
+        matches!(self, Self::ReleaseChannel(_) | Self::Prerelease)
+    }
+}
+
+/// Combine `filters`, e.g. a profile's filters extended with a mod's, into ones that don't contradict each other
+///
+/// - Game version filters are merged into one that selects files compatible with any of their versions.
+///   If any of them is strict, the merged filter is strict too, otherwise it's minor.
+/// - Release channel filters are merged into the most stable of their channels, since a file has to pass all of them.
+/// - Other duplicate filters, such as the same regex twice, are removed.
+///
+/// The merged filters take the place of the first filter they were merged from.
+pub fn normalize_filters(filters: Vec<Filter>) -> Vec<Filter> {
+    let mut normalized = Vec::<Filter>::with_capacity(filters.len());
+    for filter in filters {
+        let (strict, versions) = match filter {
+            Filter::GameVersionStrict(versions) => (true, versions),
+            Filter::GameVersionMinor(versions) => (false, versions),
+            Filter::ReleaseChannel(channel) => {
+                match normalized
+                    .iter_mut()
+                    .find(|filter| matches!(filter, Filter::ReleaseChannel(_)))
+                {
+                    Some(Filter::ReleaseChannel(existing)) => *existing = channel.min(*existing),
+                    _ => normalized.push(Filter::ReleaseChannel(channel)),
+                }
+                continue;
+            }
+            filter => {
+                if !normalized.contains(&filter) {
+                    normalized.push(filter);
+                }
+                continue;
+            }
+        };
+
+        match normalized.iter_mut().find(|filter| {
+            matches!(
+                filter,
+                Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_)
+            )
+        }) {
+            Some(existing) => {
+                let existing_strict = matches!(existing, Filter::GameVersionStrict(_));
+                let (Filter::GameVersionStrict(merged) | Filter::GameVersionMinor(merged)) =
+                    existing
+                else {
+                    unreachable!()
+                };
+                let mut merged = std::mem::take(merged);
+                for version in versions {
+                    if !merged.contains(&version) {
+                        merged.push(version);
+                    }
+                }
+                *existing = if strict || existing_strict {
+                    Filter::GameVersionStrict(merged)
+                } else {
+                    Filter::GameVersionMinor(merged)
+                };
+            }
+            None if strict => normalized.push(Filter::GameVersionStrict(versions)),
+            None => normalized.push(Filter::GameVersionMinor(versions)),
+        }
+    }
+    normalized
 }
 
 pub trait ProfileParameters {
@@ -35,6 +117,8 @@ pub trait ProfileParameters {
     fn game_versions(&self) -> Option<&Vec<String>>;
     /// Get the game versions present, if self has `GameVersionStrict` or `GameVersionMinor`
     fn game_versions_mut(&mut self) -> Option<&mut Vec<String>>;
+    /// Set the game versions, adding a `GameVersionStrict` filter if self doesn't have a game version filter
+    fn set_game_versions(&mut self, versions: Vec<String>);
 }
 
 impl ProfileParameters for Vec<Filter> {
@@ -53,6 +137,14 @@ impl ProfileParameters for Vec<Filter> {
             _ => None,
         })
     }
+
+    fn set_game_versions(&mut self, versions: Vec<String>) {
+        if let Some(existing) = self.game_versions_mut() {
+            *existing = versions;
+        } else {
+            self.insert(0, Filter::GameVersionStrict(versions));
+        }
+    }
 }
 
 // impl PartialEq for Filter {
@@ -61,7 +153,20 @@ impl ProfileParameters for Vec<Filter> {
 //     }
 // }
 
-#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+/// Ordered from most to least stable
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    Display,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    clap::ValueEnum,
+)]
 pub enum ReleaseChannel {
     Release,
     Beta,
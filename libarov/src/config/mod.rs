@@ -1,10 +1,11 @@
 pub mod filters;
 pub mod structs;
+pub mod validate;
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use std::{
     fs::{create_dir_all, File},
-    io::{BufReader, Result},
+    io::{self, BufReader, Result},
     path::Path,
 };
 
@@ -20,6 +21,7 @@ pub fn read_config(path: impl AsRef<Path>) -> Result<structs::Config> {
 
     let mut config: structs::Config = serde_json::from_reader(config_file)?;
     info!(SCOPE = "libarov::config"; "config deserialised");
+    config.resolve_active_profile();
 
     // config
     //     .profiles
@@ -29,10 +31,35 @@ pub fn read_config(path: impl AsRef<Path>) -> Result<structs::Config> {
     Ok(config)
 }
 
+/// Serialise `config` to the config file at `path`
+///
+/// If [`validate_on_save`](structs::Config::validate_on_save) is set and the config has [problems](validate::Problem),
+/// nothing is written and the error wraps a [`validate::InvalidConfig`] listing them.
 pub fn write_config(path: impl AsRef<Path>, config: &structs::Config) -> Result<()> {
     info!(SCOPE = "libarov::config", path:debug = &path.as_ref(); "writing config");
+    if config.validate_on_save {
+        let problems = config.problems();
+        if !problems.is_empty() {
+            warn!(SCOPE = "libarov::config", problems = problems.len(); "config is invalid, not writing it");
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                validate::InvalidConfig(problems),
+            ));
+        }
+    }
     let config_file = File::create(path)?;
 
+    // Profiles' GitHub tokens shouldn't be readable by other users
+    #[cfg(unix)]
+    if config
+        .profiles
+        .iter()
+        .any(|profile| profile.github_token.is_some())
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        config_file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
     serde_json::to_writer_pretty(config_file, config)?;
 
     info!(SCOPE = "libarov::config"; "config write complete");
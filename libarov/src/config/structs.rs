@@ -1,13 +1,21 @@
-use super::filters::Filter;
+use super::filters::{normalize_filters, Filter, ProfileParameters as _};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 #[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct Config {
+    /// The index of the active profile, kept in sync with [`Config::active_profile_name`]
     #[serde(skip_serializing_if = "is_zero")]
     #[serde(default)]
     pub active_profile: usize,
 
+    /// The name of the active profile, which takes precedence over the index
+    /// so that removing or reordering profiles doesn't change which one is active
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub active_profile_name: Option<String>,
+
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub profiles: Vec<Profile>,
@@ -19,12 +27,87 @@ pub struct Config {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub modpacks: Vec<Modpack>,
+
+    /// The number of recent releases to scan per mod when resolving downloads
+    ///
+    /// Scanning more releases finds files for older SPT versions,
+    /// but every 100 releases costs another GitHub API request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_releases: Option<usize>,
+
+    /// The size of the buffer downloads are written through, in KiB
+    ///
+    /// Defaults to [`crate::upgrade::DEFAULT_DOWNLOAD_BUFFER_SIZE`], regardless of how large the file is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub download_buffer_kib: Option<usize>,
+
+    /// Don't check whether a newer version of tarium is available
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub disable_update_check: bool,
+
+    /// Check the config for problems before saving it, and refuse to save it if there are any,
+    /// see [`super::validate`]
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub validate_on_save: bool,
+
+    /// Keep mods in the order they were added instead of sorting them alphabetically
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub keep_mod_order: bool,
+
+    /// Major lines of SPT to recognise in addition to [`crate::SPT_MAJOR_VERSIONS`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub extra_spt_major_versions: Vec<u64>,
+
+    /// Short names that expand to an `owner/repo` identifier when adding mods, e.g. `sain` to `Solarint/SAIN`
+    ///
+    /// Names are stored in lowercase and matched ignoring case.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+
+    /// The output directory that was picked or set most recently, which the folder picker starts from next time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub last_output_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// Make the profile at `index` the active one
+    pub fn set_active_profile(&mut self, index: usize) {
+        self.active_profile = index;
+        self.active_profile_name = self.profiles.get(index).map(|profile| profile.name.clone());
+    }
+
+    /// Point [`Config::active_profile`] at the profile named [`Config::active_profile_name`],
+    /// or record the name of the profile at the index for configs from before names were tracked
+    ///
+    /// If no profile has that name anymore, the profile at the index becomes the active one.
+    pub fn resolve_active_profile(&mut self) {
+        match self.active_profile_name.as_deref().and_then(|name| {
+            self.profiles
+                .iter()
+                .position(|profile| profile.name.eq_ignore_ascii_case(name))
+        }) {
+            Some(index) => self.active_profile = index,
+            None => self.set_active_profile(self.active_profile),
+        }
+    }
 }
 
 const fn is_zero(n: &usize) -> bool {
     *n == 0
 }
 
+const fn is_false(b: &bool) -> bool {
+    !*b
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Modpack {
     pub name: String,
@@ -43,6 +126,11 @@ pub enum ModpackIdentifier {
 pub struct Profile {
     pub name: String,
 
+    /// A note on what the profile is for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub description: Option<String>,
+
     /// The directory to download mod files to
     pub output_dir: PathBuf,
 
@@ -50,6 +138,35 @@ pub struct Profile {
     #[serde(default)]
     pub filters: Vec<Filter>,
 
+    /// Mods to install first, in this order, so that files from later mods overwrite conflicting ones.
+    /// Entries are mod names or `owner/repo` identifiers.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub install_order: Vec<String>,
+
+    /// Where mod files are installed to within the output directory
+    #[serde(skip_serializing_if = "InstallDirs::is_default")]
+    #[serde(default)]
+    pub install_dirs: InstallDirs,
+
+    /// When the profile's mods were last downloaded and installed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub last_upgrade: Option<DateTime<Utc>>,
+
+    /// The `owner/repo` identifiers of the mods that failed in the last upgrade, to retry with `upgrade --resume`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub failed_mods: Vec<String>,
+
+    /// A GitHub token to send this profile's requests with instead of the global one,
+    /// e.g. for private repositories of an organisation only this profile uses
+    ///
+    /// It's stored in plain text, so anyone who can read the config file can use it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub github_token: Option<String>,
+
     pub mods: Vec<Mod>,
     // Kept for backwards compatibility reasons (i.e. migrating from a v4 config)
     // #[serde(skip_serializing)]
@@ -58,26 +175,15 @@ pub struct Profile {
 
 impl Profile {
     /// A simple constructor that automatically deals with converting to filters
+    ///
+    /// The `game_versions` are kept as precise as they're given, trim them with [`crate::trim_patch`]
+    /// to match every patch of their minor versions.
     pub fn new(
         name: String,
         output_dir: PathBuf,
         game_versions: Vec<String>,
         strict: bool,
     ) -> Self {
-        // cutoff patch segment from supplied versions (remove patch component)
-        let game_versions = game_versions
-            .into_iter()
-            .map(|v| {
-                // cutoff patch segment by removing patch version at last dot
-                if let Some(pos) = v.rfind('.') {
-                    // need String here
-                    v[..pos].to_string()
-                } else {
-                    v
-                }
-            })
-            .collect::<Vec<_>>();
-
         let filters = vec![if strict {
             Filter::GameVersionStrict(game_versions)
         } else {
@@ -86,30 +192,90 @@ impl Profile {
 
         Self {
             name,
+            description: None,
             output_dir,
             filters,
+            install_order: vec![],
+            install_dirs: InstallDirs::default(),
+            last_upgrade: None,
+            failed_mods: vec![],
+            github_token: None,
             mods: vec![],
             // game_version: None,
         }
     }
 
+    /// Make sure the profile filters by at least one game version, otherwise many commands can't do anything useful
+    ///
+    /// If it doesn't, it is set to strictly filter by the latest SPT version and `true` is returned.
+    pub fn ensure_game_versions(&mut self) -> bool {
+        if self
+            .filters
+            .game_versions()
+            .is_some_and(|versions| !versions.is_empty())
+        {
+            return false;
+        }
+        let latest = crate::SPT_VERSIONS[0];
+        let latest = latest.rsplit_once('.').map_or(latest, |(minor, _)| minor);
+        self.filters.set_game_versions(vec![latest.to_owned()]);
+        true
+    }
+
     pub fn push_mod(
         &mut self,
         name: String,
         identifier: ModIdentifier,
         slug: String,
-        // filters: Vec<Filter>,
+        override_filters: bool,
+        filters: Vec<Filter>,
     ) {
         self.mods.push(Mod {
             name,
             slug: Some(slug),
+            description: None,
             identifier,
-            // filters,
-            // check_game_version: None,
+            filters,
+            override_filters,
+            source_archives: false,
+            force_added: false,
+            include_paths: Vec::new(),
+            exclude_paths: Vec::new(),
+            installed_tag: None,
+            files: Vec::new(),
         })
     }
 }
 
+/// Where mod files are installed to, relative to the output directory unless absolute.
+/// The defaults match a standard SPT install.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct InstallDirs {
+    /// Where client plugins go, `BepInEx/plugins` by default
+    pub plugins: PathBuf,
+    /// Where server mods go, `user/mods` by default
+    pub server_mods: PathBuf,
+    /// Where archives are kept after being installed, `MODS` by default
+    pub archive_store: PathBuf,
+}
+
+impl Default for InstallDirs {
+    fn default() -> Self {
+        Self {
+            plugins: PathBuf::from("BepInEx").join("plugins"),
+            server_mods: PathBuf::from("user").join("mods"),
+            archive_store: PathBuf::from("MODS"),
+        }
+    }
+}
+
+impl InstallDirs {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Mod {
     pub name: String,
@@ -119,14 +285,52 @@ pub struct Mod {
     // since the slug field didn't exist in older ferium versions
     #[serde(skip_serializing_if = "Option::is_none")]
     pub slug: Option<String>,
+
+    /// The repository's description, fetched when the mod was added or listed verbosely
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub description: Option<String>,
+
     // Custom filters that apply only for this mod
-    // #[serde(skip_serializing_if = "Vec::is_empty")]
-    // #[serde(default)]
-    // pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+
     // Whether the filters specified above replace or apply with the profile's filters
-    // #[serde(skip_serializing_if = "is_false")]
-    // #[serde(default)]
-    // pub override_filters: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub override_filters: bool,
+
+    /// Whether releases without archive assets fall back to their source code archive
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub source_archives: bool,
+
+    /// Whether the mod was added without checking it's compatible with the profile,
+    /// until it's resolved successfully during an upgrade
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub force_added: bool,
+
+    /// Globs of the paths in the mod's archive to install, everything is installed if empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub include_paths: Vec<String>,
+
+    /// Globs of the paths in the mod's archive not to install, even if they match `include_paths`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
+
+    /// The tag of the release that was last installed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub installed_tag: Option<String>,
+
+    /// The files installed from the mod's archive when it was last installed, relative to the output directory
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
     // Kept for backwards compatibility reasons
     // #[serde(skip_serializing)]
     // check_game_version: Option<bool>,
@@ -137,10 +341,58 @@ impl Mod {
         Self {
             name,
             slug: None,
+            description: None,
             identifier,
-            // filters,
-            // check_game_version: None,
+            filters,
+            override_filters: false,
+            source_archives: false,
+            force_added: false,
+            include_paths: Vec::new(),
+            exclude_paths: Vec::new(),
+            installed_tag: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// The filters that apply to this mod in a profile with `profile_filters`, combined with [`normalize_filters`]
+    ///
+    /// If the mod overrides the profile's filters only its own apply, otherwise they're added to the profile's.
+    /// The mod's release channel filters take precedence over the profile's, even when they're less stable.
+    pub fn effective_filters(&self, profile_filters: &[Filter]) -> Vec<Filter> {
+        if self.override_filters {
+            return normalize_filters(self.filters.clone());
         }
+        let has_channel = self.filters.iter().any(Filter::is_channel);
+        normalize_filters(
+            profile_filters
+                .iter()
+                .filter(|filter| !(has_channel && filter.is_channel()))
+                .chain(&self.filters)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// The `owner/repo` identifier of the mod's repository
+    pub fn repository(&self) -> String {
+        let (ModIdentifier::GitHubRepository(owner, repo)
+        | ModIdentifier::PinnedGitHubRepository((owner, repo), _)) = &self.identifier;
+        format!("{owner}/{repo}")
+    }
+
+    /// Whether `name` refers to this mod by its name, `owner/repo` identifier, or slug, ignoring case
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.repository().eq_ignore_ascii_case(name)
+            || self
+                .slug
+                .as_ref()
+                .is_some_and(|slug| slug.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the mod has been installed before, going by its installed release or tracked files
+    pub fn is_installed(&self) -> bool {
+        self.installed_tag.is_some() || !self.files.is_empty()
     }
 }
 
@@ -0,0 +1,173 @@
+//! Checks for a config that would be left in a bad state if it were saved
+//!
+//! The rest of the code assumes these hold without checking them,
+//! so [`super::write_config`] refuses to save a config that breaks them if [`Config::validate_on_save`] is set.
+
+use super::{
+    filters::Filter,
+    structs::{Config, ModIdentifier, Profile},
+};
+use crate::iter_ext::IterExt as _;
+use std::{collections::HashSet, path::PathBuf};
+
+/// Something wrong with a config
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    #[error("There are several profiles named '{0}'")]
+    DuplicateProfileName(String),
+    #[error("The active profile is number {}, but there are only {count} profiles", index + 1)]
+    ActiveProfileOutOfRange { index: usize, count: usize },
+    #[error("The output directory of profile '{profile}' isn't an absolute path: {}", dir.display())]
+    RelativeOutputDir { profile: String, dir: PathBuf },
+    #[error("Profile '{profile}' has an empty {filter} filter, which no file can pass")]
+    EmptyFilter { profile: String, filter: String },
+    #[error("'{name}' has been added to profile '{profile}' more than once")]
+    DuplicateMod { profile: String, name: String },
+}
+
+/// The problems that stopped a config from being saved
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Refusing to save the config, it has these problems:\n  {}", _0.iter().display("\n  "))]
+pub struct InvalidConfig(pub Vec<Problem>);
+
+impl Config {
+    /// Find the problems with this config, in the order of its profiles
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        if !self.profiles.is_empty() && self.active_profile >= self.profiles.len() {
+            problems.push(Problem::ActiveProfileOutOfRange {
+                index: self.active_profile,
+                count: self.profiles.len(),
+            });
+        }
+
+        let mut names = HashSet::new();
+        for profile in &self.profiles {
+            if !names.insert(profile.name.to_lowercase()) {
+                problems.push(Problem::DuplicateProfileName(profile.name.clone()));
+            }
+            problems.extend(profile.problems());
+        }
+
+        problems
+    }
+}
+
+impl Profile {
+    /// Find the problems with this profile, other than those with the config it's in
+    fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        if !self.output_dir.is_absolute() {
+            problems.push(Problem::RelativeOutputDir {
+                profile: self.name.clone(),
+                dir: self.output_dir.clone(),
+            });
+        }
+
+        for filter in self
+            .filters
+            .iter()
+            .chain(self.mods.iter().flat_map(|mod_| &mod_.filters))
+            .filter(|filter| is_empty(filter))
+        {
+            problems.push(Problem::EmptyFilter {
+                profile: self.name.clone(),
+                filter: filter.to_string(),
+            });
+        }
+
+        let mut identifiers = HashSet::new();
+        for mod_ in &self.mods {
+            let (ModIdentifier::GitHubRepository(owner, repo)
+            | ModIdentifier::PinnedGitHubRepository((owner, repo), _)) = &mod_.identifier;
+            if !identifiers.insert(format!("{owner}/{repo}").to_lowercase()) {
+                problems.push(Problem::DuplicateMod {
+                    profile: self.name.clone(),
+                    name: mod_.name.clone(),
+                });
+            }
+        }
+
+        problems
+    }
+}
+
+/// Whether `filter` has nothing to select files with, so it rejects every file
+fn is_empty(filter: &Filter) -> bool {
+    match filter {
+        Filter::GameVersionStrict(versions) | Filter::GameVersionMinor(versions) => {
+            versions.is_empty()
+        }
+        Filter::Any(filters) => filters.is_empty() || filters.iter().all(is_empty),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::structs::Mod;
+
+    fn profile(name: &str) -> Profile {
+        Profile::new(
+            name.to_owned(),
+            PathBuf::from(if cfg!(windows) { r"C:\SPT" } else { "/SPT" }),
+            vec!["3.11".to_owned()],
+            true,
+        )
+    }
+
+    fn sain() -> Mod {
+        Mod::new(
+            "SAIN".to_owned(),
+            ModIdentifier::GitHubRepository("Solarint".to_owned(), "SAIN".to_owned()),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn valid_config_has_no_problems() {
+        let mut config = Config::default();
+        assert!(config.problems().is_empty());
+
+        config.profiles = vec![profile("Main"), profile("Testing")];
+        config.profiles[0].mods.push(sain());
+        config.set_active_profile(1);
+        assert!(config.problems().is_empty());
+    }
+
+    #[test]
+    fn finds_every_problem() {
+        let mut config = Config::default();
+        let mut main = profile("Main");
+        main.output_dir = PathBuf::from("SPT");
+        main.filters.push(Filter::GameVersionMinor(vec![]));
+        let mut duplicate = sain();
+        duplicate.name = "sain".to_owned();
+        main.mods = vec![sain(), duplicate];
+        config.profiles = vec![main, profile("main")];
+        config.active_profile = 2;
+
+        assert_eq!(
+            config.problems(),
+            [
+                Problem::ActiveProfileOutOfRange { index: 2, count: 2 },
+                Problem::RelativeOutputDir {
+                    profile: "Main".to_owned(),
+                    dir: PathBuf::from("SPT"),
+                },
+                Problem::EmptyFilter {
+                    profile: "Main".to_owned(),
+                    filter: Filter::GameVersionMinor(vec![]).to_string(),
+                },
+                Problem::DuplicateMod {
+                    profile: "Main".to_owned(),
+                    name: "sain".to_owned(),
+                },
+                Problem::DuplicateProfileName("main".to_owned()),
+            ]
+        );
+    }
+}
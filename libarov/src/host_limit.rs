@@ -0,0 +1,226 @@
+//! Limits on how many requests are sent to a single host at once
+//!
+//! GitHub's secondary rate limits respond with a 403 to clients that send too many concurrent requests,
+//! regardless of how many requests they have left, so the API and download hosts are capped separately
+//! from the overall number of parallel tasks. Requests that hit it anyway are retried, see [`send_retrying`].
+
+use log::{debug, warn};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use url::Url;
+
+/// How many concurrent requests are sent to a single host unless [`set_concurrency_per_host`] is called
+pub const DEFAULT_CONCURRENCY_PER_HOST: usize = 4;
+
+/// The host that GitHub API requests are limited under, wherever the client is actually pointed
+pub const GITHUB_API_HOST: &str = "api.github.com";
+
+/// How many times a request that hit GitHub's secondary rate limit is retried, unless waiting is enabled
+/// with [`set_wait_for_rate_limit`]
+pub const SECONDARY_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// How long to wait before the first retry, which doubles with every retry after it
+#[cfg(not(test))]
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+#[cfg(test)]
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+/// How long to wait between retries when waiting for the limit to clear, GitHub asks for at least a minute
+#[cfg(not(test))]
+const WAIT_DELAY: Duration = Duration::from_secs(60);
+#[cfg(test)]
+const WAIT_DELAY: Duration = Duration::from_millis(10);
+
+static CONCURRENCY_PER_HOST: AtomicUsize = AtomicUsize::new(DEFAULT_CONCURRENCY_PER_HOST);
+static WAIT_FOR_RATE_LIMIT: AtomicBool = AtomicBool::new(false);
+static ON_RETRY: Mutex<Option<fn(Duration)>> = Mutex::new(None);
+/// The semaphore of each host, with the limit it was created with
+static SEMAPHORES: LazyLock<Mutex<HashMap<String, (Arc<Semaphore>, usize)>>> =
+    LazyLock::new(Default::default);
+
+/// Set how many requests can be sent to a single host at once, at least one
+///
+/// Hosts that have already been requested keep their limit, so call this before sending any requests.
+pub fn set_concurrency_per_host(limit: usize) {
+    CONCURRENCY_PER_HOST.store(limit.max(1), Ordering::Relaxed);
+}
+
+/// Keep retrying requests that hit GitHub's secondary rate limit until it clears,
+/// instead of giving up after [`SECONDARY_RATE_LIMIT_RETRIES`]
+pub fn set_wait_for_rate_limit(wait: bool) {
+    WAIT_FOR_RATE_LIMIT.store(wait, Ordering::Relaxed);
+}
+
+/// Call `on_retry` with how long [`send_retrying`] waits whenever it retries a request that hit GitHub's secondary rate limit,
+/// so the user can be told why nothing is happening
+pub fn set_on_retry(on_retry: fn(Duration)) {
+    *ON_RETRY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(on_retry);
+}
+
+/// Whether `err` is GitHub's secondary rate limit, rather than the hourly one or missing permissions
+pub fn is_secondary_rate_limit(err: &octocrab::Error) -> bool {
+    matches!(
+        err,
+        octocrab::Error::GitHub { source, .. }
+            if source.status_code.as_u16() == 403
+                && source.message.to_lowercase().contains("secondary rate limit")
+    )
+}
+
+/// Permission to send a request to a host, which is given back when this is dropped
+#[derive(Debug)]
+pub struct HostPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Wait until another request can be sent to `host`
+pub async fn acquire(host: &str) -> HostPermit {
+    let (semaphore, limit) = SEMAPHORES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entry(host.to_owned())
+        .or_insert_with(|| {
+            let limit = CONCURRENCY_PER_HOST.load(Ordering::Relaxed);
+            (Arc::new(Semaphore::new(limit)), limit)
+        })
+        .clone();
+    let permit = semaphore
+        .acquire_owned()
+        .await
+        .expect("host semaphores are never closed");
+    // The limit may have been changed since, which the host's semaphore doesn't follow
+    debug!(
+        SCOPE = "libarov::host_limit",
+        host,
+        in_flight = limit - permit.semaphore().available_permits();
+        "acquired host permit"
+    );
+    HostPermit { _permit: permit }
+}
+
+/// Wait until another request can be sent to the host of `url`
+pub async fn acquire_for(url: &Url) -> HostPermit {
+    acquire(url.host_str().unwrap_or_default()).await
+}
+
+/// Send the request made by `request` to `host` whenever it can be, see [`acquire`],
+/// and send it again after a while if it hit GitHub's secondary rate limit
+///
+/// The request is retried [`SECONDARY_RATE_LIMIT_RETRIES`] times with a growing delay,
+/// or every minute until it gets through if [`set_wait_for_rate_limit`] is enabled.
+/// The callback set with [`set_on_retry`] is called before each wait.
+/// GitHub's `Retry-After` header isn't available through octocrab's errors, so the delays are fixed.
+pub async fn send_retrying<T, F>(host: &str, mut request: impl FnMut() -> F) -> octocrab::Result<T>
+where
+    F: Future<Output = octocrab::Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        let result = {
+            let _permit = acquire(host).await;
+            request().await
+        };
+        match result {
+            Err(err) if is_secondary_rate_limit(&err) => {
+                let wait = WAIT_FOR_RATE_LIMIT.load(Ordering::Relaxed);
+                if !wait && retries == SECONDARY_RATE_LIMIT_RETRIES {
+                    warn!(SCOPE = "libarov::host_limit", host, retries; "still rate limited, giving up");
+                    return Err(err);
+                }
+                let delay = if wait {
+                    WAIT_DELAY
+                } else {
+                    RETRY_DELAY * 2_u32.pow(retries)
+                };
+                retries += 1;
+                warn!(SCOPE = "libarov::host_limit", host, retries, delay_secs = delay.as_secs(); "hit the secondary rate limit, retrying");
+                let on_retry = *ON_RETRY
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                if let Some(on_retry) = on_retry {
+                    on_retry(delay);
+                }
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn limits_concurrent_requests_per_host() {
+        static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+        static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+        let tasks = (0..DEFAULT_CONCURRENCY_PER_HOST * 3).map(|_| async {
+            let _permit = acquire("limits-concurrent-requests.invalid").await;
+            let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX_IN_FLIGHT.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+        });
+        futures_util::future::join_all(tasks).await;
+
+        assert_eq!(
+            MAX_IN_FLIGHT.load(Ordering::SeqCst),
+            DEFAULT_CONCURRENCY_PER_HOST
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_secondary_rate_limit() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/Solarint/SAIN"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+                "message": "You have exceeded a secondary rate limit. Please wait a few minutes before you try again.",
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/Solarint/SAIN"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 1,
+                "name": "SAIN",
+                "url": format!("{}/repos/Solarint/SAIN", server.uri()),
+            })))
+            .mount(&server)
+            .await;
+
+        let github_api = octocrab::Octocrab::builder()
+            .base_uri(server.uri())
+            .unwrap()
+            .build()
+            .unwrap();
+        let attempts = AtomicUsize::new(0);
+        let repository = send_retrying("retries-secondary-rate-limit.invalid", || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { github_api.repos("Solarint", "SAIN").get().await }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(repository.name, "SAIN");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
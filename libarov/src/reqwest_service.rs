@@ -0,0 +1,41 @@
+//! Sends octocrab's requests with reqwest, so that GitHub API calls use the same proxy and compression
+//! settings as every other request, see [`http_client_builder`](crate::http_client_builder)
+
+use bytes::Bytes;
+use http_body_util::BodyExt as _;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The innermost service of a GitHub client, which sends each request with the wrapped client
+#[derive(Clone)]
+pub(crate) struct ReqwestService(pub(crate) reqwest::Client);
+
+impl<B> tower_service::Service<http::Request<B>> for ReqwestService
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = http::Response<reqwest::Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let client = self.0.clone();
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            // API request bodies are small JSON documents, so they're buffered instead of streamed
+            let body = body.collect().await.map_err(Into::into)?.to_bytes();
+            let request = reqwest::Request::try_from(http::Request::from_parts(parts, body))?;
+            Ok(client.execute(request).await?.into())
+        })
+    }
+}
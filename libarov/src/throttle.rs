@@ -0,0 +1,106 @@
+//! A limit on how fast all downloads together are transferred
+//!
+//! Downloading many large archives at full speed can take up a shared connection,
+//! so the chunks of every download draw from one token bucket that refills at the limit.
+
+use log::debug;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+static MAX_DOWNLOAD_SPEED: AtomicU64 = AtomicU64::new(0);
+static BUCKET: Mutex<Option<TokenBucket>> = Mutex::new(None);
+
+/// Limit all downloads together to `bytes_per_sec`, or lift the limit with `None`
+pub fn set_max_download_speed(bytes_per_sec: Option<u64>) {
+    let rate = bytes_per_sec.unwrap_or(0);
+    MAX_DOWNLOAD_SPEED.store(rate, Ordering::Relaxed);
+    *BUCKET
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+        (rate > 0).then(|| TokenBucket::new(rate, Instant::now()));
+}
+
+/// The limit set with [`set_max_download_speed`], in bytes per second
+pub fn max_download_speed() -> Option<u64> {
+    match MAX_DOWNLOAD_SPEED.load(Ordering::Relaxed) {
+        0 => None,
+        rate => Some(rate),
+    }
+}
+
+/// Wait until `bytes` more can be downloaded without going over the limit, if there is one
+pub async fn take(bytes: usize) {
+    let wait = match BUCKET
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .as_mut()
+    {
+        Some(bucket) => bucket.take(bytes, Instant::now()),
+        None => return,
+    };
+    if !wait.is_zero() {
+        debug!(SCOPE = "libarov::throttle", bytes, wait_ms = wait.as_millis(); "throttling download");
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Holds up to a second's worth of bytes, which downloaded chunks take from
+///
+/// Chunks can take more than there is, so that a chunk larger than the bucket still gets through.
+/// Whoever takes it into debt waits for it to refill, and so does everyone after them until it's paid off.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_sec: u64, now: Instant) -> Self {
+        Self {
+            rate: bytes_per_sec as f64,
+            tokens: bytes_per_sec as f64,
+            refilled: now,
+        }
+    }
+
+    /// Take `bytes` out of the bucket at `now`, returning how long to wait before sending them on
+    fn take(&mut self, bytes: usize, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.refilled = now;
+        self.tokens -= bytes as f64;
+        if self.tokens < 0. {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket_limits_the_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, start);
+
+        // A second's worth goes through straight away
+        assert_eq!(bucket.take(1000, start), Duration::ZERO);
+        // Then everyone waits for the bucket to refill
+        assert_eq!(bucket.take(500, start), Duration::from_millis(500));
+        assert_eq!(bucket.take(500, start), Duration::from_secs(1));
+
+        // Once the debt is paid off, it fills up to a second's worth again
+        let later = start + Duration::from_secs(5);
+        assert_eq!(bucket.take(1000, later), Duration::ZERO);
+        assert_eq!(bucket.take(250, later), Duration::from_millis(250));
+    }
+}
@@ -1,11 +1,12 @@
 use super::Metadata;
 use crate::{
-    config::filters::{Filter, ReleaseChannel},
+    config::filters::{normalize_filters, Filter, ReleaseChannel},
+    game_version_matches,
     iter_ext::IterExt,
 };
 use log::{debug, info, warn};
 use regex::Regex;
-use std::sync::OnceLock;
+use std::{fmt, sync::OnceLock};
 
 #[derive(thiserror::Error, Debug)]
 #[error(transparent)]
@@ -15,6 +16,16 @@ pub enum Error {
     FilterEmpty(Vec<String>),
     #[error("No compatible files found after applying all filters")]
     NoCompatibleFiles,
+    /// Every filter passes some file, but no file passes all of them
+    #[error("{filename} fails the {filter} filter, and no other file passes all of them")]
+    FailsFilter {
+        filter: String,
+        filename: String,
+    },
+    #[error("The repository has releases, but none of them contain a .zip or .7z archive")]
+    NoArchiveAssets,
+    #[error("The repository has no releases or tags to download")]
+    NoReleases,
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -56,24 +67,12 @@ impl Filter {
         let result = match self {
             Filter::GameVersionStrict(versions) => {
                 debug!(SCOPE = "libarov::upgrade::check", filter:display = self, filename = metadata.filename.as_str(); "checking filter");
+                // As precise as the filter's versions are, so `3.11.4` doesn't match a release for any `3.11`
                 versions.iter().any(|v| {
-                    if metadata.game_versions.contains(v) {
-                        return true;
-                    }
-                    // Accept minor-only vs minor.x equivalence
-                    if v.ends_with(".x") {
-                        let trimmed = &v[..v.len() - 2];
-                        if metadata.game_versions.contains(&trimmed.to_string()) {
-                            return true;
-                        }
-                    } else if v.chars().filter(|c| *c == '.').count() == 1 {
-                        // major.minor form
-                        let with_x = format!("{v}.x");
-                        if metadata.game_versions.contains(&with_x) {
-                            return true;
-                        }
-                    }
-                    false
+                    metadata
+                        .game_versions
+                        .iter()
+                        .any(|found| game_version_matches(v, found))
                 })
             }
 
@@ -106,39 +105,17 @@ impl Filter {
                     "expanded minor-compatible versions"
                 );
 
-                let mut matched = false;
-                let mut matched_by: &str = "";
-                let mut matched_value: String = String::new();
-                for v in &final_versions {
-                    if metadata.game_versions.contains(v) {
-                        matched = true;
-                        matched_by = "exact";
-                        matched_value = v.clone();
-                        break;
-                    }
-                    if v.ends_with(".x") {
-                        let trimmed = &v[..v.len() - 2];
-                        if metadata.game_versions.contains(&trimmed.to_string()) {
-                            matched = true;
-                            matched_by = "wildcard->exact";
-                            matched_value = v.clone();
-                            break;
-                        }
-                    } else if v.chars().filter(|c| *c == '.').count() == 1 {
-                        let with_x = format!("{v}.x");
-                        if metadata.game_versions.contains(&with_x) {
-                            matched = true;
-                            matched_by = "minor->wildcard";
-                            matched_value = with_x;
-                            break;
-                        }
-                    }
-                }
+                let matched_value = final_versions.iter().find_map(|v| {
+                    metadata
+                        .game_versions
+                        .iter()
+                        .find(|found| game_version_matches(v, found))
+                });
+                let matched = matched_value.is_some();
 
                 if matched {
                     debug!(
                         SCOPE = "libarov::upgrade::check",
-                        matched_by,
                         matched_value:debug = matched_value,
                         metadata_versions:debug = &metadata.game_versions;
                         "minor filter matched"
@@ -163,6 +140,8 @@ impl Filter {
                 ReleaseChannel::Release => metadata.channel == ReleaseChannel::Release,
             },
 
+            Filter::Prerelease => metadata.channel != ReleaseChannel::Release,
+
             Filter::Filename(regex) => {
                 debug!(SCOPE = "libarov::upgrade::check", pattern = regex.as_str(), filename = metadata.filename.as_str(); "compiling filename regex");
                 let regex = Regex::new(regex)?;
@@ -180,6 +159,18 @@ impl Filter {
                 let regex = Regex::new(regex)?;
                 regex.is_match(&metadata.description)
             }
+
+            Filter::Any(filters) => {
+                let mut matched = false;
+                for filter in filters {
+                    // Recursive futures have to be boxed
+                    if Box::pin(filter.matches(metadata)).await? {
+                        matched = true;
+                        break;
+                    }
+                }
+                matched
+            }
         };
 
         info!(
@@ -213,14 +204,49 @@ impl Filter {
     }
 }
 
+impl Metadata {
+    /// Returns whether this passes through all of the `filters`
+    pub async fn passes(&self, filters: &[Filter]) -> Result<bool> {
+        Ok(self.first_failing_filter(filters).await?.is_none())
+    }
+
+    /// Returns the first of the `filters` this doesn't pass through, to explain why it's incompatible
+    pub async fn first_failing_filter<'a>(
+        &self,
+        filters: &'a [Filter],
+    ) -> Result<Option<&'a Filter>> {
+        for filter in filters {
+            if !filter.matches(self).await? {
+                return Ok(Some(filter));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The error explaining why this, the most preferred candidate, was rejected by the `filters`
+    pub(crate) async fn rejection(&self, filters: &[Filter]) -> Error {
+        match self.first_failing_filter(filters).await {
+            Ok(Some(filter)) => Error::FailsFilter {
+                filter: filter.to_string(),
+                filename: self.filename.clone(),
+            },
+            Ok(None) => Error::NoCompatibleFiles,
+            Err(err) => err,
+        }
+    }
+}
+
 /// Apply multiple filters and select the best matching metadata from the provided candidates.
 /// The candidates should be sorted in order of preference (e.g., chronological, newest first).
 ///
+/// The `filters` are combined with [`normalize_filters`] first.
+///
 /// Returns the selected metadata that passes all filters, or an error if no candidates pass.
 pub async fn select_latest<'a>(
     candidates: impl Iterator<Item = &'a Metadata> + Clone,
     filters: Vec<Filter>,
 ) -> Result<&'a Metadata> {
+    let filters = normalize_filters(filters);
     let candidates_vec: Vec<&Metadata> = candidates.collect();
     info!(SCOPE = "libarov::upgrade::check", candidates = candidates_vec.len(), filters:debug = filters; "select_latest invoked");
 
@@ -251,19 +277,155 @@ pub async fn select_latest<'a>(
 
     // Find the first candidate that passes all filters
     for &candidate in &candidates_vec {
-        let mut passes_all = true;
-        for filter in &filters {
-            if !filter.matches(candidate).await? {
-                passes_all = false;
-                break;
-            }
-        }
-        if passes_all {
+        if candidate.passes(&filters).await? {
             info!(SCOPE = "libarov::upgrade::check", filename = candidate.filename.as_str(), title = candidate.title.as_str(); "selected candidate");
             return Ok(candidate);
         }
     }
 
     warn!(SCOPE = "libarov::upgrade::check"; "no compatible files after applying filters");
-    Err(Error::NoCompatibleFiles)
+    Err(candidates_vec[0].rejection(&filters).await)
+}
+
+/// Which of the filters each candidate passes, to explain why a mod didn't resolve
+///
+/// Unlike [`select_latest`], every filter is applied to every candidate instead of stopping at the first one that fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// The filters that were applied, displayed
+    pub filters: Vec<String>,
+    /// The candidates in order of preference
+    pub candidates: Vec<ExplainedCandidate>,
+    /// The indices of the game version filters in [`Explanation::filters`]
+    version_filters: Vec<usize>,
+}
+
+/// A candidate of an [`Explanation`] and whether it passes each of its filters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainedCandidate {
+    pub filename: String,
+    pub tag: String,
+    pub game_versions: Vec<String>,
+    /// Whether it passes each of the [`Explanation::filters`], in the same order
+    pub passes: Vec<bool>,
+}
+
+impl Explanation {
+    /// Apply each of the `filters` to each of the `candidates`
+    pub async fn new(candidates: &[&Metadata], filters: &[Filter]) -> Result<Self> {
+        let mut explained = Vec::new();
+        for candidate in candidates {
+            let mut passes = Vec::with_capacity(filters.len());
+            for filter in filters {
+                passes.push(filter.matches(candidate).await?);
+            }
+            explained.push(ExplainedCandidate {
+                filename: candidate.filename.clone(),
+                tag: candidate.tag.clone(),
+                game_versions: candidate.game_versions.clone(),
+                passes,
+            });
+        }
+        Ok(Self {
+            version_filters: filters
+                .iter()
+                .enumerate()
+                .filter(|(_, filter)| {
+                    matches!(
+                        filter,
+                        Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_)
+                    )
+                })
+                .map(|(index, _)| index)
+                .collect(),
+            filters: filters.iter().map(ToString::to_string).collect(),
+            candidates: explained,
+        })
+    }
+
+    /// The candidates that the filter at `index` rejected
+    fn rejected_by(&self, index: usize) -> impl Iterator<Item = &ExplainedCandidate> {
+        self.candidates
+            .iter()
+            .filter(move |candidate| !candidate.passes[index])
+    }
+
+    /// A line for each filter that rejected any candidates, saying how many,
+    /// along with the game versions of the candidates game version filters rejected
+    pub fn summary(&self) -> Vec<String> {
+        (0..self.filters.len())
+            .filter_map(|index| {
+                let rejected = self.rejected_by(index).collect::<Vec<_>>();
+                if rejected.is_empty() {
+                    return None;
+                }
+                let mut line = if rejected.len() == self.candidates.len() {
+                    format!(
+                        "{} candidates, all failed the {} filter",
+                        self.candidates.len(),
+                        self.filters[index]
+                    )
+                } else {
+                    format!(
+                        "{} of {} candidates failed the {} filter",
+                        rejected.len(),
+                        self.candidates.len(),
+                        self.filters[index]
+                    )
+                };
+                if self.version_filters.contains(&index) {
+                    let mut versions = rejected
+                        .iter()
+                        .flat_map(|candidate| &candidate.game_versions)
+                        .collect::<Vec<_>>();
+                    versions.sort_unstable();
+                    versions.dedup();
+                    if versions.is_empty() {
+                        line.push_str("; none of them name a game version");
+                    } else {
+                        line.push_str(&format!(
+                            "; their versions were {}",
+                            versions.iter().display(", ")
+                        ));
+                    }
+                }
+                Some(line)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Explanation {
+    /// The [`Explanation::summary`], followed by a row per candidate marking the filters it passes and fails
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.candidates.is_empty() {
+            return write!(f, "There were no candidates to apply the filters to");
+        }
+        for line in self.summary() {
+            writeln!(f, "{line}")?;
+        }
+        for (index, filter) in self.filters.iter().enumerate() {
+            writeln!(f, "  {}: {filter}", index + 1)?;
+        }
+        write!(f, "  {}", (1..=self.filters.len()).display(" "))?;
+        for candidate in &self.candidates {
+            write!(
+                f,
+                "\n  {}  {} ({})",
+                candidate
+                    .passes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, passes)| {
+                        // Pad each mark to the width of its filter's number
+                        let width = (index + 1).to_string().len();
+                        format!("{:width$}", if *passes { "+" } else { "x" })
+                    })
+                    .display(" "),
+                candidate.filename,
+                candidate.tag
+            )?;
+        }
+        Ok(())
+    }
 }
@@ -1,12 +1,21 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::config::filters::{Filter, ReleaseChannel};
-    use chrono::Utc;
+    use crate::{
+        config::{
+            filters::{normalize_filters, Filter, ReleaseChannel},
+            structs::{Mod, ModIdentifier},
+        },
+        upgrade::{
+            check::{select_latest, Error, Explanation},
+            cmp_preference, Metadata,
+        },
+    };
+    use chrono::{Duration, Utc};
 
     fn create_test_metadata(title: &str, filename: &str, game_versions: Vec<String>) -> Metadata {
         Metadata::new(
             title.to_string(),
+            "v1.0.0".to_string(),
             "Test description".to_string(),
             filename.to_string(),
             Utc::now(),
@@ -18,7 +27,11 @@ mod tests {
     async fn test_game_version_strict_filter() {
         let metadata1 = create_test_metadata("mod1", "mod1.zip", vec!["3.10.0".to_string()]);
         let metadata2 = create_test_metadata("mod2", "mod2.zip", vec!["3.11.0".to_string()]);
-        let metadata3 = create_test_metadata("mod3", "mod3.zip", vec!["3.10.0".to_string(), "3.11.0".to_string()]);
+        let metadata3 = create_test_metadata(
+            "mod3",
+            "mod3.zip",
+            vec!["3.10.0".to_string(), "3.11.0".to_string()],
+        );
 
         let filter = Filter::GameVersionStrict(vec!["3.10.0".to_string()]);
 
@@ -27,6 +40,38 @@ mod tests {
         assert!(filter.matches(&metadata3).await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_game_version_patch_precision() {
+        let any_patch = create_test_metadata("any", "Mod-SPT-3.11.zip", vec!["3.11.x".to_string()]);
+        let patch_4 = create_test_metadata("4", "Mod-SPT-3.11.4.zip", vec!["3.11.4".to_string()]);
+        let patch_3 = create_test_metadata("3", "Mod-SPT-3.11.3.zip", vec!["3.11.3".to_string()]);
+
+        // A minor version matches every patch of it
+        for minor in ["3.11", "3.11.x"] {
+            let filter = Filter::GameVersionStrict(vec![minor.to_string()]);
+            assert!(filter.matches(&any_patch).await.unwrap());
+            assert!(filter.matches(&patch_4).await.unwrap());
+            assert!(filter.matches(&patch_3).await.unwrap());
+        }
+
+        // A patch only matches releases for exactly that patch
+        let filter = Filter::GameVersionStrict(vec!["3.11.4".to_string()]);
+        assert!(!filter.matches(&any_patch).await.unwrap());
+        assert!(filter.matches(&patch_4).await.unwrap());
+        assert!(!filter.matches(&patch_3).await.unwrap());
+        let result = select_latest([&any_patch, &patch_3, &patch_4].into_iter(), vec![filter])
+            .await
+            .unwrap();
+        assert_eq!(result.filename, "Mod-SPT-3.11.4.zip");
+
+        assert!(!Filter::GameVersionStrict(vec!["3.1".to_string()])
+            .matches(&patch_4)
+            .await
+            .unwrap());
+        assert_eq!(crate::trim_patch("3.11.4"), "3.11");
+        assert_eq!(crate::trim_patch("3.11"), "3.11");
+    }
+
     #[tokio::test]
     async fn test_release_channel_filter() {
         let mut metadata_alpha = create_test_metadata("alpha", "alpha.zip", vec![]);
@@ -78,8 +123,10 @@ mod tests {
         let candidates = vec![&metadata1, &metadata2, &metadata3];
         let filters = vec![Filter::GameVersionStrict(vec!["3.10.0".to_string()])];
 
-        let result = select_latest(candidates.into_iter(), filters).await.unwrap();
-        
+        let result = select_latest(candidates.into_iter(), filters)
+            .await
+            .unwrap();
+
         // Should return the first candidate that matches (metadata1)
         assert_eq!(result.title, "mod1");
     }
@@ -93,7 +140,7 @@ mod tests {
         let filters = vec![Filter::GameVersionStrict(vec!["3.10.0".to_string()])];
 
         let result = select_latest(candidates.into_iter(), filters).await;
-        
+
         assert!(matches!(result, Err(Error::FilterEmpty(_))));
     }
 
@@ -103,7 +150,311 @@ mod tests {
         let filters = vec![Filter::GameVersionStrict(vec!["3.10.0".to_string()])];
 
         let result = select_latest(candidates.into_iter(), filters).await;
-        
+
         assert!(matches!(result, Err(Error::NoCompatibleFiles)));
     }
+
+    #[tokio::test]
+    async fn test_passes_and_first_failing_filter() {
+        let metadata = create_test_metadata("mod", "mod-3.10.zip", vec!["3.10".to_string()]);
+        let filters = [
+            Filter::Filename("mod".to_string()),
+            Filter::GameVersionStrict(vec!["3.11".to_string()]),
+            Filter::Title("other".to_string()),
+        ];
+
+        assert!(!metadata.passes(&filters).await.unwrap());
+        assert_eq!(
+            metadata.first_failing_filter(&filters).await.unwrap(),
+            Some(&filters[1])
+        );
+
+        assert!(metadata.passes(&filters[..1]).await.unwrap());
+        assert!(metadata
+            .first_failing_filter(&filters[..1])
+            .await
+            .unwrap()
+            .is_none());
+        // No filters at all are always passed
+        assert!(metadata.passes(&[]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_select_latest_names_failing_filter() {
+        let metadata1 = create_test_metadata("mod1", "mod1.zip", vec!["3.10".to_string()]);
+        let metadata2 = create_test_metadata("mod2", "mod2.zip", vec!["3.11".to_string()]);
+
+        // Each filter matches one of them, but neither matches both
+        let filters = vec![
+            Filter::GameVersionStrict(vec!["3.10".to_string()]),
+            Filter::Filename("mod2".to_string()),
+        ];
+        let result = select_latest([&metadata1, &metadata2].into_iter(), filters).await;
+
+        match result {
+            Err(Error::FailsFilter { filter, filename }) => {
+                assert_eq!(filter, Filter::Filename("mod2".to_string()).to_string());
+                assert_eq!(filename, "mod1.zip");
+            }
+            other => panic!("expected the failing filter to be named, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_any_filter() {
+        let mut metadata_rc = create_test_metadata("rc", "mod-rc1.zip", vec![]);
+        metadata_rc.channel = ReleaseChannel::Beta;
+
+        let metadata_release = create_test_metadata("release", "mod.zip", vec![]);
+
+        let mut metadata_alpha = create_test_metadata("alpha", "mod-nightly.zip", vec![]);
+        metadata_alpha.channel = ReleaseChannel::Alpha;
+
+        let filter = Filter::Any(vec![
+            Filter::ReleaseChannel(ReleaseChannel::Release),
+            Filter::Filename("rc".to_string()),
+        ]);
+
+        assert!(filter.matches(&metadata_rc).await.unwrap());
+        assert!(filter.matches(&metadata_release).await.unwrap());
+        assert!(!filter.matches(&metadata_alpha).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_any_filter_with_and_semantics() {
+        let mut metadata_rc = create_test_metadata("rc", "mod-rc1.zip", vec!["3.11".to_string()]);
+        metadata_rc.channel = ReleaseChannel::Beta;
+
+        let mut metadata_beta =
+            create_test_metadata("beta", "mod-beta.zip", vec!["3.11".to_string()]);
+        metadata_beta.channel = ReleaseChannel::Beta;
+
+        let candidates = vec![&metadata_beta, &metadata_rc];
+        let filters = vec![
+            Filter::GameVersionStrict(vec!["3.11".to_string()]),
+            Filter::Any(vec![
+                Filter::ReleaseChannel(ReleaseChannel::Release),
+                Filter::Filename("rc".to_string()),
+            ]),
+        ];
+
+        let result = select_latest(candidates.into_iter(), filters)
+            .await
+            .unwrap();
+
+        assert_eq!(result.title, "rc");
+    }
+
+    #[test]
+    fn test_any_filter_display_and_serde() {
+        let filter = Filter::Any(vec![
+            Filter::ReleaseChannel(ReleaseChannel::Beta),
+            Filter::Filename("rc".to_string()),
+        ]);
+        assert_eq!(
+            filter.to_string(),
+            "Any (Release Channel (Beta) | Filename (rc))"
+        );
+
+        let json = serde_json::to_string(&filter).unwrap();
+        let parsed: Filter = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.to_string(), filter.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_stable_preferred_over_newer_prerelease() {
+        let stable = create_test_metadata("stable", "stable.zip", vec![]);
+        let mut prerelease = create_test_metadata("prerelease", "prerelease.zip", vec![]);
+        prerelease.channel = ReleaseChannel::Beta;
+        prerelease.release_date = stable.release_date + Duration::days(1);
+
+        let mut candidates = vec![prerelease, stable];
+        candidates.sort_by(cmp_preference);
+
+        let filters = vec![Filter::ReleaseChannel(ReleaseChannel::Beta)];
+        let result = select_latest(candidates.iter(), filters).await.unwrap();
+        assert_eq!(result.title, "stable");
+    }
+
+    #[tokio::test]
+    async fn test_prerelease_flags() {
+        let stable = create_test_metadata("stable", "stable.zip", vec![]);
+        let mut prerelease = create_test_metadata("prerelease", "prerelease.zip", vec![]);
+        prerelease.channel = ReleaseChannel::Beta;
+        prerelease.release_date = stable.release_date + Duration::days(1);
+        let mut older_stable = create_test_metadata("older stable", "older.zip", vec![]);
+        older_stable.release_date = stable.release_date - Duration::days(1);
+
+        let mut candidates = vec![older_stable, prerelease, stable];
+        candidates.sort_by(cmp_preference);
+
+        // --prerelease-only
+        let filters = vec![Filter::Prerelease];
+        let result = select_latest(candidates.iter(), filters).await.unwrap();
+        assert_eq!(result.filename, "prerelease.zip");
+
+        // --no-prerelease, even when the profile allows alpha
+        let filters = vec![Filter::ReleaseChannel(ReleaseChannel::Release)];
+        let result = select_latest(candidates.iter(), filters).await.unwrap();
+        assert_eq!(result.filename, "stable.zip");
+    }
+
+    #[test]
+    fn test_normalize_merges_version_filters() {
+        let filters = normalize_filters(vec![
+            Filter::GameVersionMinor(vec!["3.10".to_string()]),
+            Filter::Filename("SPT".to_string()),
+            Filter::GameVersionStrict(vec!["3.11".to_string(), "3.10".to_string()]),
+        ]);
+        // Strict wins over minor, and the merged filter keeps the first one's place
+        assert_eq!(
+            filters,
+            [
+                Filter::GameVersionStrict(vec!["3.10".to_string(), "3.11".to_string()]),
+                Filter::Filename("SPT".to_string()),
+            ]
+        );
+
+        let filters = normalize_filters(vec![
+            Filter::GameVersionMinor(vec!["3.10".to_string()]),
+            Filter::GameVersionMinor(vec!["3.11".to_string()]),
+        ]);
+        assert_eq!(
+            filters,
+            [Filter::GameVersionMinor(vec![
+                "3.10".to_string(),
+                "3.11".to_string()
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_normalize_channels_and_duplicates() {
+        let filters = normalize_filters(vec![
+            Filter::ReleaseChannel(ReleaseChannel::Alpha),
+            Filter::Filename("SPT".to_string()),
+            Filter::Title("SPT".to_string()),
+            Filter::ReleaseChannel(ReleaseChannel::Beta),
+            Filter::Filename("SPT".to_string()),
+        ]);
+        assert_eq!(
+            filters,
+            [
+                Filter::ReleaseChannel(ReleaseChannel::Beta),
+                Filter::Filename("SPT".to_string()),
+                Filter::Title("SPT".to_string()),
+            ]
+        );
+    }
+
+    fn create_test_mod(filters: Vec<Filter>, override_filters: bool) -> Mod {
+        let mut mod_ = Mod::new(
+            "mod".to_string(),
+            ModIdentifier::GitHubRepository("owner".to_string(), "mod".to_string()),
+            filters,
+        );
+        mod_.override_filters = override_filters;
+        mod_
+    }
+
+    #[test]
+    fn test_effective_filters_extend_profile() {
+        let profile_filters = vec![
+            Filter::GameVersionStrict(vec!["3.10".to_string()]),
+            Filter::ReleaseChannel(ReleaseChannel::Release),
+        ];
+
+        let mod_ = create_test_mod(vec![Filter::Filename("SAIN".to_string())], false);
+        assert_eq!(
+            mod_.effective_filters(&profile_filters),
+            [
+                Filter::GameVersionStrict(vec!["3.10".to_string()]),
+                Filter::ReleaseChannel(ReleaseChannel::Release),
+                Filter::Filename("SAIN".to_string()),
+            ]
+        );
+
+        // The mod's channel replaces the profile's, even though it's less stable
+        let mod_ = create_test_mod(vec![Filter::ReleaseChannel(ReleaseChannel::Beta)], false);
+        assert_eq!(
+            mod_.effective_filters(&profile_filters),
+            [
+                Filter::GameVersionStrict(vec!["3.10".to_string()]),
+                Filter::ReleaseChannel(ReleaseChannel::Beta),
+            ]
+        );
+
+        // Version filters are merged like anywhere else
+        let mod_ = create_test_mod(
+            vec![Filter::GameVersionMinor(vec!["3.11".to_string()])],
+            false,
+        );
+        assert_eq!(
+            mod_.effective_filters(&profile_filters),
+            [
+                Filter::GameVersionStrict(vec!["3.10".to_string(), "3.11".to_string()]),
+                Filter::ReleaseChannel(ReleaseChannel::Release),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_effective_filters_override_profile() {
+        let profile_filters = vec![
+            Filter::GameVersionStrict(vec!["3.10".to_string()]),
+            Filter::ReleaseChannel(ReleaseChannel::Release),
+        ];
+
+        let mod_ = create_test_mod(vec![Filter::Filename("SAIN".to_string())], true);
+        assert_eq!(
+            mod_.effective_filters(&profile_filters),
+            [Filter::Filename("SAIN".to_string())]
+        );
+
+        let mod_ = create_test_mod(vec![], true);
+        assert!(mod_.effective_filters(&profile_filters).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_contradicting_version_filters_select_either() {
+        let old = create_test_metadata("old", "old.zip", vec!["3.10".to_string()]);
+        let filters = vec![
+            Filter::GameVersionStrict(vec!["3.11".to_string()]),
+            Filter::GameVersionStrict(vec!["3.10".to_string()]),
+        ];
+        // Previously both lists had to match, so every candidate failed one of them
+        let result = select_latest([&old].into_iter(), filters).await.unwrap();
+        assert_eq!(result.filename, "old.zip");
+    }
+
+    #[tokio::test]
+    async fn test_explanation_applies_every_filter() {
+        let old = create_test_metadata("old", "Mod-SPT-3.10.zip", vec!["3.10".to_string()]);
+        let server =
+            create_test_metadata("server", "Mod-Server-SPT-3.9.zip", vec!["3.9".to_string()]);
+        let filters = vec![
+            Filter::GameVersionStrict(vec!["3.11".to_string()]),
+            Filter::Filename("Server".to_string()),
+        ];
+
+        let explanation = Explanation::new(&[&old, &server], &filters).await.unwrap();
+        assert_eq!(
+            explanation
+                .candidates
+                .iter()
+                .map(|candidate| candidate.passes.clone())
+                .collect::<Vec<_>>(),
+            [vec![false, false], vec![false, true]]
+        );
+        assert_eq!(
+            explanation.summary(),
+            [
+                "2 candidates, all failed the Game Version (3.11) filter; their versions were 3.10, 3.9",
+                "1 of 2 candidates failed the Filename (Server) filter",
+            ]
+        );
+        assert!(explanation.to_string().ends_with(
+            "  1 2\n  x x  Mod-SPT-3.10.zip (v1.0.0)\n  x +  Mod-Server-SPT-3.9.zip (v1.0.0)"
+        ));
+    }
 }
@@ -1,27 +1,61 @@
 pub mod check;
+#[cfg(test)]
+mod check_test;
 pub mod mod_downloadable;
+pub mod plan;
+pub mod source;
 
 use crate::{
     config::{filters::ReleaseChannel, structs::ModIdentifier},
-    extract_versions, is_spt_version,
+    extract_versions,
+    host_limit::{self, GITHUB_API_HOST},
+    is_spt_version, is_supported_archive,
     iter_ext::IterExt as _,
+    throttle,
 };
 use chrono::{DateTime, Utc};
 use log::{debug, info};
-use octocrab::models::repos::{Asset as GHAsset, Release as GHRelease};
-use reqwest::{Client, Url};
+use octocrab::{
+    models::repos::{Asset as GHAsset, Release as GHRelease, Tag as GHTag},
+    Octocrab,
+};
+use reqwest::{header::RANGE, Client, Response, StatusCode, Url};
 use std::{
+    cmp::Ordering,
     fs::{create_dir_all, rename, OpenOptions},
     io::{BufWriter, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::{self, AtomicUsize},
+    time::{Duration, Instant},
 };
 
 #[derive(Debug, thiserror::Error)]
-#[error(transparent)]
 pub enum Error {
+    #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
+    #[error(transparent)]
     IOError(#[from] std::io::Error),
+    #[error("The download broke off and the server can't continue it from where it stopped")]
+    RangeNotSupported,
+    /// Any of the above, with the file that was being downloaded
+    #[error("Failed downloading {filename} from {url}: {source}")]
+    Download {
+        filename: String,
+        url: Url,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Wrap `self` in [`Error::Download`] for the file `filename` downloaded from `url`
+    fn downloading(self, filename: impl Into<String>, url: &Url) -> Self {
+        Self::Download {
+            filename: filename.into(),
+            url: url.clone(),
+            source: Box::new(self),
+        }
+    }
 }
 type Result<T> = std::result::Result<T, Error>;
 
@@ -30,6 +64,8 @@ type Result<T> = std::result::Result<T, Error>;
 pub struct Metadata {
     /// The title of the GitHub Release
     pub title: String,
+    /// The tag of the GitHub Release
+    pub tag: String,
     /// The body of the GitHub Release
     pub description: String,
     /// The filename of the asset
@@ -45,6 +81,7 @@ pub struct Metadata {
 impl Metadata {
     pub fn new(
         title: String,
+        tag: String,
         description: String,
         filename: String,
         release_date: DateTime<Utc>,
@@ -52,6 +89,7 @@ impl Metadata {
     ) -> Self {
         Metadata {
             title,
+            tag,
             description,
             filename,
             release_date,
@@ -61,6 +99,24 @@ impl Metadata {
     }
 }
 
+/// Order candidates by preference: more stable channels first, then newest first
+///
+/// This way a stable release wins over a newer prerelease when the filters allow both.
+pub fn cmp_preference(a: &Metadata, b: &Metadata) -> Ordering {
+    a.channel
+        .cmp(&b.channel)
+        .then_with(|| b.release_date.cmp(&a.release_date))
+}
+
+/// The release channel of a GitHub Release
+pub fn gh_release_channel(release: &GHRelease) -> ReleaseChannel {
+    if release.prerelease {
+        ReleaseChannel::Beta
+    } else {
+        ReleaseChannel::Release
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DownloadData {
     pub download_url: Url,
@@ -70,6 +126,8 @@ pub struct DownloadData {
     pub output: PathBuf,
     /// The length of the file in bytes
     pub length: usize,
+    /// The tag of the release the file belongs to, if known
+    pub tag: Option<String>,
     /// The dependencies this file has
     pub dependencies: Vec<ModIdentifier>,
     /// Other mods this file is incompatible with
@@ -95,13 +153,200 @@ pub struct DistributionDeniedError(pub i32, pub i32);
 //     }
 // }
 
+/// Fetch at most `max_releases` of the most recent releases of `owner/repo` using `github_api`
+///
+/// GitHub returns at most 100 releases per page, so larger windows cost an extra request per page.
+pub async fn fetch_gh_releases(
+    github_api: &Octocrab,
+    owner: &str,
+    repo: &str,
+    max_releases: usize,
+) -> octocrab::Result<Vec<GHRelease>> {
+    let per_page = max_releases.clamp(1, 100);
+    let mut releases = Vec::new();
+    let mut page = 1_u32;
+
+    while releases.len() < max_releases {
+        let items = host_limit::send_retrying(GITHUB_API_HOST, || async {
+            github_api
+                .repos(owner, repo)
+                .releases()
+                .list()
+                .per_page(u8::try_from(per_page).unwrap_or(100))
+                .page(page)
+                .send()
+                .await
+        })
+        .await?
+        .items;
+        let exhausted = items.len() < per_page;
+        releases.extend(items);
+        if exhausted {
+            break;
+        }
+        page += 1;
+    }
+    releases.truncate(max_releases);
+
+    debug!(SCOPE = "libarov::upgrade", owner, repo, max_releases, fetched = releases.len(); "fetched releases");
+    Ok(releases)
+}
+
+/// Get at most `max_releases` of the most recent tags of `owner/repo` using `github_api`,
+/// for repositories that push tags without publishing releases
+pub async fn fetch_gh_tags(
+    github_api: &Octocrab,
+    owner: &str,
+    repo: &str,
+    max_releases: usize,
+) -> octocrab::Result<Vec<GHTag>> {
+    let per_page = max_releases.clamp(1, 100);
+    let mut tags = Vec::new();
+    let mut page = 1_u32;
+
+    while tags.len() < max_releases {
+        let items = host_limit::send_retrying(GITHUB_API_HOST, || async {
+            github_api
+                .repos(owner, repo)
+                .list_tags()
+                .per_page(u8::try_from(per_page).unwrap_or(100))
+                .page(page)
+                .send()
+                .await
+        })
+        .await?
+        .items;
+        let exhausted = items.len() < per_page;
+        tags.extend(items);
+        if exhausted {
+            break;
+        }
+        page += 1;
+    }
+    tags.truncate(max_releases);
+
+    debug!(SCOPE = "libarov::upgrade", owner, repo, max_releases, fetched = tags.len(); "fetched tags");
+    Ok(tags)
+}
+
+/// Appended to the repository name and tag to name the source code archive of a release
+const SOURCE_ARCHIVE_SUFFIX: &str = "-source.zip";
+
+/// Whether `filename` is the source code archive of a release rather than one of its assets
+pub fn is_source_archive(filename: &str) -> bool {
+    filename.ends_with(SOURCE_ARCHIVE_SUFFIX)
+}
+
+/// The filename of the source code archive of the `tag` of the `repo` repository
+fn source_archive_filename(repo: &str, tag: &str) -> String {
+    format!("{repo}-{}{SOURCE_ARCHIVE_SUFFIX}", tag.replace('/', "-"))
+}
+
+/// The SPT versions mentioned in `texts`, without duplicates
+fn guess_game_versions<'a>(texts: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let mut game_versions = Vec::new();
+    for version in texts
+        .into_iter()
+        .flat_map(|text| extract_versions(text))
+        .filter(|version| is_spt_version(version))
+    {
+        if !game_versions.contains(&version) {
+            game_versions.push(version);
+        }
+    }
+    game_versions
+}
+
+/// The source code archive of `release` from the `repo` repository,
+/// for mods that are distributed as source rather than as a release asset
+///
+/// Its game versions are guessed from the release's name and tag, since there's no asset name to go by.
+pub fn source_archive(release: &GHRelease, repo: &str) -> Option<(Metadata, DownloadData)> {
+    let download_url = release.zipball_url.clone()?;
+    let filename = source_archive_filename(repo, &release.tag_name);
+
+    let mut metadata = Metadata::new(
+        release.name.clone().unwrap_or_default(),
+        release.tag_name.clone(),
+        release.body.clone().unwrap_or_default(),
+        filename.clone(),
+        release.published_at.unwrap_or_else(Utc::now),
+        Some(guess_game_versions(
+            release.name.iter().chain([&release.tag_name]),
+        )),
+    );
+    metadata.channel = gh_release_channel(release);
+
+    Some((
+        metadata,
+        DownloadData {
+            download_url,
+            output: filename.into(),
+            // The size of source archives isn't known up front
+            length: 0,
+            tag: Some(release.tag_name.clone()),
+            dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        },
+    ))
+}
+
+/// The source code archives of `tags` from the `repo` repository, in the order GitHub lists them,
+/// for repositories that push tags without publishing releases
+///
+/// Tags don't have a date, notes or prerelease flag, so they're all treated as releases of the same age,
+/// and their game versions are guessed from the tag alone.
+pub fn from_gh_tags(
+    tags: impl IntoIterator<Item = GHTag>,
+    repo: &str,
+) -> Vec<(Metadata, DownloadData)> {
+    tags.into_iter()
+        .map(|tag| {
+            let filename = source_archive_filename(repo, &tag.name);
+            let metadata = Metadata::new(
+                tag.name.clone(),
+                tag.name.clone(),
+                String::new(),
+                filename.clone(),
+                DateTime::UNIX_EPOCH,
+                Some(guess_game_versions([&tag.name])),
+            );
+            (
+                metadata,
+                DownloadData {
+                    download_url: tag.zipball_url,
+                    output: filename.into(),
+                    length: 0,
+                    tag: Some(tag.name),
+                    dependencies: Vec::new(),
+                    conflicts: Vec::new(),
+                },
+            )
+        })
+        .collect()
+}
+
 // TODO: de-duplicate this? also in add.rs, from switch to REST calls
+/// Get the archive assets of `releases`, ordered by [`cmp_preference`]
+///
+/// If `source_repo` is provided, releases without archive assets fall back to their [`source_archive`].
 pub fn from_gh_releases(
     releases: impl IntoIterator<Item = GHRelease>,
+    source_repo: Option<&str>,
 ) -> Vec<(Metadata, DownloadData)> {
-    releases
+    let mut candidates = releases
         .into_iter()
         .flat_map(|release| {
+            if let Some(repo) = source_repo.filter(|_| {
+                !release
+                    .assets
+                    .iter()
+                    .any(|asset| is_supported_archive(&asset.name))
+            }) {
+                return source_archive(&release, repo).into_iter().collect_vec();
+            }
+
+            let channel = gh_release_channel(&release);
             let mut found_versions = Vec::new();
             if let Some(ref release_name) = release.name {
                 found_versions.push(extract_versions(release_name.as_str()));
@@ -111,7 +356,7 @@ pub fn from_gh_releases(
                 .assets
                 .into_iter()
                 // Only consider archive assets we can process
-                .filter(|asset| asset.name.ends_with(".zip") || asset.name.ends_with(".7z"))
+                .filter(|asset| is_supported_archive(&asset.name))
                 .map(move |asset| {
                     found_versions.push(extract_versions(asset.name.as_str()));
                     let game_versions = if found_versions.is_empty() {
@@ -125,48 +370,197 @@ pub fn from_gh_releases(
                             .flatten()
                             .filter(|v| is_spt_version(v))
                             .collect::<Vec<_>>();
+                        // Kept with their patch, or `x` if there's none, for filters that want a specific patch
                         if filtered_versions.is_empty() {
                             None
                         } else {
-                            // Normalize to major.minor by trimming patch/wildcard segment
-                            let normalized = filtered_versions
-                                .into_iter()
-                                .map(|v| {
-                                    if v.matches('.').count() == 2 {
-                                        // major.minor.patch or major.minor.x
-                                        if let Some(pos) = v.rfind('.') {
-                                            v[..pos].to_string()
-                                        } else {
-                                            v
-                                        }
-                                    } else {
-                                        v
-                                    }
-                                })
-                                .collect::<Vec<_>>();
-                            Some(normalized)
+                            Some(filtered_versions)
                         }
                     };
 
+                    let mut metadata = Metadata::new(
+                        release.name.clone().unwrap_or_default(),
+                        release.tag_name.clone(),
+                        release.body.clone().unwrap_or_default(),
+                        asset.name.clone(),
+                        release.published_at.unwrap_or_else(Utc::now),
+                        game_versions,
+                    );
+                    metadata.channel = channel;
+
                     (
-                        Metadata::new(
-                            release.name.clone().unwrap_or_default(),
-                            release.body.clone().unwrap_or_default(),
-                            asset.name.clone(),
-                            release.published_at.unwrap_or_else(Utc::now),
-                            game_versions,
-                        ),
+                        metadata,
                         DownloadData {
                             download_url: asset.browser_download_url,
                             output: asset.name.into(),
                             length: asset.size as usize,
+                            tag: Some(release.tag_name.clone()),
                             dependencies: Vec::new(),
                             conflicts: Vec::new(),
                         },
                     )
                 })
+                .collect_vec()
         })
-        .collect_vec()
+        .collect_vec();
+
+    // Stable sort, so assets keep their order within a release
+    candidates.sort_by(|(a, _), (b, _)| cmp_preference(a, b));
+    candidates
+}
+
+/// The path a download to `path` is written to until it completes
+///
+/// `.part` is appended to the whole filename rather than replacing the extension,
+/// so that names with several dots like `mod.v1.2.zip` keep their version.
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    part.into()
+}
+
+/// The largest buffer downloads are written to the file through unless [`set_download_buffer_size`] is called
+pub const DEFAULT_DOWNLOAD_BUFFER_SIZE: usize = 256 * 1024;
+
+static DOWNLOAD_BUFFER_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_DOWNLOAD_BUFFER_SIZE);
+
+/// Set the largest buffer downloads are written to the file through, in bytes
+///
+/// The buffer is independent of the size of the file, so large archives don't have to fit in memory.
+pub fn set_download_buffer_size(bytes: usize) {
+    DOWNLOAD_BUFFER_SIZE.store(bytes.max(1), atomic::Ordering::Relaxed);
+}
+
+/// The capacity of the buffer to write a download of `size` bytes through,
+/// no larger than the file unless it's tiny, or than the configured buffer size
+pub(crate) fn buffer_capacity(size: usize) -> usize {
+    DOWNLOAD_BUFFER_SIZE
+        .load(atomic::Ordering::Relaxed)
+        .min(size.max(8 * 1024))
+}
+
+/// How far along a download is, whenever a chunk of it is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The length of the chunk that was just written
+    pub chunk: usize,
+    /// The number of bytes written so far
+    pub written: usize,
+    /// The expected size of the file, which `written` can exceed if the response was compressed
+    pub total: usize,
+    /// The time since the download started, including any retries
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// The average speed of the download so far, in bytes per second
+    pub fn bytes_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0. {
+            self.written as f64 / secs
+        } else {
+            0.
+        }
+    }
+
+    /// How long the rest of the download will take at the average speed so far,
+    /// if anything has been written and the expected size is known
+    pub fn eta(&self) -> Option<Duration> {
+        let speed = self.bytes_per_sec();
+        if speed <= 0. || self.total == 0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            self.total.saturating_sub(self.written) as f64 / speed,
+        ))
+    }
+}
+
+/// How many times a download is requested before giving up on a transfer that keeps breaking off
+const MAX_DOWNLOAD_ATTEMPTS: usize = 3;
+
+/// Request `url` from the byte at `offset` onwards, following redirects
+async fn request_from(client: &Client, url: Url, offset: usize) -> reqwest::Result<Response> {
+    let _permit = host_limit::acquire_for(&url).await;
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    request.send().await
+}
+
+/// Download `url`, which is expected to be `total` bytes long, into `sink` and flush it,
+/// calling `progress` whenever a chunk is written
+///
+/// GitHub redirects downloads to a signed CDN URL, which is resolved once and reused to continue
+/// the transfer if it breaks off. If the signature has expired by then, it's resolved again from `url`.
+///
+/// Requests and transfers are limited per host, see [`host_limit`],
+/// and chunks are held back to keep under the maximum download speed, see [`throttle`].
+///
+/// Returns the number of bytes written.
+async fn write_response(
+    client: Client,
+    url: Url,
+    sink: &mut (impl Write + Send),
+    total: usize,
+    progress: impl Fn(Progress) + Send,
+) -> Result<usize> {
+    let started = Instant::now();
+    let mut resolved = None;
+    // Chunks are already decompressed, so count what's written rather than trusting `Content-Length`
+    let mut written = 0;
+    let mut attempt = 1;
+    loop {
+        let mut response = match resolved.take() {
+            Some(resolved) => {
+                let response = request_from(&client, resolved, written).await?;
+                if response.status() == StatusCode::FORBIDDEN {
+                    debug!(SCOPE = "libarov::upgrade::download", url = url.as_str(); "signed download URL expired, re-resolving it");
+                    request_from(&client, url.clone(), written).await?
+                } else {
+                    response
+                }
+            }
+            None => request_from(&client, url.clone(), written).await?,
+        }
+        .error_for_status()?;
+        if written > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            // The whole file was sent again, which can't be appended to what's already written
+            return Err(Error::RangeNotSupported);
+        }
+        resolved = Some(response.url().clone());
+
+        // The redirect may lead to another host, which the body is streamed from
+        let _permit = host_limit::acquire_for(response.url()).await;
+        let broken_off = loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    throttle::take(chunk.len()).await;
+                    sink.write_all(&chunk)?;
+                    written += chunk.len();
+                    progress(Progress {
+                        chunk: chunk.len(),
+                        written,
+                        total,
+                        elapsed: started.elapsed(),
+                    });
+                }
+                Ok(None) => break None,
+                Err(err) => break Some(err),
+            }
+        };
+        match broken_off {
+            None => break,
+            Some(err) if written > 0 && attempt < MAX_DOWNLOAD_ATTEMPTS => {
+                debug!(SCOPE = "libarov::upgrade::download", url = url.as_str(), written, attempt, error:display = err; "download broke off, continuing it");
+                attempt += 1;
+            }
+            Some(err) => return Err(err.into()),
+        }
+    }
+    sink.flush()?;
+    Ok(written)
 }
 
 pub fn from_gh_asset(asset: GHAsset) -> DownloadData {
@@ -174,6 +568,7 @@ pub fn from_gh_asset(asset: GHAsset) -> DownloadData {
         download_url: asset.browser_download_url,
         output: asset.name.into(),
         length: asset.size as usize,
+        tag: None,
         dependencies: Vec::new(),
         conflicts: Vec::new(),
     }
@@ -183,17 +578,51 @@ impl DownloadData {
     /// Consumes `self` and downloads the file to the `output_dir`
     ///
     /// The `update` closure is called with the chunk length whenever a chunk is downloaded and written.
+    /// Use [`DownloadData::download_with_progress`] to get the speed and how much is left too.
     ///
-    /// Returns the total size of the file and the filename.
+    /// Returns the number of bytes written, which is the decompressed size if the response was compressed,
+    /// and the filename.
     pub async fn download(
         self,
         client: Client,
         output_dir: impl AsRef<Path>,
         update: impl Fn(usize) + Send,
+    ) -> Result<(usize, String)> {
+        self.download_with_progress(client, output_dir, move |progress| {
+            update(progress.chunk);
+        })
+        .await
+    }
+
+    /// Consumes `self` and downloads the file to the `output_dir`
+    ///
+    /// The `progress` closure is called whenever a chunk is downloaded and written,
+    /// with enough to show the speed and time left.
+    ///
+    /// Returns the number of bytes written, which is the decompressed size if the response was compressed,
+    /// and the filename. Errors are wrapped in [`Error::Download`] to tell which file failed.
+    pub async fn download_with_progress(
+        self,
+        client: Client,
+        output_dir: impl AsRef<Path>,
+        progress: impl Fn(Progress) + Send,
+    ) -> Result<(usize, String)> {
+        let (filename, url) = (self.filename(), self.download_url.clone());
+        self.download_to_dir(client, output_dir.as_ref(), progress)
+            .await
+            .map_err(|err| err.downloading(filename, &url))
+    }
+
+    /// The actual download of [`DownloadData::download_with_progress`]
+    async fn download_to_dir(
+        self,
+        client: Client,
+        output_dir: &Path,
+        progress: impl Fn(Progress) + Send,
     ) -> Result<(usize, String)> {
         let (filename, url, size) = (self.filename(), self.download_url, self.length);
-        let out_file_path = output_dir.as_ref().join(&self.output);
-        let temp_file_path = out_file_path.with_extension("part");
+        let out_file_path = output_dir.join(&self.output);
+        let temp_file_path = part_path(&out_file_path);
         if let Some(up_dir) = out_file_path.parent() {
             create_dir_all(up_dir)?;
             info!(SCOPE = "libarov::upgrade::download", dir:display = up_dir.display().to_string(); "ensured parent directory exists");
@@ -202,7 +631,7 @@ impl DownloadData {
         debug!(SCOPE = "libarov::upgrade::download", path:display = temp_file_path.display(), size = size; "creating temp file");
 
         let mut temp_file = BufWriter::with_capacity(
-            size,
+            buffer_capacity(size),
             OpenOptions::new()
                 .write(true)
                 .create(true)
@@ -211,16 +640,10 @@ impl DownloadData {
         );
 
         info!(SCOPE = "libarov::upgrade::download", url = url.as_str(), size = size, filename = filename.as_str(); "starting download");
-        let mut response = client.get(url).send().await?;
-
-        while let Some(chunk) = response.chunk().await? {
-            temp_file.write_all(&chunk)?;
-            update(chunk.len());
-        }
-        temp_file.flush()?;
+        let written = write_response(client, url, &mut temp_file, size, progress).await?;
         debug!(SCOPE = "libarov::upgrade::download", from:display = temp_file_path.display().to_string(), to:display = out_file_path.display().to_string(); "renaming temp file to final");
         rename(temp_file_path, &out_file_path)?;
-        info!(SCOPE = "libarov::upgrade::download", path:display = out_file_path.display().to_string(), size = size; "download complete");
+        info!(SCOPE = "libarov::upgrade::download", path:display = out_file_path.display().to_string(), size = written; "download complete");
         #[cfg(windows)]
         {
             if let Ok(meta) = std::fs::metadata(&out_file_path) {
@@ -243,11 +666,51 @@ impl DownloadData {
 
                 let _ = perms.set_mode(0o644);
                 let _ = std::fs::set_permissions(&out_file_path, perms);
-                info!(SCOPE = "libarov::upgrade::download", path = out_file_path.display().to_string(); "permissions normalised (unix)");
+                info!(SCOPE = "libarov::upgrade::download", path:display = out_file_path.display(); "permissions normalised (unix)");
             }
         }
 
-        Ok((size, filename))
+        Ok((written, filename))
+    }
+
+    /// Consumes `self` and downloads the file into `sink` instead of the filesystem,
+    /// e.g. to extract an archive straight from memory
+    ///
+    /// The `update` closure is called with the chunk length whenever a chunk is downloaded and written.
+    /// Use [`DownloadData::download_into_with_progress`] to get the speed and how much is left too.
+    ///
+    /// Returns the number of bytes written, which is the decompressed size if the response was compressed.
+    pub async fn download_into(
+        self,
+        client: Client,
+        sink: &mut (impl Write + Send),
+        update: impl Fn(usize) + Send,
+    ) -> Result<usize> {
+        self.download_into_with_progress(client, sink, move |progress| update(progress.chunk))
+            .await
+    }
+
+    /// Consumes `self` and downloads the file into `sink` instead of the filesystem,
+    /// calling `progress` whenever a chunk is downloaded and written
+    ///
+    /// Returns the number of bytes written, which is the decompressed size if the response was compressed.
+    /// Errors are wrapped in [`Error::Download`] to tell which file failed.
+    pub async fn download_into_with_progress(
+        self,
+        client: Client,
+        sink: &mut (impl Write + Send),
+        progress: impl Fn(Progress) + Send,
+    ) -> Result<usize> {
+        info!(SCOPE = "libarov::upgrade::download", url = self.download_url.as_str(), size = self.length, filename = self.filename().as_str(); "starting streamed download");
+        write_response(
+            client,
+            self.download_url.clone(),
+            sink,
+            self.length,
+            progress,
+        )
+        .await
+        .map_err(|err| err.downloading(self.filename(), &self.download_url))
     }
 
     pub fn filename(&self) -> String {
@@ -258,3 +721,235 @@ impl DownloadData {
             .to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client;
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    /// A new mock server that serves `response` at `path`
+    async fn serve(path: &str, response: ResponseTemplate) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(matchers::path(path))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// The download of the file at `path` on the `server`, which is expected to be `length` bytes long
+    fn download_from(server: &MockServer, path: &str, length: usize) -> DownloadData {
+        DownloadData {
+            download_url: format!("{}{path}", server.uri()).parse().unwrap(),
+            output: path.trim_start_matches('/').into(),
+            length,
+            tag: None,
+            dependencies: vec![],
+            conflicts: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn download_keeps_dotted_filenames() {
+        let filename = "SAIN.v3.2.0.zip";
+        let path = format!("/{filename}");
+        let server = serve(&path, ResponseTemplate::new(200).set_body_string("zip")).await;
+        let output_dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            part_path(&output_dir.path().join(filename)),
+            output_dir.path().join("SAIN.v3.2.0.zip.part")
+        );
+
+        let (written, downloaded) = download_from(&server, &path, 3)
+            .download(http_client(), output_dir.path(), |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!((written, downloaded.as_str()), (3, filename));
+        let files = std::fs::read_dir(output_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(files, [filename]);
+    }
+
+    #[tokio::test]
+    async fn download_errors_name_the_file() {
+        let server = serve("/SAIN.zip", ResponseTemplate::new(404)).await;
+        let download = download_from(&server, "/SAIN.zip", 3);
+        let url = download.download_url.clone();
+
+        let err = download
+            .download_into(http_client(), &mut Vec::new(), |_| {})
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            &err,
+            Error::Download { filename, source, .. }
+                if filename == "SAIN.zip" && matches!(**source, Error::ReqwestError(_))
+        ));
+        let message = err.to_string();
+        assert!(message.starts_with(&format!("Failed downloading SAIN.zip from {url}: ")));
+        assert!(message.contains("404"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn download_reports_progress() {
+        // Large enough to arrive in several chunks
+        let length = 1024 * 1024;
+        let server = serve(
+            "/archive.zip",
+            ResponseTemplate::new(200).set_body_bytes(vec![0; length]),
+        )
+        .await;
+
+        let updates = std::sync::Mutex::new(Vec::new());
+        let mut sink = Vec::new();
+        let written = download_from(&server, "/archive.zip", length)
+            .download_into_with_progress(http_client(), &mut sink, |progress| {
+                updates.lock().unwrap().push(progress);
+            })
+            .await
+            .unwrap();
+
+        let updates = updates.into_inner().unwrap();
+        let last = updates.last().unwrap();
+        assert!(updates.len() > 1);
+        assert_eq!(
+            (written, last.written, last.total),
+            (length, length, length)
+        );
+        assert_eq!(
+            updates.iter().map(|progress| progress.chunk).sum::<usize>(),
+            length
+        );
+        assert!(updates
+            .windows(2)
+            .all(|pair| pair[0].written < pair[1].written && pair[0].elapsed <= pair[1].elapsed));
+        assert_eq!(last.eta(), Some(Duration::ZERO));
+
+        let halfway = Progress {
+            chunk: 1,
+            written: 50,
+            total: 100,
+            elapsed: Duration::from_secs(5),
+        };
+        assert_eq!(halfway.bytes_per_sec(), 10.);
+        assert_eq!(halfway.eta(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn download_buffer_is_bounded() {
+        assert_eq!(
+            buffer_capacity(500 * 1024 * 1024),
+            DEFAULT_DOWNLOAD_BUFFER_SIZE
+        );
+        assert_eq!(buffer_capacity(3), 8 * 1024);
+        // Used to allocate a buffer as large as the file
+        assert_eq!(buffer_capacity(usize::MAX), DEFAULT_DOWNLOAD_BUFFER_SIZE);
+    }
+
+    /// A server that answers each connection with the next of the raw `responses`,
+    /// and returns the path of each request and whether it asked for a range
+    ///
+    /// Wiremock won't send a body shorter than its `Content-Length`, which a transfer that breaks off needs.
+    async fn serve_raw(
+        responses: &'static [&'static str],
+    ) -> (
+        std::net::SocketAddr,
+        tokio::task::JoinHandle<Vec<(String, bool)>>,
+    ) {
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 1024];
+                let read = socket.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..read]).to_lowercase();
+                requests.push((
+                    request.split_whitespace().nth(1).unwrap().to_owned(),
+                    request.contains("range: bytes="),
+                ));
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (addr, server)
+    }
+
+    #[tokio::test]
+    async fn download_re_resolves_expired_redirects() {
+        // The first signed URL breaks off half way through the file, and has expired when it's continued
+        let (expired, expired_requests) = serve_raw(&[
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\nzip",
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let server = MockServer::start().await;
+        Mock::given(matchers::path("/asset"))
+            .respond_with(
+                ResponseTemplate::new(302)
+                    .insert_header("Location", format!("http://{expired}/signed?sig=1")),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/asset"))
+            .respond_with(
+                ResponseTemplate::new(302)
+                    .insert_header("Location", format!("{}/signed?sig=2", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(matchers::path("/signed"))
+            .and(matchers::header("range", "bytes=3-"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("Content-Range", "bytes 3-5/6")
+                    .set_body_string("ped"),
+            )
+            .mount(&server)
+            .await;
+
+        let mut sink = Vec::new();
+        let written = download_from(&server, "/asset", 6)
+            .download_into(http_client(), &mut sink, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!((written, sink.as_slice()), (6, b"zipped".as_slice()));
+        assert_eq!(
+            expired_requests.await.unwrap(),
+            [
+                ("/signed?sig=1".to_owned(), false),
+                ("/signed?sig=1".to_owned(), true),
+            ]
+        );
+        let requests = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|request| {
+                (
+                    request.url[url::Position::BeforePath..].to_owned(),
+                    request.headers.contains_key("range"),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requests,
+            [
+                ("/asset".to_owned(), false),
+                ("/asset".to_owned(), true),
+                ("/signed?sig=2".to_owned(), true),
+            ]
+        );
+    }
+}
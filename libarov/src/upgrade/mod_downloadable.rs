@@ -1,11 +1,18 @@
-use super::{from_gh_asset, from_gh_releases, DistributionDeniedError, DownloadData};
+use super::{
+    check::Explanation,
+    from_gh_asset, is_source_archive,
+    source::{GitHubReleaseSource, ReleaseSource as _},
+    DistributionDeniedError, DownloadData,
+};
 use crate::{
     config::{
         filters::Filter,
         structs::{Mod, ModIdentifier},
     },
-    GITHUB_API,
+    host_limit::{self, GITHUB_API_HOST},
 };
+use log::{debug, log_enabled, warn, Level};
+use octocrab::Octocrab;
 
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
@@ -15,56 +22,79 @@ pub enum Error {
     #[error("The pin provided is an invalid identifier")]
     InvalidPinID(#[from] std::num::ParseIntError),
     #[error("GitHub: {0:#?}")]
-    GitHubError(#[from] octocrab::Error),
+    GitHubError(octocrab::Error),
+    #[error("GitHub's secondary rate limit is still in effect after retrying, try again in a few minutes or pass `--wait`")]
+    SecondaryRateLimit(#[source] octocrab::Error),
+}
+
+impl From<octocrab::Error> for Error {
+    fn from(err: octocrab::Error) -> Self {
+        if host_limit::is_secondary_rate_limit(&err) {
+            Self::SecondaryRateLimit(err)
+        } else {
+            Self::GitHubError(err)
+        }
+    }
 }
 type Result<T> = std::result::Result<T, Error>;
 
 impl Mod {
+    /// Resolve the latest file compatible with `profile_filters` using `github_api`,
+    /// scanning at most `max_releases` of the most recent releases
     pub async fn fetch_download_file(
         &self,
-        mut profile_filters: Vec<Filter>,
+        github_api: &Octocrab,
+        profile_filters: Vec<Filter>,
+        max_releases: usize,
     ) -> Result<DownloadData> {
         match &self.identifier {
             ModIdentifier::PinnedGitHubRepository((owner, repo), pin) => Ok(from_gh_asset(
-                GITHUB_API
-                    .repos(owner, repo)
-                    .release_assets()
-                    .get(*pin as u64)
-                    .await?,
+                host_limit::send_retrying(GITHUB_API_HOST, || async {
+                    github_api
+                        .repos(owner, repo)
+                        .release_assets()
+                        .get(*pin as u64)
+                        .await
+                })
+                .await?,
             )),
             id => {
+                // Read the releases from wherever the mod is published
                 let download_files = match &id {
-                    ModIdentifier::GitHubRepository(owner, repo) => GITHUB_API
-                        .repos(owner, repo)
-                        .releases()
-                        .list()
-                        .send()
-                        .await
-                        .map(|r| from_gh_releases(r.items))?,
+                    ModIdentifier::GitHubRepository(owner, repo) => {
+                        GitHubReleaseSource {
+                            github_api,
+                            owner,
+                            repo,
+                            max_releases,
+                            source_archives: self.source_archives,
+                        }
+                        .fetch_candidates()
+                        .await?
+                    }
                     _ => unreachable!(),
                 };
 
                 // Find the best candidate using filters
                 let mut best_candidate = None;
-                let filters = profile_filters;
+                let filters = self.effective_filters(&profile_filters);
 
                 // Check each candidate against all filters
                 for (metadata, download_data) in &download_files {
-                    let mut passes_all = true;
-                    for filter in &filters {
-                        if !filter.matches(metadata).await? {
-                            passes_all = false;
-                            break;
-                        }
-                    }
-                    if passes_all {
+                    if metadata.passes(&filters).await? {
                         best_candidate = Some(download_data);
                         break; // Take the first (best) match since they're sorted by preference
                     }
                 }
 
                 match best_candidate {
-                    Some(download_data) => Ok(download_data.clone()),
+                    Some(download_data) => {
+                        // Callers warn about this themselves, see `is_source_archive`
+                        if is_source_archive(&download_data.filename()) {
+                            warn!(SCOPE = "libarov::upgrade::mod_downloadable", name = self.name.as_str(), tag = download_data.tag.as_deref().unwrap_or_default(); "no release archive, using the source code instead");
+                        }
+                        Ok(download_data.clone())
+                    }
                     None => {
                         // Fallback 1: if every candidate has empty game_versions and the only
                         // failing filters are GameVersion filters, allow the newest asset.
@@ -106,10 +136,53 @@ impl Mod {
                             return Ok(dd.clone());
                         }
 
-                        Err(super::check::Error::NoCompatibleFiles.into())
+                        if log_enabled!(Level::Debug) {
+                            let candidates = download_files
+                                .iter()
+                                .map(|(metadata, _)| metadata)
+                                .collect::<Vec<_>>();
+                            if let Ok(explanation) = Explanation::new(&candidates, &filters).await {
+                                debug!(SCOPE = "libarov::upgrade::mod_downloadable", name = self.name.as_str(); "no compatible files:\n{explanation}");
+                            }
+                        }
+                        match download_files.first() {
+                            Some((metadata, _)) => Err(metadata.rejection(&filters).await.into()),
+                            None => Err(super::check::Error::NoCompatibleFiles.into()),
+                        }
                     }
                 }
             }
         }
     }
+
+    /// Explain which filters reject which of the candidates [`Mod::fetch_download_file`] picks from,
+    /// to show why a mod didn't resolve, see [`Explanation`]
+    ///
+    /// Pinned mods aren't filtered, so they have no candidates.
+    pub async fn explain_resolution(
+        &self,
+        github_api: &Octocrab,
+        profile_filters: Vec<Filter>,
+        max_releases: usize,
+    ) -> Result<Explanation> {
+        let candidates = match &self.identifier {
+            ModIdentifier::GitHubRepository(owner, repo) => {
+                GitHubReleaseSource {
+                    github_api,
+                    owner,
+                    repo,
+                    max_releases,
+                    source_archives: self.source_archives,
+                }
+                .fetch_candidates()
+                .await?
+            }
+            ModIdentifier::PinnedGitHubRepository(..) => Vec::new(),
+        };
+        let candidates = candidates
+            .iter()
+            .map(|(metadata, _)| metadata)
+            .collect::<Vec<_>>();
+        Ok(Explanation::new(&candidates, &self.effective_filters(&profile_filters)).await?)
+    }
 }
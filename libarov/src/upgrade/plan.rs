@@ -0,0 +1,132 @@
+//! What upgrading a profile would download, without downloading or installing anything
+
+use super::{mod_downloadable, DownloadData};
+use crate::config::structs::Profile;
+use futures_util::future::join_all;
+use log::debug;
+use octocrab::Octocrab;
+
+/// What upgrading a single mod of a profile would download
+#[derive(Debug)]
+pub struct PlanItem {
+    /// The index of the mod in the profile's mods
+    pub index: usize,
+    /// The name of the mod
+    pub name: String,
+    /// The tag of the release that was last installed, to compare the resolved file with
+    pub installed_tag: Option<String>,
+    /// The latest compatible file, or why there isn't one
+    pub resolved: Result<DownloadData, mod_downloadable::Error>,
+}
+
+impl PlanItem {
+    /// Whether the resolved file is from a different release than the installed one,
+    /// which is the case for mods that haven't been installed yet too
+    pub fn is_outdated(&self) -> bool {
+        self.resolved
+            .as_ref()
+            .is_ok_and(|download| download.tag != self.installed_tag)
+    }
+}
+
+/// Resolve the latest compatible file of each of `profile`'s mods using `github_api`,
+/// scanning at most `max_releases` of each mod's most recent releases, and return them in the profile's order.
+///
+/// Mods are resolved concurrently, limited per host like every other request, see [`crate::host_limit`].
+/// A mod failing to resolve doesn't stop the others, its error is returned in its [`PlanItem::resolved`].
+pub async fn plan(github_api: &Octocrab, profile: &Profile, max_releases: usize) -> Vec<PlanItem> {
+    let items = join_all(
+        profile
+            .mods
+            .iter()
+            .enumerate()
+            .map(|(index, mod_)| async move {
+                PlanItem {
+                    index,
+                    name: mod_.name.clone(),
+                    installed_tag: mod_.installed_tag.clone(),
+                    resolved: mod_
+                        .fetch_download_file(github_api, profile.filters.clone(), max_releases)
+                        .await,
+                }
+            }),
+    )
+    .await;
+    debug!(
+        SCOPE = "libarov::upgrade::plan",
+        mods = items.len(),
+        outdated = items.iter().filter(|item| item.is_outdated()).count();
+        "planned upgrade"
+    );
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::structs::{Mod, ModIdentifier},
+        upgrade::check,
+    };
+    use std::path::PathBuf;
+
+    fn download(tag: &str) -> DownloadData {
+        DownloadData {
+            download_url: "https://example.com/Mod.zip".parse().unwrap(),
+            output: PathBuf::from("Mod.zip"),
+            length: 0,
+            tag: Some(tag.to_owned()),
+            dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn outdated_compares_installed_tag() {
+        let item = |installed_tag: Option<&str>, resolved| PlanItem {
+            index: 0,
+            name: "Mod".to_owned(),
+            installed_tag: installed_tag.map(ToOwned::to_owned),
+            resolved,
+        };
+
+        assert!(!item(Some("v1.0.0"), Ok(download("v1.0.0"))).is_outdated());
+        assert!(item(Some("v1.0.0"), Ok(download("v1.1.0"))).is_outdated());
+        assert!(item(None, Ok(download("v1.0.0"))).is_outdated());
+        assert!(!item(Some("v1.0.0"), Err(check::Error::NoCompatibleFiles.into())).is_outdated());
+    }
+
+    #[tokio::test]
+    async fn plan_has_an_item_per_mod_in_order() {
+        // Nothing listens on port 1, so every mod fails to resolve without touching the network
+        let github_api = Octocrab::builder()
+            .base_uri("http://127.0.0.1:1")
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut profile = Profile::new(
+            "Test".to_owned(),
+            PathBuf::new(),
+            vec!["3.11".to_owned()],
+            true,
+        );
+        for (name, installed_tag) in [("A", Some("v1.0.0")), ("B", None)] {
+            let mut mod_ = Mod::new(
+                name.to_owned(),
+                ModIdentifier::GitHubRepository("owner".to_owned(), name.to_owned()),
+                vec![],
+            );
+            mod_.installed_tag = installed_tag.map(ToOwned::to_owned);
+            profile.mods.push(mod_);
+        }
+
+        let items = plan(&github_api, &profile, crate::DEFAULT_MAX_RELEASES).await;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].index, items[0].name.as_str()), (0, "A"));
+        assert_eq!(items[0].installed_tag.as_deref(), Some("v1.0.0"));
+        assert_eq!((items[1].index, items[1].name.as_str()), (1, "B"));
+        assert!(items.iter().all(|item| item.resolved.is_err()));
+        assert!(!items.iter().any(PlanItem::is_outdated));
+    }
+}
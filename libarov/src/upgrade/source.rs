@@ -0,0 +1,62 @@
+//! Where the releases of a mod are read from
+//!
+//! Every host a mod can be published on implements [`ReleaseSource`],
+//! so resolving a mod's latest compatible file doesn't depend on where its releases come from.
+
+use super::{
+    fetch_gh_releases, fetch_gh_tags, from_gh_releases, from_gh_tags, mod_downloadable::Error,
+    DownloadData, Metadata,
+};
+use log::warn;
+use octocrab::Octocrab;
+use std::future::Future;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A host that the releases of a mod can be read from
+pub trait ReleaseSource {
+    /// Fetch the candidate files of the mod's recent releases, ordered by [`cmp_preference`](super::cmp_preference)
+    ///
+    /// Fails with [`NoArchiveAssets`](super::check::Error::NoArchiveAssets) if there are releases,
+    /// but none of them have a file that can be installed,
+    /// and with [`NoReleases`](super::check::Error::NoReleases) if there's nothing released at all.
+    fn fetch_candidates(
+        &self,
+    ) -> impl Future<Output = Result<Vec<(Metadata, DownloadData)>>> + Send;
+}
+
+/// The releases of a GitHub repository
+///
+/// Repositories that push tags without publishing any releases fall back to the source code archives of their tags.
+#[derive(Debug, Clone)]
+pub struct GitHubReleaseSource<'a> {
+    pub github_api: &'a Octocrab,
+    pub owner: &'a str,
+    pub repo: &'a str,
+    /// How many of the most recent releases to scan
+    pub max_releases: usize,
+    /// Whether releases without archive assets fall back to their source code archive
+    pub source_archives: bool,
+}
+
+impl ReleaseSource for GitHubReleaseSource<'_> {
+    async fn fetch_candidates(&self) -> Result<Vec<(Metadata, DownloadData)>> {
+        let releases =
+            fetch_gh_releases(self.github_api, self.owner, self.repo, self.max_releases).await?;
+        if releases.is_empty() {
+            let tags =
+                fetch_gh_tags(self.github_api, self.owner, self.repo, self.max_releases).await?;
+            if tags.is_empty() {
+                return Err(super::check::Error::NoReleases.into());
+            }
+            warn!(SCOPE = "libarov::upgrade::source", owner = self.owner, repo = self.repo, tags = tags.len(); "no releases, falling back to the source archives of tags");
+            return Ok(from_gh_tags(tags, self.repo));
+        }
+        let candidates = from_gh_releases(releases, self.source_archives.then_some(self.repo));
+        if candidates.is_empty() {
+            // Tell this apart from the filters excluding every file
+            return Err(super::check::Error::NoArchiveAssets.into());
+        }
+        Ok(candidates)
+    }
+}
@@ -1,17 +1,236 @@
+use crate::{default_semaphore, SEMAPHORE, STYLE_ADD, TICK};
+use anyhow::{bail, ensure, Result};
+use chrono::{DateTime, Utc};
 use colored::Colorize as _;
-use libarov::{add::Error, iter_ext::IterExt as _};
-use std::collections::HashMap;
+use indicatif::ProgressBar;
+use inquire::MultiSelect;
+use libarov::{
+    add::{add_batch, AddOptions, AddOutcome, Error},
+    config::structs::{ModIdentifier, Profile},
+    host_limit::{self, GITHUB_API_HOST},
+    iter_ext::IterExt as _,
+    upgrade::fetch_gh_releases,
+};
+use octocrab::Octocrab;
+use std::{collections::HashMap, io::IsTerminal as _};
+use tokio::task::JoinSet;
 
-pub fn display_successes_failures(successes: &[String], failures: Vec<(String, Error)>) -> bool {
+/// How many repositories with a topic are offered to pick from
+const TOPIC_CANDIDATES: u8 = 30;
+
+/// A repository found by its topic that can be added
+#[derive(Debug)]
+pub struct TopicCandidate {
+    pub owner: String,
+    pub repo: String,
+    pub stars: u32,
+    /// When the latest release was published, if there is one
+    pub last_release: Option<DateTime<Utc>>,
+}
+
+/// Search GitHub for the most starred repositories with `topic`, leaving out the ones already in `profile`,
+/// and get when each of them last released
+pub async fn topic_candidates(
+    github_api: &Octocrab,
+    profile: &Profile,
+    topic: &str,
+) -> Result<Vec<TopicCandidate>> {
+    let page = {
+        let _permit = host_limit::acquire(GITHUB_API_HOST).await;
+        github_api
+            .search()
+            .repositories(&format!("topic:{topic}"))
+            .sort("stars")
+            .order("desc")
+            .per_page(TOPIC_CANDIDATES)
+            .send()
+            .await?
+    };
+
+    let mut tasks = JoinSet::new();
+    for repository in page.items {
+        let Some(owner) = repository.owner.map(|owner| owner.login) else {
+            continue;
+        };
+        let repo = repository.name;
+        let added = profile.mods.iter().any(|mod_| {
+            matches!(
+                &mod_.identifier,
+                ModIdentifier::GitHubRepository(o, r) | ModIdentifier::PinnedGitHubRepository((o, r), _)
+                    if o.eq_ignore_ascii_case(&owner) && r.eq_ignore_ascii_case(&repo),
+            )
+        });
+        if added {
+            continue;
+        }
+        let github_api = github_api.clone();
+        let stars = repository.stargazers_count.unwrap_or_default();
+        tasks.spawn(async move {
+            let _permit = SEMAPHORE.get_or_init(default_semaphore).acquire().await?;
+            // `fetch_gh_releases` waits for its own host permit
+            let last_release = fetch_gh_releases(&github_api, &owner, &repo, 1)
+                .await?
+                .first()
+                .and_then(|release| release.published_at.or(release.created_at));
+            Ok::<_, anyhow::Error>(TopicCandidate {
+                owner,
+                repo,
+                stars,
+                last_release,
+            })
+        });
+    }
+
+    let mut candidates = tasks
+        .join_all()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    candidates.sort_unstable_by(|a, b| b.stars.cmp(&a.stars).then_with(|| a.repo.cmp(&b.repo)));
+    Ok(candidates)
+}
+
+/// Let the user pick which of the repositories with `topic` to add to `profile`
+///
+/// Fails if stdin isn't a terminal, as there's nobody to pick.
+pub async fn pick_from_topic(
+    github_api: &Octocrab,
+    profile: &Profile,
+    topic: &str,
+) -> Result<Vec<ModIdentifier>> {
+    ensure!(
+        std::io::stdin().is_terminal(),
+        "Picking mods by topic needs an interactive terminal, add them by `owner/repo` instead"
+    );
+    eprint!("Searching for repositories with the topic {topic}... ");
+    let candidates = topic_candidates(github_api, profile, topic).await?;
+    eprintln!("{}", &*TICK);
+    if candidates.is_empty() {
+        bail!("No repositories with the topic {topic} that aren't in the profile already");
+    }
+
+    let options = candidates
+        .iter()
+        .map(|candidate| {
+            format!(
+                "{}/{}  {} stars  {}",
+                candidate.owner,
+                candidate.repo,
+                candidate.stars,
+                candidate.last_release.map_or_else(
+                    || "no releases".to_owned(),
+                    |date| format!("last released {}", date.format("%Y-%m-%d"))
+                )
+            )
+        })
+        .collect_vec();
+    Ok(MultiSelect::new("Which mods do you want to add?", options)
+        .raw_prompt_skippable()?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|option| {
+            let candidate = &candidates[option.index];
+            ModIdentifier::GitHubRepository(candidate.owner.clone(), candidate.repo.clone())
+        })
+        .collect())
+}
+
+/// Add the mods of `identifiers` to `profile` one at a time, showing how many have been added so far,
+/// and return the outcome for each of them in order
+///
+/// See [`add_batch`] for what the `options` do.
+pub async fn add_with_progress(
+    github_api: &Octocrab,
+    profile: &mut Profile,
+    identifiers: Vec<ModIdentifier>,
+    options: &AddOptions,
+) -> Vec<AddOutcome> {
+    let progress_bar = ProgressBar::new(identifiers.len() as u64).with_style(STYLE_ADD.clone());
+    let mut outcomes = Vec::with_capacity(identifiers.len());
+    for identifier in identifiers {
+        progress_bar.set_message(match &identifier {
+            ModIdentifier::GitHubRepository(owner, repo)
+            | ModIdentifier::PinnedGitHubRepository((owner, repo), _) => format!("{owner}/{repo}"),
+        });
+        outcomes.extend(add_batch(github_api, profile, vec![identifier], options).await);
+        progress_bar.inc(1);
+    }
+    progress_bar.finish_and_clear();
+    outcomes
+}
+
+/// A line counting how many of the `total` mods were added, updated, already present, or failed
+pub fn summary(
+    total: usize,
+    successes: &[String],
+    updated: &[String],
+    failures: &[(String, Error)],
+) -> String {
+    let already_added = failures
+        .iter()
+        .filter(|(_, err)| matches!(err, Error::AlreadyAdded))
+        .count();
+    let mut counts = vec![format!("{} added", successes.len()).green().to_string()];
+    if !updated.is_empty() {
+        counts.push(format!("{} updated", updated.len()).green().to_string());
+    }
+    if already_added > 0 {
+        counts.push(
+            format!("{already_added} already present")
+                .yellow()
+                .to_string(),
+        );
+    }
+    if failures.len() > already_added {
+        counts.push(
+            format!("{} failed", failures.len() - already_added)
+                .red()
+                .to_string(),
+        );
+    }
+    format!("{} {}", format!("{total} mods:").bold(), counts.join(", "))
+}
+
+/// Print which mods were added, updated, or failed, and return whether any failed
+///
+/// With `dry_run`, the mods are reported as what would have happened to them.
+pub fn display_successes_failures(
+    successes: &[String],
+    updated: &[String],
+    warnings: &[(String, String)],
+    failures: Vec<(String, Error)>,
+    dry_run: bool,
+) -> bool {
     if !successes.is_empty() {
         println!(
             "{} {}",
-            "Successfully added".green(),
+            if dry_run {
+                "Would add"
+            } else {
+                "Successfully added"
+            }
+            .green(),
             successes.iter().map(|s| s.bold()).display(", ")
         );
+    }
+    if !updated.is_empty() {
+        println!(
+            "{} {}",
+            if dry_run {
+                "Would update"
+            } else {
+                "Successfully updated"
+            }
+            .green(),
+            updated.iter().map(|s| s.bold()).display(", ")
+        );
+    }
+    for (name, warning) in warnings {
+        println!("{}: {}", name.bold(), warning.yellow());
+    }
 
     // No need to print the ID again if there is only one
-    } else if failures.len() == 1 {
+    if successes.is_empty() && updated.is_empty() && failures.len() == 1 {
         let err = &failures[0].1;
         return if matches!(err, libarov::add::Error::AlreadyAdded) {
             println!("{}", err.to_string().yellow());
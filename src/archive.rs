@@ -0,0 +1,342 @@
+use anyhow::{Context as _, Result};
+use sevenz_rust::{Password, SevenZReader};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+/// The archive formats mods can be installed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    /// Determine the format of the archive at `path` from its extension
+    ///
+    /// Every extension in [`libarov::ARCHIVE_EXTENSIONS`] has a format.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "zip" => Some(Self::Zip),
+            "7z" => Some(Self::SevenZ),
+            _ => None,
+        }
+    }
+}
+
+/// How the contents of a mod archive are laid out, see [`ArchiveAnalyzer::analyze_layout`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// A client mod with its plugins in `BepInEx/plugins`
+    BepInExPlugin,
+    /// An SPT server mod in `user/mods`
+    ServerMod,
+    /// Plugin DLLs at the archive's root, which are installed into the plugins directory
+    LooseDlls,
+    /// Everything is in a single folder, e.g. one named after the mod
+    WrappedSingleFolder,
+    /// None of the above, so the archive probably won't install anything
+    Unknown,
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::BepInExPlugin => "BepInEx plugin",
+            Self::ServerMod => "server mod",
+            Self::LooseDlls => "loose DLLs",
+            Self::WrappedSingleFolder => "wrapped in a single folder",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+impl Layout {
+    /// Classify the `files` of an archive, as listed by [`ArchiveAnalyzer::extract_file_list`]
+    ///
+    /// Archives with both client plugins and a server mod are classified as [`Layout::BepInExPlugin`].
+    pub fn from_files(files: &[PathBuf]) -> Self {
+        fn starts_with(file: &Path, dirs: &[&str]) -> bool {
+            let mut components = file.components();
+            dirs.iter().all(|dir| {
+                components
+                    .next()
+                    .is_some_and(|c| c.as_os_str().eq_ignore_ascii_case(dir))
+            })
+        }
+
+        if files
+            .iter()
+            .any(|file| starts_with(file, &["BepInEx", "plugins"]))
+        {
+            Self::BepInExPlugin
+        } else if files
+            .iter()
+            .any(|file| starts_with(file, &["user", "mods"]))
+        {
+            Self::ServerMod
+        } else if files.iter().any(|file| {
+            file.components().count() == 1
+                && file
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("dll"))
+        }) {
+            Self::LooseDlls
+        } else if let Some(first) = files.first().and_then(|file| file.components().next()) {
+            if files.iter().all(|file| {
+                file.components().count() > 1 && file.components().next() == Some(first)
+            }) {
+                Self::WrappedSingleFolder
+            } else {
+                Self::Unknown
+            }
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+/// Inspects the contents of mod archives without extracting them
+pub struct ArchiveAnalyzer;
+
+impl ArchiveAnalyzer {
+    /// List the files in the archive at `path`, as paths relative to the archive's root
+    pub fn extract_file_list(path: &Path) -> Result<Vec<PathBuf>> {
+        let format = ArchiveFormat::from_path(path)
+            .with_context(|| format!("{} is not a supported archive", path.display()))?;
+        let file = File::open(path)?;
+        Self::extract_file_list_from_reader(file, format)
+            .with_context(|| format!("Failed to read archive {}", path.display()))
+    }
+
+    /// List the files in the `format` archive read from `reader`, as paths relative to the archive's root
+    ///
+    /// This works on archives that were never written to disk, e.g. a download in a `Cursor<Vec<u8>>`.
+    pub fn extract_file_list_from_reader(
+        mut reader: impl Read + Seek,
+        format: ArchiveFormat,
+    ) -> Result<Vec<PathBuf>> {
+        match format {
+            ArchiveFormat::Zip => {
+                let mut archive = ZipArchive::new(reader)?;
+                let mut files = Vec::new();
+                for i in 0..archive.len() {
+                    let file = archive.by_index(i)?;
+                    if file.is_file() {
+                        files.push(file.mangled_name());
+                    }
+                }
+                Ok(files)
+            }
+            ArchiveFormat::SevenZ => {
+                let len = reader.seek(SeekFrom::End(0))?;
+                reader.rewind()?;
+                let archive = SevenZReader::new(reader, len, Password::empty())?;
+                Ok(archive
+                    .archive()
+                    .files
+                    .iter()
+                    .filter(|entry| !entry.is_directory())
+                    .map(|entry| PathBuf::from(entry.name()))
+                    .collect())
+            }
+        }
+    }
+
+    /// Classify how the archive at `path` is laid out, see [`Layout::from_files`]
+    ///
+    /// Archives that can't be read are [`Layout::Unknown`].
+    pub fn analyze_layout(path: &Path) -> Layout {
+        Self::extract_file_list(path).map_or(Layout::Unknown, |files| Layout::from_files(&files))
+    }
+
+    /// Read the `sptVersion` from the `package.json` of the SPT server mod in the archive at `path`
+    ///
+    /// Returns `None` if the archive can't be read or has no `package.json` with an `sptVersion`,
+    /// e.g. when it only contains BepInEx plugins.
+    pub fn read_spt_version_from_archive(path: &Path) -> Option<String> {
+        let format = ArchiveFormat::from_path(path)?;
+        Self::read_spt_version_from_reader(File::open(path).ok()?, format)
+    }
+
+    /// Read the `sptVersion` from the `package.json` of the SPT server mod in the `format` archive read from `reader`
+    ///
+    /// The `package.json` closest to the archive's root is used, so those of bundled dependencies are ignored.
+    pub fn read_spt_version_from_reader(
+        mut reader: impl Read + Seek,
+        format: ArchiveFormat,
+    ) -> Option<String> {
+        fn shallowest_package_json<'a>(names: impl Iterator<Item = &'a str>) -> Option<String> {
+            names
+                .filter(|name| name == &"package.json" || name.ends_with("/package.json"))
+                .min_by_key(|name| name.matches('/').count())
+                .map(ToOwned::to_owned)
+        }
+
+        let mut json = String::new();
+        match format {
+            ArchiveFormat::Zip => {
+                let mut archive = ZipArchive::new(reader).ok()?;
+                let name = shallowest_package_json(archive.file_names())?;
+                archive
+                    .by_name(&name)
+                    .ok()?
+                    .read_to_string(&mut json)
+                    .ok()?;
+            }
+            ArchiveFormat::SevenZ => {
+                let len = reader.seek(SeekFrom::End(0)).ok()?;
+                reader.rewind().ok()?;
+                let mut archive = SevenZReader::new(reader, len, Password::empty()).ok()?;
+                let name = shallowest_package_json(
+                    archive.archive().files.iter().map(|entry| entry.name()),
+                )?;
+                archive
+                    .for_each_entries(|entry, entry_reader| {
+                        if entry.name() == name {
+                            entry_reader.read_to_string(&mut json)?;
+                            Ok(false)
+                        } else {
+                            // Entries are decompressed in sequence, so skipped ones still have to be read
+                            io::copy(entry_reader, &mut io::sink())?;
+                            Ok(true)
+                        }
+                    })
+                    .ok()?;
+            }
+        }
+
+        let package: serde_json::Value = serde_json::from_str(&json).ok()?;
+        package["sptVersion"].as_str().map(ToOwned::to_owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write as _};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    #[test]
+    fn file_list_from_memory() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.add_directory("BepInEx/plugins/", SimpleFileOptions::default())
+            .unwrap();
+        zip.start_file("BepInEx/plugins/Mod.dll", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"dll").unwrap();
+        zip.start_file("user/mods/Mod/package.json", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"{}").unwrap();
+        let archive = zip.finish().unwrap();
+
+        let files =
+            ArchiveAnalyzer::extract_file_list_from_reader(archive, ArchiveFormat::Zip).unwrap();
+
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("BepInEx/plugins/Mod.dll"),
+                PathBuf::from("user/mods/Mod/package.json"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spt_version_from_package_json() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(
+            "user/mods/Mod/node_modules/dep/package.json",
+            SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(br#"{"sptVersion": "~3.9.0"}"#).unwrap();
+        zip.start_file("user/mods/Mod/package.json", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(br#"{"name": "Mod", "sptVersion": "~3.11.0"}"#)
+            .unwrap();
+        let archive = zip.finish().unwrap();
+
+        assert_eq!(
+            ArchiveAnalyzer::read_spt_version_from_reader(archive, ArchiveFormat::Zip).as_deref(),
+            Some("~3.11.0")
+        );
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("BepInEx/plugins/Mod.dll", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"dll").unwrap();
+        let archive = zip.finish().unwrap();
+        assert!(
+            ArchiveAnalyzer::read_spt_version_from_reader(archive, ArchiveFormat::Zip).is_none()
+        );
+    }
+
+    /// A zip archive of `files` in a temporary directory, which is deleted when it's dropped
+    fn zip_with(files: &[&str]) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Mod.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for file in files {
+            zip.start_file(*file, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"contents").unwrap();
+        }
+        zip.finish().unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn layout_of_each_shape() {
+        for (files, layout) in [
+            (
+                &["BepInEx/plugins/Mod.dll", "BepInEx/config/Mod.cfg"][..],
+                Layout::BepInExPlugin,
+            ),
+            (
+                &["BepInEx/plugins/Mod.dll", "user/mods/Mod/package.json"],
+                Layout::BepInExPlugin,
+            ),
+            (
+                &["user/mods/Mod/package.json", "user/mods/Mod/src/mod.js"],
+                Layout::ServerMod,
+            ),
+            (&["Mod.dll", "README.md"], Layout::LooseDlls),
+            (
+                &["Mod/BepInEx/plugins/Mod.dll", "Mod/README.md"],
+                Layout::WrappedSingleFolder,
+            ),
+            (&["README.md", "docs/guide.md"], Layout::Unknown),
+            (&["README.md"], Layout::Unknown),
+        ] {
+            let (_dir, path) = zip_with(files);
+            assert_eq!(ArchiveAnalyzer::analyze_layout(&path), layout, "{files:?}");
+        }
+
+        assert_eq!(
+            ArchiveAnalyzer::analyze_layout(Path::new("Missing.zip")),
+            Layout::Unknown
+        );
+    }
+
+    #[test]
+    fn supported_archives_have_a_format() {
+        for extension in libarov::ARCHIVE_EXTENSIONS {
+            let path = PathBuf::from(format!("Mod.{extension}"));
+            assert!(libarov::is_supported_archive(&path.to_string_lossy()));
+            assert!(ArchiveFormat::from_path(&path).is_some(), "{extension}");
+        }
+    }
+
+    #[test]
+    fn file_list_rejects_unsupported_archives() {
+        assert!(ArchiveAnalyzer::extract_file_list(Path::new("Mod.rar")).is_err());
+        assert!(ArchiveAnalyzer::extract_file_list_from_reader(
+            Cursor::new(b"not an archive".to_vec()),
+            ArchiveFormat::Zip
+        )
+        .is_err());
+    }
+}
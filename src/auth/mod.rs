@@ -3,12 +3,18 @@
 //! This module handles GitHub App authentication using embedded credentials.
 //! No user authentication is required - credentials are embedded at build time.
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context as _, Result};
+use chrono::{DateTime, TimeDelta, Utc};
 use colored::Colorize;
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// How far the local clock can be off from GitHub's before JWTs are likely to be rejected with a 401.
+/// JWTs are issued 10 seconds in the past to allow for some drift, see [`GitHubAppConfig::generate_jwt`]
+pub const MAX_CLOCK_SKEW: TimeDelta = TimeDelta::seconds(30);
+
 /// GitHub App configuration
 #[derive(Debug, Clone)]
 pub struct GitHubAppConfig {
@@ -102,7 +108,7 @@ impl GitHubAppConfig {
 impl GitHubAppClient {
     /// Create a new GitHub App client
     pub fn new(config: GitHubAppConfig) -> Self {
-        let client = reqwest::Client::new();
+        let client = libarov::http_client();
         Self { config, client }
     }
 
@@ -160,7 +166,7 @@ impl GitHubAppClient {
     /// Test the GitHub App authentication
     pub async fn test_authentication(&self) -> Result<()> {
         let token = self.get_installation_token().await?;
-        let client = reqwest::Client::new();
+        let client = libarov::http_client();
         let response = client
             .get("https://api.github.com/rate_limit")
             .header("Authorization", format!("Bearer {}", token))
@@ -184,7 +190,7 @@ impl GitHubAppClient {
     /// Get current rate limit information
     pub async fn get_rate_limit_info(&self) -> Result<RateLimitInfo> {
         let token = self.get_installation_token().await?;
-        let client = reqwest::Client::new();
+        let client = libarov::http_client();
         let response = client
             .get("https://api.github.com/rate_limit")
             .header("Authorization", format!("Bearer {}", token))
@@ -276,6 +282,74 @@ pub async fn handle_auth_command() -> Result<()> {
     Ok(())
 }
 
+/// Measure how far the local clock is ahead of GitHub's, or behind it if negative,
+/// from the `Date` header of a GitHub API response
+///
+/// The local time is taken halfway through the request so that latency mostly cancels out,
+/// but the header only has whole seconds.
+pub async fn measure_clock_skew() -> Result<TimeDelta> {
+    let base = std::env::var(libarov::GITHUB_API_URL_ENV)
+        .unwrap_or_else(|_| "https://api.github.com".to_owned());
+    measure_clock_skew_at(&format!("{}/rate_limit", base.trim_end_matches('/'))).await
+}
+
+/// [`measure_clock_skew`] against the server at `url`
+async fn measure_clock_skew_at(url: &str) -> Result<TimeDelta> {
+    let sent = Utc::now();
+    let response = libarov::http_client()
+        .head(url)
+        .header(
+            "User-Agent",
+            "tarium/5.0.0 (https://github.com/NQMVD/tarium)",
+        )
+        .send()
+        .await?;
+    let received = Utc::now();
+
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .context("GitHub's response has no Date header to compare the clock with")?
+        .to_str()?;
+    let server = DateTime::parse_from_rfc2822(date)
+        .with_context(|| format!("Invalid Date header {date:?}"))?;
+    let local = sent + (received - sent) / 2;
+    let skew = local.signed_duration_since(server);
+    debug!(SCOPE = "auth::clock", skew_ms = skew.num_milliseconds(), server_date = date; "measured clock skew");
+    Ok(skew)
+}
+
+/// A warning about the local clock if it's off from GitHub's by more than [`MAX_CLOCK_SKEW`]
+pub fn clock_skew_warning(skew: TimeDelta) -> Option<String> {
+    (skew.abs() > MAX_CLOCK_SKEW).then(|| {
+        format!(
+            "Your clock is {} seconds {} GitHub's, which makes GitHub App authentication fail. Sync your system clock and try again",
+            skew.num_seconds().abs(),
+            if skew > TimeDelta::zero() {
+                "ahead of"
+            } else {
+                "behind"
+            },
+        )
+    })
+}
+
+/// Measure the clock skew and warn if it's too large, see [`clock_skew_warning`]
+///
+/// Failing to measure it is only logged, since it's just a diagnosis.
+pub async fn warn_clock_skew() {
+    match measure_clock_skew().await {
+        Ok(skew) => {
+            if let Some(warning) = clock_skew_warning(skew) {
+                println!("{}", warning.yellow().bold());
+            }
+        }
+        Err(e) => {
+            warn!(SCOPE = "auth::clock", error:display = format!("{e:#}"); "failed to measure clock skew");
+        }
+    }
+}
+
 /// Initialize GitHub App authentication for the application
 pub async fn initialize_github_app() -> Result<()> {
     if let Some(client) = GitHubAppClient::embedded() {
@@ -310,12 +384,15 @@ pub async fn get_github_client() -> reqwest::Client {
                 ),
             );
 
-            if let Ok(client) = reqwest::Client::builder().default_headers(headers).build() {
+            if let Ok(client) = libarov::http_client_builder()
+                .default_headers(headers)
+                .build()
+            {
                 return client;
             }
         }
     }
-    reqwest::Client::new()
+    libarov::http_client()
 }
 
 #[cfg(test)]
@@ -335,6 +412,30 @@ mod tests {
         assert_eq!(config.private_key, "test-key");
     }
 
+    #[tokio::test]
+    async fn measures_clock_skew_from_date_header() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let behind = Utc::now() - TimeDelta::minutes(2);
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(200).insert_header(
+                "Date",
+                behind.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+            ))
+            .mount(&server)
+            .await;
+
+        let skew = measure_clock_skew_at(&server.uri()).await.unwrap();
+        assert!(
+            (skew - TimeDelta::minutes(2)).abs() <= TimeDelta::seconds(2),
+            "{skew}"
+        );
+        assert!(clock_skew_warning(skew).unwrap().contains("ahead of"));
+        assert!(clock_skew_warning(-skew).unwrap().contains("behind"));
+        assert!(clock_skew_warning(TimeDelta::seconds(5)).is_none());
+    }
+
     #[tokio::test]
     async fn test_get_github_client() {
         let client = get_github_client().await;
@@ -20,11 +20,15 @@ use tokio::task::JoinSet;
 /// - If a file in `to_download` or `to_install` is already there, it will be removed from the respective vector
 /// - If there are `.part` files, they will be deleted
 /// - Other files are left in place (SPT mod manager doesn't need to move old files to .old)
+///
+/// Returns the number of bytes freed by deleting files
 pub async fn clean(
     directory: &Path,
     to_download: &mut Vec<DownloadData>,
     to_install: &mut Vec<(OsString, PathBuf)>,
-) -> Result<()> {
+) -> Result<u64> {
+    let mut freed = 0;
+
     let dupes = find_dupes_by_key(to_download, DownloadData::filename);
     if !dupes.is_empty() {
         println!(
@@ -63,11 +67,12 @@ pub async fn clean(
             // If the file is a `.part` file, delete it
             } else if filename.ends_with("part") {
                 warn!(SCOPE = "src::download::clean", path:display = file.path().display().to_string(); "deleting partial file");
+                freed += file.metadata()?.len();
                 remove_file(file.path())?;
             }
         }
     }
-    Ok(())
+    Ok(freed)
 }
 
 /// Construct a `to_install` vector from the `directory`
@@ -83,11 +88,13 @@ pub fn read_overrides(directory: &Path) -> Result<Vec<(OsString, PathBuf)>> {
 }
 
 /// Download and install the files in `to_download` and `to_install` to `output_dir`
+///
+/// Returns the filename and number of bytes downloaded of each file in `to_download`
 pub async fn download(
     output_dir: PathBuf,
     to_download: Vec<DownloadData>,
     to_install: Vec<(OsString, PathBuf)>,
-) -> Result<()> {
+) -> Result<Vec<(String, u64)>> {
     // let progress_bar = Arc::new(Mutex::new(
     //     ProgressBar::new(
     //         to_download
@@ -101,7 +108,7 @@ pub async fn download(
     //     .lock()
     //     .enable_steady_tick(Duration::from_millis(100));
     let mut tasks = JoinSet::new();
-    let client = reqwest::Client::new();
+    let client = libarov::http_client();
 
     for downloadable in to_download {
         // let progress_bar = Arc::clone(&progress_bar);
@@ -111,26 +118,40 @@ pub async fn download(
         tasks.spawn(async move {
             let _permit = SEMAPHORE.get_or_init(default_semaphore).acquire().await?;
 
+            let speed = Mutex::new(0.);
             let (length, filename) = downloadable
-                .download(client, &output_dir, |_additional| {
-                    // progress_bar.lock().inc(additional as u64);
+                .download_with_progress(client, &output_dir, |progress| {
+                    // progress_bar.lock().inc(progress.chunk as u64);
+                    *speed.lock() = progress.bytes_per_sec();
                 })
                 .await?;
+            // When throttled, show that the limit is being kept to
+            let speed = libarov::throttle::max_download_speed().map_or_else(String::new, |_| {
+                format!(
+                    "  {}/s",
+                    size::Size::from_bytes(*speed.lock())
+                        .format()
+                        .with_base(size::Base::Base10)
+                )
+            });
             println!(
-                "{} Downloaded  {:>7}  {}",
+                "{} Downloaded  {:>7}{}  {}",
                 &*TICK,
                 size::Size::from_bytes(length)
                     .format()
                     .with_base(size::Base::Base10)
                     .to_string(),
+                speed.dimmed(),
                 filename.dimmed(),
             );
-            Ok::<(), Error>(())
+            Ok::<_, Error>((filename, length as u64))
         });
     }
-    for res in tasks.join_all().await {
-        res?;
-    }
+    let downloaded = tasks
+        .join_all()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
     // Arc::try_unwrap(progress_bar)
     //     .map_err(|_| anyhow!("Failed to run threads to completion"))?
     //     .into_inner()
@@ -156,7 +177,7 @@ pub async fn download(
         );
     }
 
-    Ok(())
+    Ok(downloaded)
 }
 
 /// Find duplicates of the items in `slice` using a value obtained by the `key` closure
@@ -2,8 +2,8 @@ use beef::Cow;
 use chrono::Local;
 use colored::Colorize;
 use log::kv::{Key, Value, VisitSource};
-use log::{kv, Level, LevelFilter};
-use std::collections::BTreeMap;
+use log::{kv, Level, LevelFilter, Record};
+use std::{collections::BTreeMap, fmt, fs::create_dir_all};
 
 // https://docs.rs/log/latest/log/kv/index.html
 
@@ -17,6 +17,105 @@ impl<'kvs> VisitSource<'kvs> for Collect<'kvs> {
     }
 }
 
+/// Paints `text` with the given RGB colour, or leaves it plain when `color` is false
+fn paint(text: &str, (r, g, b): (u8, u8, u8), color: bool) -> String {
+    if color {
+        text.truecolor(r, g, b).to_string()
+    } else {
+        text.to_owned()
+    }
+}
+
+const TRACE_RGB: (u8, u8, u8) = (144, 144, 144);
+const DEBUG_RGB: (u8, u8, u8) = (95, 96, 255);
+const INFO_RGB: (u8, u8, u8) = (99, 254, 218);
+const WARN_RGB: (u8, u8, u8) = (219, 254, 143);
+const ERROR_RGB: (u8, u8, u8) = (254, 95, 136);
+const KEY_RGB: (u8, u8, u8) = (142, 142, 142);
+
+/// Formats a log record, only emitting ANSI escape codes if `color` is true
+fn format_record(
+    out: fern::FormatCallback,
+    message: &fmt::Arguments,
+    record: &Record,
+    color: bool,
+) {
+    let time = Local::now().format("%H:%M");
+    let lvl_plain = format!("{:>5}", record.level());
+    let lvl_rgb = match record.level() {
+        Level::Trace => TRACE_RGB,
+        Level::Debug => DEBUG_RGB,
+        Level::Info => INFO_RGB,
+        Level::Warn => WARN_RGB,
+        Level::Error => ERROR_RGB,
+    };
+    let lvl_colored = paint(&lvl_plain, lvl_rgb, color);
+
+    let has_kvs = record.key_values().count() > 0;
+    if has_kvs {
+        let mut visitor = Collect(BTreeMap::new());
+        let _ = record.key_values().visit(&mut visitor);
+        let collected = visitor.0;
+        let (single, multiline) = collected
+            .iter()
+            .partition::<Vec<_>, _>(|(_, v)| !v.to_string().contains('\n'));
+
+        let scope = single
+            .iter()
+            .find(|(k, _)| *k == "SCOPE")
+            .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let eq = paint("=", KEY_RGB, color);
+
+        let formatted_pairs = single
+            .iter()
+            .filter(|(k, _)| *k != "SCOPE")
+            .map(|(k, v)| format!("{}{eq}{v}", paint(k, KEY_RGB, color)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let vb = paint("┊", KEY_RGB, color);
+        let formatted_multiline_pairs = multiline
+            .iter()
+            .map(|(k, v)| {
+                format!(
+                    "{}{eq}\n  {vb} {}",
+                    paint(k, KEY_RGB, color),
+                    v.lines()
+                        .collect::<Vec<_>>()
+                        .join(format!("\n  {vb} ").as_str())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n  ");
+
+        out.finish(format_args!(
+            "{time} {lvl_colored} {}{message}{}{}",
+            if let Some((_, v)) = scope {
+                if color {
+                    format!("[{}] ", v.bold())
+                } else {
+                    format!("[{v}] ")
+                }
+            } else {
+                String::new()
+            },
+            if !single.is_empty() {
+                format!(" {}", formatted_pairs)
+            } else {
+                String::new()
+            },
+            if !multiline.is_empty() {
+                format!("\n  {}", formatted_multiline_pairs)
+            } else {
+                String::new()
+            }
+        ));
+    } else {
+        out.finish(format_args!("{time} {lvl_colored} {message}"));
+    }
+}
+
 pub fn setup_logger(verbosity: u8) -> Result<(), fern::InitError> {
     let log_level = match verbosity {
         0 => LevelFilter::Error,
@@ -26,94 +125,28 @@ pub fn setup_logger(verbosity: u8) -> Result<(), fern::InitError> {
         _ => LevelFilter::Trace,
     };
 
-    const TRACE_RGB: (u8, u8, u8) = (144, 144, 144);
-    const DEBUG_RGB: (u8, u8, u8) = (95, 96, 255);
-    const INFO_RGB: (u8, u8, u8) = (99, 254, 218);
-    const WARN_RGB: (u8, u8, u8) = (219, 254, 143);
-    const ERROR_RGB: (u8, u8, u8) = (254, 95, 136);
+    // Written to the data directory rather than wherever tarium is run from, so `tarium log` can find it
+    let log_dir = libarov::logs_dir();
+    create_dir_all(&log_dir)?;
+
+    // Honours `NO_COLOR`/`CLICOLOR_FORCE` and only colours stdout when it is a TTY
+    let stdout_color = colored::control::SHOULD_COLORIZE.should_colorize();
 
     fern::Dispatch::new()
-        .format(move |out, message, record| {
-            let time = Local::now().format("%H:%M");
-            let lvl_plain = format!("{:>5}", record.level());
-            let (r, g, b) = match record.level() {
-                Level::Trace => TRACE_RGB,
-                Level::Debug => DEBUG_RGB,
-                Level::Info => INFO_RGB,
-                Level::Warn => WARN_RGB,
-                Level::Error => ERROR_RGB,
-            };
-            let lvl_colored = lvl_plain.truecolor(r, g, b);
-
-            let has_kvs = record.key_values().count() > 0;
-            if has_kvs {
-                let mut visitor = Collect(BTreeMap::new());
-                let _ = record.key_values().visit(&mut visitor);
-                let collected = visitor.0;
-                let (single, multiline) = collected
-                    .iter()
-                    .partition::<Vec<_>, _>(|(_, v)| !v.to_string().contains('\n'));
-
-                let scope = single
-                    .iter()
-                    .find(|(k, _)| *k == "SCOPE")
-                    .map(|(k, v)| (k.to_string(), v.to_string()));
-
-                let formatted_pairs = single
-                    .iter()
-                    .filter(|(k, _)| *k != "SCOPE")
-                    .map(|(k, v)| {
-                        let k = k.to_string().as_str().truecolor(142, 142, 142);
-                        let eq = "=".truecolor(142, 142, 142);
-                        let v = v.to_string();
-                        format!("{k}{eq}{v}")
-                    })
-                    .collect::<Vec<_>>()
-                    .join(" ");
-
-                let formatted_multiline_pairs = multiline
-                    .iter()
-                    .map(|(k, v)| {
-                        let k = k.to_string().as_str().truecolor(142, 142, 142);
-                        let eq = "=".truecolor(142, 142, 142);
-                        let vb = "┊".truecolor(142, 142, 142);
-                        let v = v.to_string();
-                        format!(
-                            "{k}{eq}\n  {vb} {}",
-                            v.to_string()
-                                .lines()
-                                .collect::<Vec<_>>()
-                                .join(format!("\n  {vb} ").as_str())
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n  ");
-
-                out.finish(format_args!(
-                    "{time} {lvl_colored} {}{message}{}{}",
-                    if let Some((_, v)) = scope {
-                        format!("[{}] ", v.bold())
-                    } else {
-                        String::new()
-                    },
-                    if !single.is_empty() {
-                        format!(" {}", formatted_pairs)
-                    } else {
-                        String::new()
-                    },
-                    if !multiline.is_empty() {
-                        format!("\n  {}", formatted_multiline_pairs)
-                    } else {
-                        String::new()
-                    }
-                ))
-            } else {
-                out.finish(format_args!("{time} {lvl_colored} {message}"))
-            }
-        })
         .level(log_level)
-        .chain(std::io::stdout())
-        .chain(fern::log_file("tarium.log")?)
+        .chain(
+            fern::Dispatch::new()
+                .format(move |out, message, record| {
+                    format_record(out, message, record, stdout_color);
+                })
+                .chain(std::io::stdout()),
+        )
+        // Log files are always written as plain text
+        .chain(
+            fern::Dispatch::new()
+                .format(|out, message, record| format_record(out, message, record, false))
+                .chain(fern::log_file(log_dir.join("tarium.log"))?),
+        )
         .apply()?;
 
     Ok(())
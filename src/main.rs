@@ -17,26 +17,31 @@
 #![cfg_attr(debug_assertions, allow(warnings))]
 
 mod add;
+mod archive;
 mod auth;
 mod cli;
 mod download;
 mod file_picker;
 mod logging;
+mod path_filter;
+mod report;
 mod subcommands;
+mod update_check;
 
 #[cfg(test)]
 mod tests;
 
 use anyhow::{anyhow, bail, ensure, Context as _, Result};
 use clap::{CommandFactory, Parser};
-use cli::{ProfileSubCommands, SubCommands, Tarium};
+use cli::{AliasSubCommands, ProfileSubCommands, SubCommands, Tarium};
 use colored::{ColoredString, Colorize};
 use indicatif::ProgressStyle;
 use libarov::{
+    add::{AddOptions, AssetSelection},
     config::{
         self,
         filters::ProfileParameters as _,
-        structs::{Config, ModIdentifier, Profile},
+        structs::{Config, Mod, ModIdentifier, Profile},
     },
     iter_ext::IterExt as _,
 };
@@ -45,6 +50,7 @@ use std::{
     env::{set_var, var_os},
     process::ExitCode,
     sync::{LazyLock, OnceLock},
+    time::Duration,
 };
 use tokio::sync::Semaphore;
 
@@ -67,6 +73,12 @@ pub static STYLE_NO: LazyLock<ProgressStyle> = LazyLock::new(|| {
         .progress_chars("#>-")
 });
 #[expect(clippy::expect_used)]
+pub static STYLE_ADD: LazyLock<ProgressStyle> = LazyLock::new(|| {
+    ProgressStyle::default_spinner()
+        .template("{spinner} Adding {pos:.cyan}/{len:.blue} {msg}")
+        .expect("Progress bar template parse failure")
+});
+#[expect(clippy::expect_used)]
 pub static STYLE_BYTE: LazyLock<ProgressStyle> = LazyLock::new(|| {
     ProgressStyle::default_bar()
         .template(
@@ -128,6 +140,17 @@ fn main() -> ExitCode {
 }
 
 async fn actual_main(mut cli_app: Tarium) -> Result<()> {
+    // Before authenticating, which is what a skewed clock breaks
+    if matches!(
+        cli_app.subcommand,
+        SubCommands::Upgrade {
+            check_clock: true,
+            ..
+        }
+    ) {
+        auth::warn_clock_skew().await;
+    }
+
     // Initialize GitHub App authentication for libarov
     if let Err(e) = auth::initialize_github_app().await {
         warn!("Failed to initialize GitHub App authentication: {}", e);
@@ -144,10 +167,21 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
         );
         return Ok(());
     }
+    // Hashing a file doesn't need a config either
+    if let SubCommands::Hash { path } = &cli_app.subcommand {
+        let digest = libarov::hash::sha256_file(path)
+            .with_context(|| format!("Failed to hash {}", path.display()))?;
+        println!("{digest}  {}", path.display());
+        return Ok(());
+    }
+    // Neither does reading the logs
+    if let SubCommands::Log { path, tail } = cli_app.subcommand {
+        return subcommands::log(&libarov::logs_dir(), path, tail);
+    }
     // Alias `tarium profiles` to `tarium profile list`
     if let SubCommands::Profiles = cli_app.subcommand {
         cli_app.subcommand = SubCommands::Profile {
-            subcommand: Some(ProfileSubCommands::List),
+            subcommand: Some(ProfileSubCommands::List { json: false }),
         };
     }
 
@@ -156,8 +190,25 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
             set_var("GITHUB_TOKEN", token);
         }
     }
+    if let Some(proxy) = cli_app.proxy {
+        reqwest::Proxy::all(&proxy).context("Invalid proxy URL")?;
+        set_var(libarov::PROXY_ENV, proxy);
+    }
 
     let _ = SEMAPHORE.set(Semaphore::new(cli_app.parallel_tasks));
+    libarov::host_limit::set_concurrency_per_host(cli_app.concurrency_per_host);
+    libarov::host_limit::set_wait_for_rate_limit(cli_app.wait);
+    libarov::host_limit::set_on_retry(|delay| {
+        eprintln!(
+            "{}",
+            format!(
+                "GitHub's secondary rate limit was hit, trying again in {} seconds",
+                delay.as_secs()
+            )
+            .yellow()
+        );
+    });
+    libarov::throttle::set_max_download_speed(cli_app.max_download_speed);
 
     let old_default_config_path = libarov::BASE_DIRS
         .home_dir()
@@ -192,25 +243,60 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
 
     let mut config = config::read_config(config_path)?;
     info!("Loaded config with {} profiles", config.profiles.len());
+    // Used to only rewrite the config if the command changed it
+    let original_config = serde_json::to_value(&config)?;
+    let _ = libarov::EXTRA_SPT_MAJOR_VERSIONS.set(config.extra_spt_major_versions.clone());
+    if let Some(kib) = config.download_buffer_kib {
+        libarov::upgrade::set_download_buffer_size(kib.saturating_mul(1024));
+    }
+
+    let update_check =
+        (!cli_app.no_update_check && !config.disable_update_check).then(update_check::spawn);
 
     // TODO: this needs a fucking rework holy shit
     let mut did_add_fail = false;
+    // Returned after saving the config, so that what the upgrade did record isn't lost
+    let mut upgrade_error = None;
 
     // Run function(s) based on the sub(sub)command to be executed
     info!(SCOPE = "clap", subcommand:debug = cli_app.subcommand; "Executing");
     match cli_app.subcommand {
-        SubCommands::Complete { .. } | SubCommands::Profiles => {
+        SubCommands::Complete { .. }
+        | SubCommands::Hash { .. }
+        | SubCommands::Log { .. }
+        | SubCommands::Profiles => {
             unreachable!();
         }
         SubCommands::Auth => {
             subcommands::auth::handle_auth_command().await?;
         }
+        SubCommands::Status { check } => {
+            let max_releases = resolve_max_releases(None, &config);
+            let profile = get_active_profile(&mut config)?;
+            subcommands::status(profile, check.then_some(max_releases)).await?;
+        }
+        SubCommands::Which { mod_name } => {
+            let profile = get_active_profile(&mut config)?;
+            subcommands::which(profile, &mod_name)?;
+        }
         SubCommands::Add {
             identifiers,
+            topic,
             force,
+            no_metadata,
+            dry_run,
             pin,
+            replace,
+            include_source_archives,
+            asset,
+            asset_index,
+            filters,
+            max_releases,
         } => {
-            let profile = get_active_profile(&mut config)?;
+            let max_releases = resolve_max_releases(max_releases, &config);
+            let asset = asset
+                .map(AssetSelection::Pattern)
+                .or(asset_index.map(AssetSelection::Index));
 
             ensure!(
                 // If a pin is specified, there should only be one mod
@@ -219,7 +305,7 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
             );
 
             let identifiers = if let Some(pin) = pin {
-                let id = libarov::add::parse_id(identifiers[0].clone());
+                let id = libarov::add::parse_id(identifiers[0].clone(), &config.aliases)?;
                 vec![match id {
                     ModIdentifier::GitHubRepository(owner, repo) => {
                         ModIdentifier::PinnedGitHubRepository(
@@ -232,16 +318,55 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
             } else {
                 identifiers
                     .into_iter()
-                    .map(libarov::add::parse_id)
-                    .collect_vec()
+                    .map(|id| libarov::add::parse_id(id, &config.aliases))
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+            let profile = get_active_profile(&mut config)?;
+            let github_api = subcommands::github_api(profile)?;
+            let identifiers = if let Some(topic) = topic {
+                let picked = add::pick_from_topic(&github_api, profile, &topic).await?;
+                if picked.is_empty() {
+                    println!("{}", "No mods were picked".yellow());
+                }
+                picked
+            } else {
+                identifiers
             };
 
-            let (successes, failures) = libarov::add(profile, identifiers, !force).await?;
+            let options = AddOptions {
+                perform_checks: !force,
+                fetch_metadata: !no_metadata,
+                override_profile: filters.override_profile,
+                filters: filters.into(),
+                replace,
+                source_archives: include_source_archives,
+                asset,
+                max_releases,
+            };
+            let mut preview;
+            let profile = if dry_run {
+                preview = profile.clone();
+                &mut preview
+            } else {
+                profile
+            };
+            let (successes, updated, warnings, failures) =
+                libarov::add(&github_api, profile, identifiers, &options).await?;
 
-            did_add_fail = add::display_successes_failures(&successes, failures);
+            did_add_fail =
+                add::display_successes_failures(&successes, &updated, &warnings, failures, dry_run);
+            if dry_run {
+                println!("{}", "Dry run, the profile wasn't changed".dimmed());
+            }
         }
-        SubCommands::AddFrom { file, force } => {
-            let profile = get_active_profile(&mut config)?;
+        SubCommands::AddFrom {
+            file,
+            force,
+            no_metadata,
+            dry_run,
+            max_releases,
+        } => {
+            let max_releases = resolve_max_releases(max_releases, &config);
 
             // Read the file and parse identifiers
             let file_content = std::fs::read_to_string(&file)
@@ -253,14 +378,7 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
                     continue;
                 }
 
-                if !line.contains('/') {
-                    bail!(
-                        "Invalid identifier format: '{}'. Expected format: 'owner/repo'",
-                        line
-                    );
-                }
-
-                identifiers.push(libarov::add::parse_id(line.to_string()));
+                identifiers.push(libarov::add::parse_id(line.to_string(), &config.aliases)?);
             }
 
             if identifiers.is_empty() {
@@ -272,17 +390,49 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
                 identifiers.len(),
                 file.display()
             );
+            let profile = get_active_profile(&mut config)?;
+            let github_api = subcommands::github_api(profile)?;
+            let mut preview;
+            let profile = if dry_run {
+                preview = profile.clone();
+                &mut preview
+            } else {
+                profile
+            };
 
-            let (successes, failures) = libarov::add(profile, identifiers, !force).await?;
-
-            did_add_fail = add::display_successes_failures(&successes, failures);
+            let options = AddOptions {
+                perform_checks: !force,
+                fetch_metadata: !no_metadata,
+                max_releases,
+                ..AddOptions::default()
+            };
+            let outcomes =
+                add::add_with_progress(&github_api, profile, identifiers, &options).await;
+            let total = outcomes.len();
+            let (successes, updated, warnings, failures) = libarov::add::partition(outcomes);
+            let summary = add::summary(total, &successes, &updated, &failures);
+
+            did_add_fail =
+                add::display_successes_failures(&successes, &updated, &warnings, failures, dry_run);
+            println!("\n{summary}");
+            if dry_run {
+                println!("{}", "Dry run, the profile wasn't changed".dimmed());
+            }
         }
-        SubCommands::List { verbose, markdown } => {
+        SubCommands::List {
+            verbose,
+            markdown,
+            broken,
+        } => {
+            let max_releases = resolve_max_releases(None, &config);
             let profile = get_active_profile(&mut config)?;
             check_empty_profile(profile)?;
 
-            if verbose {
-                subcommands::list::verbose(profile, markdown).await?;
+            if broken {
+                subcommands::list::broken(profile, max_releases).await?;
+            } else if verbose {
+                subcommands::list::verbose(&subcommands::github_api(profile)?, profile, markdown)
+                    .await?;
             } else {
                 println!(
                     "{} {} on {}\n",
@@ -298,7 +448,7 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
                 );
                 for mod_ in &profile.mods {
                     println!(
-                        "{:20}  {}",
+                        "{:20}  {}{}{}{}",
                         match &mod_.identifier {
                             ModIdentifier::GitHubRepository(..) => "GH".purple().to_string(),
                             _ => todo!(),
@@ -308,10 +458,30 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
                                 format!("{}/{}", owner.dimmed(), repo.bold()),
                             _ => todo!(),
                         },
+                        mod_.description
+                            .as_deref()
+                            .map(|description| format!("  {}", truncate(description, 60).dimmed()))
+                            .unwrap_or_default(),
+                        mod_.installed_tag
+                            .as_ref()
+                            .map(|tag| format!("  installed: {}", tag.green()))
+                            .unwrap_or_default(),
+                        if mod_.force_added {
+                            "  compatibility unchecked".yellow().to_string()
+                        } else {
+                            String::new()
+                        },
                     );
                 }
             }
         }
+        SubCommands::Alias { subcommand } => match subcommand {
+            AliasSubCommands::Set { name, identifier } => {
+                subcommands::alias::set(&mut config, &name, identifier)?;
+            }
+            AliasSubCommands::List => subcommands::alias::list(&config),
+            AliasSubCommands::Remove { name } => subcommands::alias::remove(&mut config, &name)?,
+        },
         SubCommands::Profile { subcommand } => {
             let mut default_flag = false;
             let subcommand = subcommand.unwrap_or_else(|| {
@@ -319,54 +489,78 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
                 ProfileSubCommands::Info
             });
             match subcommand {
-                ProfileSubCommands::Configure {
-                    game_versions,
-                    name,
-                    output_dir,
-                } => {
-                    subcommands::profile::configure(
-                        get_active_profile(&mut config)?,
-                        game_versions,
-                        name,
-                        output_dir,
-                    )
-                    .await?;
+                ProfileSubCommands::Configure(args) => {
+                    let max_releases = resolve_max_releases(None, &config);
+                    let old_output_dir = get_active_profile(&mut config)?.output_dir.clone();
+                    subcommands::profile::configure(&mut config, args, max_releases).await?;
+
+                    // If the output directory was renamed, save its new path straight away, and rename it back if that fails
+                    let renamed = &config.profiles[config.active_profile].output_dir;
+                    if *renamed != old_output_dir && !old_output_dir.exists() {
+                        if let Err(err) = config::write_config(config_path, &config) {
+                            std::fs::rename(renamed, &old_output_dir).with_context(|| {
+                                format!(
+                                    "Failed to save the config ({err}), and to rename the output directory back to {}",
+                                    old_output_dir.display()
+                                )
+                            })?;
+                            return Err(anyhow!(err).context(format!(
+                                "Failed to save the config, so the output directory was renamed back to {}",
+                                old_output_dir.display()
+                            )));
+                        }
+                    }
                 }
-                ProfileSubCommands::Create {
-                    import,
-                    output_dir,
-                    game_version,
-                    name,
-                } => {
-                    subcommands::profile::create(
-                        &mut config,
-                        output_dir,
-                        import,
-                        if game_version.is_empty() {
-                            None
-                        } else {
-                            Some(game_version)
-                        },
-                        name,
-                    )
-                    .await?;
+                ProfileSubCommands::Create(args) => {
+                    subcommands::profile::create(&mut config, args).await?;
                 }
                 ProfileSubCommands::Delete {
                     profile_name,
                     switch_to,
+                    purge_files,
                 } => {
-                    subcommands::profile::delete(&mut config, profile_name, switch_to)?;
+                    subcommands::profile::delete(
+                        &mut config,
+                        profile_name,
+                        switch_to,
+                        purge_files,
+                    )?;
                 }
                 ProfileSubCommands::Info => {
                     subcommands::profile::info(get_active_profile(&mut config)?, true);
                 }
 
-                ProfileSubCommands::List => {
-                    for (i, profile) in config.profiles.iter().enumerate() {
-                        subcommands::profile::info(profile, i == config.active_profile);
+                ProfileSubCommands::List { json } => {
+                    if json {
+                        let profiles = config
+                            .profiles
+                            .iter()
+                            .enumerate()
+                            .map(|(i, profile)| {
+                                subcommands::profile::json(profile, i == config.active_profile)
+                            })
+                            .collect::<Vec<_>>();
+                        println!("{}", serde_json::to_string_pretty(&profiles)?);
+                    } else {
+                        for (i, profile) in config.profiles.iter().enumerate() {
+                            subcommands::profile::info(profile, i == config.active_profile);
+                        }
                     }
                 }
 
+                ProfileSubCommands::SetPaths {
+                    mod_name,
+                    include,
+                    exclude,
+                } => {
+                    subcommands::profile::set_paths(
+                        get_active_profile(&mut config)?,
+                        &mod_name,
+                        include,
+                        exclude,
+                    )?;
+                }
+
                 ProfileSubCommands::Switch { profile_name } => {
                     subcommands::profile::switch(&mut config, profile_name)?;
                 }
@@ -379,41 +573,161 @@ async fn actual_main(mut cli_app: Tarium) -> Result<()> {
                 );
             }
         }
-        SubCommands::Remove { mod_names } => {
+        SubCommands::Remove {
+            mod_names,
+            regex,
+            purge,
+            keep_files,
+        } => {
             let profile = get_active_profile(&mut config)?;
             check_empty_profile(profile)?;
-            subcommands::remove(profile, mod_names)?;
+            let files = if purge {
+                subcommands::InstalledFiles::Purge
+            } else if keep_files {
+                subcommands::InstalledFiles::Keep
+            } else {
+                subcommands::InstalledFiles::Ask
+            };
+            if let Some(regex) = regex {
+                subcommands::remove_matching(profile, &regex, files)?;
+            } else {
+                subcommands::remove(profile, mod_names, files)?;
+            }
         }
-        SubCommands::Upgrade { local_only } => {
+        SubCommands::Upgrade {
+            local_only,
+            quiet,
+            stream,
+            no_move_archives,
+            only,
+            resume,
+            only_new,
+            report,
+            install_order,
+            max_releases,
+            timeout_per_mod,
+            output_format,
+            clean_first,
+            yes,
+            explain,
+            check_clock: _,
+        } => {
+            let max_releases = resolve_max_releases(max_releases, &config);
             let profile = get_active_profile(&mut config)?;
             check_empty_profile(profile)?;
-            subcommands::upgrade(profile, local_only).await?;
+            if let Some(install_order) = install_order {
+                subcommands::set_install_order(profile, &install_order)?;
+            }
+            let only = if resume {
+                // Forget about failed mods that have since been removed
+                profile
+                    .failed_mods
+                    .retain(|name| profile.mods.iter().any(|mod_| mod_.matches_name(name)));
+                Some(profile.failed_mods.clone())
+            } else if only_new {
+                let new = profile
+                    .mods
+                    .iter()
+                    .filter(|mod_| !mod_.is_installed())
+                    .collect_vec();
+                if !new.is_empty() {
+                    println!(
+                        "{} {}\n",
+                        "New mods:".bold(),
+                        new.iter().map(|mod_| mod_.name.bold()).display(", ")
+                    );
+                }
+                Some(new.into_iter().map(Mod::repository).collect())
+            } else {
+                (!only.is_empty()).then_some(only)
+            };
+            if only.as_ref().is_some_and(Vec::is_empty) {
+                println!(
+                    "{}",
+                    if only_new {
+                        "Nothing new to install, every mod has been installed before"
+                    } else {
+                        "Nothing to resume, no mods failed in the last upgrade"
+                    }
+                    .green()
+                );
+            } else {
+                let options = subcommands::UpgradeOptions {
+                    local_only,
+                    only,
+                    max_releases,
+                    timeout_per_mod: Duration::from_secs(timeout_per_mod),
+                    quiet,
+                    stream,
+                    move_archives: !no_move_archives,
+                    report,
+                    output_format,
+                    clean_first,
+                    yes,
+                    explain,
+                };
+                upgrade_error = subcommands::upgrade(profile, &options).await.err();
+            }
         }
     }
 
-    config.profiles.iter_mut().for_each(|profile| {
-        profile
-            .mods
-            .sort_unstable_by_key(|mod_| mod_.name.to_lowercase());
-    });
-    // Update config file with possibly edited config
-    info!("Persisting config changes to {:?}", config_path);
-    config::write_config(config_path, &config)?;
+    if let Some(update_check) = update_check {
+        update_check::print_notice(update_check).await;
+    }
+
+    if serde_json::to_value(&config)? == original_config {
+        debug!("Config unchanged, not rewriting it");
+    } else {
+        if !config.keep_mod_order {
+            config.profiles.iter_mut().for_each(|profile| {
+                profile
+                    .mods
+                    .sort_unstable_by_key(|mod_| mod_.name.to_lowercase());
+            });
+        }
+        // Update config file with edited config
+        info!("Persisting config changes to {:?}", config_path);
+        config::write_config(config_path, &config)?;
+    }
 
-    if did_add_fail {
+    if let Some(err) = upgrade_error {
+        Err(err)
+    } else if did_add_fail {
         Err(anyhow!("says did_add_fail here i guess..."))
     } else {
         Ok(())
     }
 }
 
+/// Use the `--max-releases` flag if provided, otherwise the config's default
+fn resolve_max_releases(flag: Option<usize>, config: &Config) -> usize {
+    flag.or(config.max_releases)
+        .unwrap_or(libarov::DEFAULT_MAX_RELEASES)
+}
+
+/// The first line of `text`, cut off with an ellipsis after `max_chars` characters
+fn truncate(text: &str, max_chars: usize) -> String {
+    let line = text.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > max_chars {
+        format!(
+            "{}…",
+            line.chars()
+                .take(max_chars - 1)
+                .collect::<String>()
+                .trim_end()
+        )
+    } else {
+        line.to_owned()
+    }
+}
+
 /// Get the active profile with error handling
 fn get_active_profile(config: &mut Config) -> Result<&mut Profile> {
     match config.profiles.len() {
         0 => {
             bail!("There are no profiles configured, add a profile using `tarium profile create`")
         }
-        1 => config.active_profile = 0,
+        1 => config.set_active_profile(0),
         n if config.active_profile >= n => {
             warn!("Active profile index out of bounds, prompting switch");
             println!(
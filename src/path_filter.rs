@@ -0,0 +1,148 @@
+use anyhow::{Context as _, Result};
+use libarov::config::structs::Mod;
+use log::debug;
+use regex::Regex;
+use std::{fs, path::Path};
+
+/// Decides which files from a mod's archive are installed, using the mod's include and exclude globs
+///
+/// Globs are matched ignoring case against paths relative to the archive's root, separated by `/`.
+/// `*` and `?` don't match a `/`, while `**` matches across directories.
+/// A glob matching a directory matches everything in it, so `BepInEx` is the same as `BepInEx/**`.
+#[derive(Debug)]
+pub struct PathFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl PathFilter {
+    /// Allow the files matching any of the `include` globs, or all files if there are none,
+    /// except those matching any of the `exclude` globs
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: include
+                .iter()
+                .map(|glob| glob_to_regex(glob))
+                .collect::<Result<_>>()?,
+            exclude: exclude
+                .iter()
+                .map(|glob| glob_to_regex(glob))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// The filter for `mod_`'s include and exclude paths, or `None` if it has neither
+    pub fn for_mod(mod_: &Mod) -> Result<Option<Self>> {
+        if mod_.include_paths.is_empty() && mod_.exclude_paths.is_empty() {
+            return Ok(None);
+        }
+        Self::new(&mod_.include_paths, &mod_.exclude_paths)
+            .map(Some)
+            .with_context(|| format!("Invalid include or exclude paths for {}", mod_.name))
+    }
+
+    /// Whether the file at `path`, relative to the archive's root, should be installed
+    pub fn allows(&self, path: &Path) -> bool {
+        let path = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        (self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(&path)))
+            && !self.exclude.iter().any(|glob| glob.is_match(&path))
+    }
+
+    /// Delete the files in the extracted archive at `root` that aren't allowed,
+    /// returning how many were deleted
+    pub fn prune(&self, root: &Path) -> Result<usize> {
+        fn prune_dir(filter: &PathFilter, root: &Path, dir: &Path) -> Result<usize> {
+            let mut pruned = 0;
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pruned += prune_dir(filter, root, &path)?;
+                } else if !filter.allows(path.strip_prefix(root)?) {
+                    debug!(SCOPE = "path_filter", path:display = path.display(); "skipping file excluded by the mod's paths");
+                    fs::remove_file(&path)?;
+                    pruned += 1;
+                }
+            }
+            Ok(pruned)
+        }
+
+        prune_dir(self, root, root)
+    }
+}
+
+/// Compile `glob` into a regex matching the paths it describes, and everything under them
+fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let mut regex = String::from("(?i)^");
+    let mut chars = glob.trim_matches('/').chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '\\' => regex.push('/'),
+            char => regex.push_str(&regex::escape(char.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push_str("(?:/.*)?$");
+    Regex::new(&regex).with_context(|| format!("Invalid path glob {glob:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let owned = |globs: &[&str]| {
+            globs
+                .iter()
+                .map(|&glob| glob.to_owned())
+                .collect::<Vec<_>>()
+        };
+        PathFilter::new(&owned(include), &owned(exclude)).unwrap()
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let filter = filter(&["BepInEx/**"], &["**/optional/**"]);
+        assert!(filter.allows(Path::new("BepInEx/plugins/Mod.dll")));
+        assert!(filter.allows(Path::new("bepinex/config/Mod.cfg")));
+        assert!(!filter.allows(Path::new("BepInEx/plugins/optional/Textures.bundle")));
+        assert!(!filter.allows(Path::new("user/mods/Mod/package.json")));
+    }
+
+    #[test]
+    fn globs_stay_within_directories() {
+        let filter = filter(&[], &["*.md", "user/mods/*/config"]);
+        assert!(!filter.allows(Path::new("README.md")));
+        assert!(filter.allows(Path::new("docs/README.md")));
+        assert!(!filter.allows(Path::new("user/mods/Mod/config/config.json")));
+        assert!(filter.allows(Path::new("user/mods/Mod/src/mod.js")));
+    }
+
+    #[test]
+    fn prune_extracted_files() {
+        let root = tempfile::tempdir().unwrap();
+        let plugins = root.path().join("BepInEx").join("plugins");
+        fs::create_dir_all(plugins.join("optional")).unwrap();
+        fs::write(plugins.join("Mod.dll"), "dll").unwrap();
+        fs::write(plugins.join("optional").join("Extra.dll"), "dll").unwrap();
+
+        let pruned = filter(&[], &["**/optional"]).prune(root.path()).unwrap();
+
+        assert_eq!(pruned, 1);
+        assert!(plugins.join("Mod.dll").is_file());
+        assert!(!plugins.join("optional").join("Extra.dll").exists());
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{fs, path::Path, time::Instant};
+
+/// A machine-readable record of what an upgrade actually did, written by `upgrade --report`
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub mods: Vec<ModReport>,
+    pub summary: Summary,
+}
+
+/// What happened to a single mod, or a local archive when running with `--local-only`
+#[derive(Serialize, Debug, Default)]
+pub struct ModReport {
+    pub name: String,
+    /// The tag of the release that was resolved
+    pub tag: Option<String>,
+    pub download_url: Option<String>,
+    pub filename: Option<String>,
+    /// The number of bytes downloaded, 0 if the file was already present
+    pub bytes: u64,
+    /// Whether the mod was resolved, downloaded, and installed without errors
+    pub success: bool,
+    /// Why resolving or downloading the mod failed
+    pub error: Option<String>,
+    pub extraction: Extraction,
+}
+
+/// What happened when extracting a mod's archive
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Extraction {
+    Installed {
+        bytes: u64,
+    },
+    Failed {
+        error: String,
+    },
+    /// The archive wasn't extracted, e.g. because it wasn't downloaded
+    #[default]
+    Skipped,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Summary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub bytes_downloaded: u64,
+    pub bytes_extracted: u64,
+    pub duration_secs: f64,
+}
+
+impl Report {
+    /// Summarise the `mods` of an upgrade that started at `started`.
+    /// Mods count as successful if they have no error and their archive didn't fail to extract.
+    pub fn new(mut mods: Vec<ModReport>, started: Instant) -> Self {
+        for mod_ in &mut mods {
+            mod_.success =
+                mod_.error.is_none() && !matches!(mod_.extraction, Extraction::Failed { .. });
+        }
+        let succeeded = mods.iter().filter(|mod_| mod_.success).count();
+        let summary = Summary {
+            total: mods.len(),
+            succeeded,
+            failed: mods.len() - succeeded,
+            bytes_downloaded: mods.iter().map(|mod_| mod_.bytes).sum(),
+            bytes_extracted: mods
+                .iter()
+                .map(|mod_| match mod_.extraction {
+                    Extraction::Installed { bytes } => bytes,
+                    _ => 0,
+                })
+                .sum(),
+            duration_secs: started.elapsed().as_secs_f64(),
+        };
+        Self { mods, summary }
+    }
+
+    /// Write the report to `path` as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_failed_extractions() {
+        let mods = vec![
+            ModReport {
+                name: "Installed".to_owned(),
+                bytes: 10,
+                extraction: Extraction::Installed { bytes: 25 },
+                ..Default::default()
+            },
+            ModReport {
+                name: "Unresolved".to_owned(),
+                error: Some("no compatible release".to_owned()),
+                ..Default::default()
+            },
+            ModReport {
+                name: "Corrupt".to_owned(),
+                bytes: 5,
+                extraction: Extraction::Failed {
+                    error: "invalid archive".to_owned(),
+                },
+                ..Default::default()
+            },
+        ];
+
+        let report = Report::new(mods, Instant::now());
+
+        assert!(report.mods[0].success);
+        assert!(!report.mods[1].success);
+        assert!(!report.mods[2].success);
+        assert_eq!(report.summary.total, 3);
+        assert_eq!(report.summary.succeeded, 1);
+        assert_eq!(report.summary.failed, 2);
+        assert_eq!(report.summary.bytes_downloaded, 15);
+        assert_eq!(report.summary.bytes_extracted, 25);
+    }
+}
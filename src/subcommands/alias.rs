@@ -0,0 +1,48 @@
+use crate::TICK;
+use anyhow::{bail, Context as _, Result};
+use colored::Colorize as _;
+use libarov::{
+    add::parse_id,
+    config::structs::{Config, ModIdentifier},
+};
+use std::collections::BTreeMap;
+
+/// Make `name` expand to the `owner/repo` in `identifier` when adding mods, replacing any existing alias
+pub fn set(config: &mut Config, name: &str, identifier: String) -> Result<()> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.contains('/') {
+        bail!("Alias names can't be empty or contain a '/'");
+    }
+    let ModIdentifier::GitHubRepository(owner, repo) = parse_id(identifier, &BTreeMap::new())?
+    else {
+        unreachable!()
+    };
+
+    let target = format!("{owner}/{repo}");
+    println!("{} {} -> {}", TICK.clone(), name.bold(), target.dimmed());
+    config.aliases.insert(name, target);
+    Ok(())
+}
+
+/// Print the aliases in `config`, sorted by name
+pub fn list(config: &Config) {
+    if config.aliases.is_empty() {
+        println!(
+            "{}",
+            "No aliases yet, add one with `tarium alias set <name> <owner/repo>`".yellow()
+        );
+    }
+    for (name, target) in &config.aliases {
+        println!("{}  {}", name.bold(), target.dimmed());
+    }
+}
+
+/// Remove the alias called `name`, ignoring case
+pub fn remove(config: &mut Config, name: &str) -> Result<()> {
+    let target = config
+        .aliases
+        .remove(&name.trim().to_lowercase())
+        .with_context(|| format!("There is no alias called '{name}'"))?;
+    println!("Removed {} -> {}", name.bold(), target.dimmed());
+    Ok(())
+}
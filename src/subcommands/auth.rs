@@ -33,6 +33,22 @@ pub async fn handle_auth_command() -> Result<()> {
         }
     }
 
+    // A skewed clock makes GitHub reject the App's JWTs
+    match crate::auth::measure_clock_skew().await {
+        Ok(skew) => match crate::auth::clock_skew_warning(skew) {
+            Some(warning) => println!("{} Clock: {}", "✗".red(), warning),
+            None => println!(
+                "{} Clock: in sync with GitHub (off by {}s)",
+                "✓".green(),
+                skew.num_seconds().abs()
+            ),
+        },
+        Err(e) => println!(
+            "{} Clock: couldn't compare with GitHub's ({e:#})",
+            "?".yellow()
+        ),
+    }
+
     Ok(())
 }
 
@@ -41,7 +57,7 @@ pub async fn get_github_client() -> reqwest::Client {
     // The GITHUB_API in libarov already handles the authentication
     // This function is kept for compatibility but the actual client
     // creation is handled in libarov
-    reqwest::Client::new()
+    libarov::http_client()
 }
 
 #[cfg(test)]
@@ -1,12 +1,17 @@
+use super::{get_platform_downloadables, OutputFormat, DEFAULT_TIMEOUT_PER_MOD};
 use crate::TICK;
-use anyhow::{Context as _, Result};
+use anyhow::{bail, Context as _, Result};
 use colored::Colorize as _;
 use libarov::{
     config::structs::{ModIdentifier, Profile},
+    host_limit::{self, GITHUB_API_HOST},
     iter_ext::IterExt as _,
-    GITHUB_API,
 };
-use octocrab::models::{repos::Release, Repository};
+use octocrab::{
+    models::{repos::Release, Repository},
+    Octocrab,
+};
+use std::time::Duration;
 use tokio::task::JoinSet;
 
 enum Metadata {
@@ -33,9 +38,16 @@ impl Metadata {
             Metadata::GH(p, _) => &p.name,
         }
     }
+
+    fn description(&self) -> Option<&str> {
+        match self {
+            Metadata::GH(p, _) => p.description.as_deref(),
+        }
+    }
 }
 
-pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
+/// List the mods of `profile` with their repository's details, fetched with `github_api`
+pub async fn verbose(github_api: &Octocrab, profile: &mut Profile, markdown: bool) -> Result<()> {
     if !markdown {
         eprint!("Querying metadata... ");
     }
@@ -44,8 +56,10 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
     for mod_ in &profile.mods {
         match mod_.identifier.clone() {
             ModIdentifier::GitHubRepository(owner, repo) => {
-                let repo = GITHUB_API.repos(owner, repo);
+                let github_api = github_api.clone();
                 tasks.spawn(async move {
+                    let _permit = host_limit::acquire(GITHUB_API_HOST).await;
+                    let repo = github_api.repos(owner, repo);
                     Ok::<_, anyhow::Error>((
                         repo.get().await?,
                         repo.releases().list().send().await?,
@@ -76,6 +90,7 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
 
         mod_.name = project.name().to_string();
         mod_.slug = Some(project.slug().to_string());
+        mod_.description = project.description().map(ToOwned::to_owned);
 
         if markdown {
             match project {
@@ -174,3 +189,40 @@ pub fn github_md(repo: &Repository) {
         )),
     );
 }
+
+/// Resolve every mod in `profile`, scanning at most `max_releases` releases each,
+/// and list the ones that fail with the reason why
+///
+/// Fails if any mod is broken, so scripts can tell from the exit code.
+pub async fn broken(profile: &Profile, max_releases: usize) -> Result<()> {
+    let (_, mut failed) = get_platform_downloadables(
+        profile,
+        None,
+        max_releases,
+        Duration::from_secs(DEFAULT_TIMEOUT_PER_MOD),
+        OutputFormat::default(),
+        false,
+    )
+    .await?;
+    if failed.is_empty() {
+        println!("\n{}", "No broken mods".green());
+        return Ok(());
+    }
+
+    failed.sort_unstable_by_key(|(index, _)| profile.mods[*index].name.to_lowercase());
+    println!("\n{}", "Broken mods".red().bold());
+    for (index, reason) in &failed {
+        let mod_ = &profile.mods[*index];
+        println!(
+            "{}  {}\n    {}",
+            mod_.name.bold(),
+            mod_.repository().dimmed(),
+            reason
+        );
+    }
+    bail!(
+        "{} of {} mods are broken, remove them with `tarium remove`",
+        failed.len(),
+        profile.mods.len()
+    )
+}
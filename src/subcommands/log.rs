@@ -0,0 +1,92 @@
+use anyhow::{Context as _, Result};
+use colored::Colorize as _;
+use std::{
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+/// The most recently written log file in `dir`, if there is one
+fn latest_log_file(dir: &Path) -> Option<PathBuf> {
+    read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// The last `count` lines of `contents`
+fn last_lines(contents: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+    // A trailing newline ends the last line rather than starting another
+    let start = contents
+        .trim_end_matches('\n')
+        .rmatch_indices('\n')
+        .nth(count - 1)
+        .map_or(0, |(index, _)| index + 1);
+    &contents[start..]
+}
+
+/// Print the latest log file in `dir`, or only its last `tail` lines
+///
+/// If `path` is true, only the path of the log file is printed.
+pub fn log(dir: &Path, path: bool, tail: Option<usize>) -> Result<()> {
+    let Some(file) = latest_log_file(dir) else {
+        println!(
+            "{}",
+            format!(
+                "There are no logs yet, they're written to {} once tarium runs",
+                dir.display()
+            )
+            .yellow()
+        );
+        return Ok(());
+    };
+    if path {
+        println!("{}", file.display());
+        return Ok(());
+    }
+
+    let contents = read_to_string(&file)
+        .with_context(|| format!("Failed to read the log file {}", file.display()))?;
+    let contents = match tail {
+        Some(count) => last_lines(&contents, count),
+        None => &contents,
+    };
+    print!("{contents}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    #[test]
+    fn tail_lines() {
+        let contents = "one\ntwo\nthree\n";
+        assert_eq!(last_lines(contents, 2), "two\nthree\n");
+        assert_eq!(last_lines(contents, 3), contents);
+        assert_eq!(last_lines(contents, 10), contents);
+        assert_eq!(last_lines(contents, 0), "");
+        assert_eq!(last_lines("one\ntwo", 1), "two");
+    }
+
+    #[test]
+    fn finds_log_files() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(latest_log_file(dir.path()).is_none());
+        assert!(latest_log_file(&dir.path().join("missing")).is_none());
+
+        write(dir.path().join("notes.txt"), "not a log").unwrap();
+        assert!(latest_log_file(dir.path()).is_none());
+        write(dir.path().join("tarium.log"), "log").unwrap();
+        assert_eq!(
+            latest_log_file(dir.path()),
+            Some(dir.path().join("tarium.log"))
+        );
+    }
+}
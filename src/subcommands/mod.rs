@@ -1,7 +1,57 @@
+pub mod alias;
 pub mod auth;
 pub mod list;
+mod log;
 pub mod profile;
 mod remove;
+mod status;
 mod upgrade;
-pub use remove::remove;
-pub use upgrade::upgrade;
+mod which;
+pub use log::log;
+pub use remove::{remove, remove_matching, InstalledFiles};
+pub use status::status;
+pub use upgrade::{
+    get_platform_downloadables, set_install_order, upgrade, OutputFormat, UpgradeOptions,
+    DEFAULT_TIMEOUT_PER_MOD,
+};
+pub use which::which;
+
+use ::log::warn;
+use anyhow::{bail, Context as _, Result};
+use libarov::{config::structs::Profile, GITHUB_API};
+use octocrab::Octocrab;
+use std::{
+    fs::{remove_file, File},
+    path::Path,
+};
+
+/// The GitHub client to send `profile`'s requests with, which uses its own token if it has one
+pub fn github_api(profile: &Profile) -> Result<Octocrab> {
+    match &profile.github_token {
+        Some(token) => libarov::github_client(Some(token.clone()))
+            .context("Could not build a GitHub client with the profile's token"),
+        None => Ok(GITHUB_API.clone()),
+    }
+}
+
+/// Check that `dir` is writable by creating and removing a probe file
+///
+/// This surfaces permission problems up front instead of deep within extraction.
+pub fn check_writable(dir: &Path) -> Result<()> {
+    let probe = dir.join(".tarium_write_test");
+    let result = File::create(&probe).and_then(|file| {
+        drop(file);
+        remove_file(&probe)
+    });
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            warn!(SCOPE = "subcommands", path:display = dir.display(); "directory is not writable");
+            bail!(
+                "Cannot write to {}: permission denied - try running elevated or fix the directory's ownership",
+                dir.display()
+            )
+        }
+        Err(e) => bail!("Cannot write to {}: {e}", dir.display()),
+    }
+}
@@ -1,93 +1,573 @@
-use super::{check_output_directory, pick_spt_versions};
-use crate::file_picker::pick_folder;
-use anyhow::{Context as _, Result};
-use inquire::{Select, Text};
+use super::{
+    check_archive_store, check_output_directory, check_output_directory_overlap,
+    create_output_directory, pick_spt_versions, trim_patches, SPT_DIR_NAME,
+};
+use crate::{
+    cli::ConfigureArguments,
+    file_picker::pick_folder,
+    subcommands::{get_platform_downloadables, OutputFormat, DEFAULT_TIMEOUT_PER_MOD},
+};
+use anyhow::{ensure, Context as _, Result};
+use colored::Colorize as _;
+use inquire::{Confirm, Select, Text};
 use libarov::{
-    config::filters::ProfileParameters as _,
-    config::structs::{Profile},
+    config::filters::{normalize_filters, Filter, ProfileParameters as _, ReleaseChannel},
+    config::structs::{Config, InstallDirs, Profile},
+    is_spt_version,
+    iter_ext::IterExt as _,
+};
+use log::info;
+use std::{
+    fs::{read_to_string, rename},
+    io::IsTerminal as _,
+    path::{Path, PathBuf},
+    time::Duration,
 };
-use std::path::PathBuf;
 
+/// Configure the active profile of `config` with `args`, or interactively if no setting was given.
+/// Mods are checked against at most `max_releases` releases each when `args.check` is set.
 pub async fn configure(
-    profile: &mut Profile,
-    game_versions: Vec<String>,
-    name: Option<String>,
-    output_dir: Option<PathBuf>,
+    config: &mut Config,
+    args: ConfigureArguments,
+    max_releases: usize,
 ) -> Result<()> {
-    let mut interactive = true;
+    let ConfigureArguments {
+        game_versions,
+        trim_patch,
+        name,
+        description,
+        output_dir,
+        force,
+        check,
+        clear_filters,
+        show_filters,
+        create_output_dir,
+        merge_filters,
+        rename_output_dir,
+        import_filters_from,
+        set_strict_version: strict_version,
+        github_token,
+        archive_store_dir,
+        validate_on_save,
+        rename_output_dir_to_match,
+    } = args;
+    let import_filters = import_filters_from
+        .map(|name| {
+            config
+                .profiles
+                .iter()
+                .find(|profile| profile.name.eq_ignore_ascii_case(&name))
+                .map(|profile| profile.filters.clone())
+                .with_context(|| format!("There is no profile named {name}"))
+        })
+        .transpose()?;
+    let other_output_dirs = config
+        .profiles
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != config.active_profile)
+        .map(|(_, profile)| profile.output_dir.clone())
+        .collect_vec();
+    let other_output_dirs = other_output_dirs.as_slice();
+    let last_output_dir = &mut config.last_output_dir;
+    let profile = &mut config.profiles[config.active_profile];
+
+    let mut interactive = !show_filters;
+
+    if clear_filters {
+        let before = profile.filters.len();
+        profile.filters.retain(|filter| {
+            matches!(
+                filter,
+                Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_)
+            )
+        });
+        println!(
+            "Cleared {} filters",
+            (before - profile.filters.len()).to_string().yellow()
+        );
+        interactive = false;
+    }
+
+    if let Some(path) = merge_filters {
+        merge_filters_from(profile, &path)?;
+        interactive = false;
+    }
+
+    if let Some(filters) = import_filters {
+        merge_filters_into(profile, filters);
+        print_filters(profile);
+        interactive = false;
+    }
 
     if !game_versions.is_empty() {
-        *profile
+        profile
             .filters
-            .game_versions_mut()
-            .context("Active profile does not filter by game version")? = game_versions;
-
+            .set_game_versions(trim_patches(game_versions, trim_patch));
+        interactive = false;
+    }
+    if let Some(version) = strict_version {
+        ensure!(
+            is_spt_version(&version),
+            "{version} is not an SPT version, e.g. 3.11"
+        );
+        // Replace whatever game version filters there were, strict or not
+        profile.filters.retain(|filter| {
+            !matches!(
+                filter,
+                Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_)
+            )
+        });
+        profile
+            .filters
+            .insert(0, Filter::GameVersionStrict(vec![version]));
         interactive = false;
     }
     if let Some(name) = name {
-        profile.name = name;
+        let old_name = std::mem::replace(&mut profile.name, name);
+        rename_output_dir_after(
+            profile,
+            &old_name,
+            rename_output_dir,
+            other_output_dirs,
+            force,
+        )?;
+        interactive = false;
+    }
+    if let Some(description) = description {
+        profile.description = Some(description).filter(|d| !d.is_empty());
+        interactive = false;
+    }
+    if let Some(token) = github_token {
+        profile.github_token = Some(token).filter(|t| !t.trim().is_empty());
+        println!(
+            "{}",
+            if profile.github_token.is_some() {
+                "The profile's requests will be sent with its own GitHub token"
+            } else {
+                "The profile's requests will be sent with the global GitHub token"
+            }
+        );
+        interactive = false;
+    }
+    if let Some(dir) = archive_store_dir {
+        if dir.as_os_str().is_empty() {
+            profile.install_dirs.archive_store = InstallDirs::default().archive_store;
+        } else {
+            check_archive_store(&dir)?;
+            profile.install_dirs.archive_store = dir;
+        }
+        println!(
+            "Archives will be kept in {}",
+            profile
+                .output_dir
+                .join(&profile.install_dirs.archive_store)
+                .display()
+                .to_string()
+                .blue()
+                .underline()
+        );
+        interactive = false;
+    }
+    if let Some(validate) = validate_on_save {
+        config.validate_on_save = validate;
+        println!(
+            "{}",
+            if validate {
+                "The config will be checked for problems before it's saved, and not saved if it has any"
+            } else {
+                "The config will be saved without checking it for problems"
+            }
+        );
         interactive = false;
     }
     if let Some(output_dir) = output_dir {
+        check_output_directory_overlap(&output_dir, other_output_dirs, force)?;
+        if create_output_dir {
+            create_output_directory(&output_dir, true)?;
+        }
+        *last_output_dir = Some(output_dir.clone());
         profile.output_dir = output_dir;
         interactive = false;
     }
+    // Last, so that nothing else can fail after the directory has been renamed
+    if rename_output_dir_to_match {
+        rename_output_dir_to_spt(profile, other_output_dirs, force)?;
+        interactive = false;
+    }
 
     if interactive {
         let items = vec![
             // Show a file dialog
             "Mods output directory",
-            // Show a picker of Minecraft versions to select from
-            "Minecraft version",
-            // Show a picker to change mod loader
-            "Mod loader",
+            // Show a picker of SPT versions to select from
+            "SPT versions",
+            // Show a picker of the release channels to download from
+            "Release channel",
             // Show a dialog to change name
             "Profile Name",
+            // Show a dialog to change the description
+            "Profile Description",
             // Quit the configuration
             "Quit",
         ];
 
         while let Ok(selection) =
-            Select::new("Which setting would you like to change", items.clone()).raw_prompt()
+            Select::new("Which setting would you like to change", items.clone()).prompt()
         {
-            match selection.index {
-                0 => {
+            // Matched by name so the menu can be reordered without renumbering
+            match selection {
+                "Mods output directory" => {
                     if let Some(dir) = pick_folder(
-                        &profile.output_dir,
+                        last_output_dir.as_ref().unwrap_or(&profile.output_dir),
                         "Pick an output directory",
                         "Output Directory",
                     )? {
-                        check_output_directory(&dir).await?;
+                        check_output_directory(&dir, create_output_dir).await?;
+                        check_output_directory_overlap(&dir, other_output_dirs, force)?;
+                        *last_output_dir = Some(dir.clone());
                         profile.output_dir = dir;
                     }
                 }
-                1 => {
-                    // TODO: refactor this, no mut here goddamnit
-                    let Some(versions) = profile.filters.game_versions_mut() else {
-                        println!("Active profile does not filter by game version");
+                "SPT versions" => {
+                    let versions = profile.filters.game_versions().cloned().unwrap_or_default();
+                    if let Ok(selection) = pick_spt_versions(&versions).await {
+                        profile
+                            .filters
+                            .set_game_versions(trim_patches(selection, trim_patch));
+                    }
+                }
+                "Release channel" => {
+                    let channels = [
+                        None,
+                        Some(ReleaseChannel::Release),
+                        Some(ReleaseChannel::Beta),
+                        Some(ReleaseChannel::Alpha),
+                    ];
+                    let current = profile.filters.iter().find_map(|filter| match filter {
+                        Filter::ReleaseChannel(channel) => Some(*channel),
+                        _ => None,
+                    });
+                    if let Ok(selection) = Select::new(
+                        "Which releases should mods be downloaded from?",
+                        vec![
+                            "Any release",
+                            "Stable releases only",
+                            "Beta or more stable",
+                            "Alpha or more stable",
+                        ],
+                    )
+                    .with_starting_cursor(
+                        channels
+                            .iter()
+                            .position(|channel| *channel == current)
+                            .unwrap_or_default(),
+                    )
+                    .raw_prompt()
+                    {
+                        set_release_channel(&mut profile.filters, channels[selection.index]);
+                    } else {
                         continue;
-                    };
-
-                    if let Ok(selection) = pick_spt_versions(versions).await {
-                        *versions = selection;
                     }
                 }
-                3 => {
+                "Profile Name" => {
                     if let Ok(new_name) = Text::new("Change the profile's name")
                         .with_default(&profile.name)
                         .prompt()
                     {
-                        profile.name = new_name;
+                        let old_name = std::mem::replace(&mut profile.name, new_name);
+                        rename_output_dir_after(
+                            profile,
+                            &old_name,
+                            rename_output_dir,
+                            other_output_dirs,
+                            force,
+                        )?;
+                    } else {
+                        continue;
+                    }
+                }
+                "Profile Description" => {
+                    if let Ok(new_description) = Text::new("Change the profile's description")
+                        .with_default(profile.description.as_deref().unwrap_or_default())
+                        .prompt()
+                    {
+                        profile.description = Some(new_description).filter(|d| !d.is_empty());
                     } else {
                         continue;
                     }
                 }
-                4 => break,
+                "Quit" => break,
                 _ => unreachable!(),
             }
             println!();
         }
     }
 
+    if profile.ensure_game_versions() {
+        println!(
+            "{}",
+            format!(
+                "The profile didn't filter by game version, so it now filters by {}. Use `--game-versions` to change it",
+                profile.filters.game_versions().unwrap().iter().display(", ")
+            )
+            .yellow()
+        );
+    }
+
+    if show_filters {
+        print_filters(profile);
+    }
+
+    if check {
+        check_mods(profile, max_releases).await?;
+    }
+
+    // Keep tracking the profile if it was renamed
+    config.set_active_profile(config.active_profile);
+
+    Ok(())
+}
+
+/// If the output directory of `profile` is named after its `old_name`, offer to rename it after its new name,
+/// or rename it without asking if `without_asking` is true. The mods in it move along with it.
+///
+/// The renamed directory must not overlap `other_output_dirs` unless `force` is set.
+fn rename_output_dir_after(
+    profile: &mut Profile,
+    old_name: &str,
+    without_asking: bool,
+    other_output_dirs: &[PathBuf],
+    force: bool,
+) -> Result<()> {
+    if profile.name == old_name
+        || profile.output_dir.file_name() != Some(old_name.as_ref())
+        || !profile.output_dir.is_dir()
+    {
+        return Ok(());
+    }
+    let target = profile.output_dir.with_file_name(&profile.name);
+    if target.exists() {
+        println!(
+            "{}",
+            format!(
+                "Not renaming the output directory, {} already exists",
+                target.display()
+            )
+            .yellow()
+        );
+        return Ok(());
+    }
+
+    if !without_asking {
+        if !std::io::stdin().is_terminal() {
+            println!(
+                "{}",
+                "The output directory is still named after the old name, use `--rename-output-dir` to rename it too"
+                    .dimmed()
+            );
+            return Ok(());
+        }
+        let confirmed = Confirm::new(&format!(
+            "The output directory {} is named after the profile, rename it to {} too?",
+            profile.output_dir.display(),
+            target.display()
+        ))
+        .with_default(false)
+        .prompt()?;
+        if !confirmed {
+            return Ok(());
+        }
+    }
+
+    rename_output_dir_to(profile, target, other_output_dirs, force)
+}
+
+/// Rename the output directory of `profile` to `SPT`, the name SPT's game folder conventionally has,
+/// asking first if stdin is a terminal. The mods in it move along with it.
+///
+/// The renamed directory must not overlap `other_output_dirs` unless `force` is set.
+fn rename_output_dir_to_spt(
+    profile: &mut Profile,
+    other_output_dirs: &[PathBuf],
+    force: bool,
+) -> Result<()> {
+    if profile.output_dir.file_name() == Some(SPT_DIR_NAME.as_ref()) {
+        println!(
+            "{}",
+            format!("The output directory is already named {SPT_DIR_NAME}").dimmed()
+        );
+        return Ok(());
+    }
+    ensure!(
+        profile.output_dir.is_dir(),
+        "The output directory {} doesn't exist, so it can't be renamed",
+        profile.output_dir.display()
+    );
+    let target = profile.output_dir.with_file_name(SPT_DIR_NAME);
+    ensure!(
+        !target.exists(),
+        "Not renaming the output directory, {} already exists",
+        target.display()
+    );
+
+    if std::io::stdin().is_terminal()
+        && !Confirm::new(&format!(
+            "Rename the output directory {} to {}?",
+            profile.output_dir.display(),
+            target.display()
+        ))
+        .with_default(false)
+        .prompt()?
+    {
+        return Ok(());
+    }
+    rename_output_dir_to(profile, target, other_output_dirs, force)
+}
+
+/// Rename the output directory of `profile` to `target` and point the profile at it,
+/// along with its archive store if that's an absolute path within it.
+///
+/// The renamed directory must not overlap `other_output_dirs` unless `force` is set.
+fn rename_output_dir_to(
+    profile: &mut Profile,
+    target: PathBuf,
+    other_output_dirs: &[PathBuf],
+    force: bool,
+) -> Result<()> {
+    check_output_directory_overlap(&target, other_output_dirs, force)?;
+    rename(&profile.output_dir, &target).with_context(|| {
+        format!(
+            "Failed to rename the output directory {}",
+            profile.output_dir.display()
+        )
+    })?;
+    info!(SCOPE = "subcommands::profile::configure", from:display = profile.output_dir.display(), to:display = target.display(); "renamed output directory");
+    println!("Renamed the output directory to {}", target.display());
+    if let Ok(inside) = profile
+        .install_dirs
+        .archive_store
+        .strip_prefix(&profile.output_dir)
+    {
+        profile.install_dirs.archive_store = target.join(inside);
+    }
+    profile.output_dir = target;
+    Ok(())
+}
+
+/// Merge the filters in the JSON file at `path` into those of `profile`, see [`merge_filters_into`]
+fn merge_filters_from(profile: &mut Profile, path: &Path) -> Result<()> {
+    let contents = read_to_string(path)
+        .with_context(|| format!("Failed to read the filters file {}", path.display()))?;
+    let filters: Vec<Filter> = serde_json::from_str(&contents).with_context(|| {
+        format!(
+            "{} isn't a JSON list of filters, like the `filters` of a profile in the config",
+            path.display()
+        )
+    })?;
+    merge_filters_into(profile, filters);
+    info!(SCOPE = "subcommands::profile::configure", path:display = path.display(); "merged filters from file");
+    Ok(())
+}
+
+/// Merge `filters` into those of `profile` with [`normalize_filters`],
+/// printing the filters that were added and the ones they replaced
+fn merge_filters_into(profile: &mut Profile, filters: Vec<Filter>) {
+    let merged = normalize_filters(profile.filters.iter().cloned().chain(filters).collect());
+    let added = merged
+        .iter()
+        .filter(|filter| !profile.filters.contains(filter))
+        .collect_vec();
+    let replaced = profile
+        .filters
+        .iter()
+        .filter(|filter| !merged.contains(filter))
+        .collect_vec();
+    if added.is_empty() {
+        println!(
+            "{}",
+            "The profile already has all of these filters".dimmed()
+        );
+    }
+    for filter in &added {
+        println!("{} {filter}", "Added".green());
+    }
+    for filter in &replaced {
+        println!("{} {filter}", "Replaced".yellow());
+    }
+    info!(SCOPE = "subcommands::profile::configure", added = added.len(), replaced = replaced.len(); "merged filters");
+    profile.filters = merged;
+}
+
+/// Replace the release channel filter in `filters` with one for `channel`, or remove it if `None`
+fn set_release_channel(filters: &mut Vec<Filter>, channel: Option<ReleaseChannel>) {
+    let position = filters
+        .iter()
+        .position(|filter| matches!(filter, Filter::ReleaseChannel(_)));
+    filters.retain(|filter| !matches!(filter, Filter::ReleaseChannel(_)));
+    if let Some(channel) = channel {
+        filters.insert(
+            position.unwrap_or(filters.len()),
+            Filter::ReleaseChannel(channel),
+        );
+    }
+}
+
+/// Print the filters of `profile`, and of the mods that have their own
+fn print_filters(profile: &Profile) {
+    println!("{}", "Filters".bold());
+    if profile.filters.is_empty() {
+        println!("  {}", "none".dimmed());
+    }
+    for filter in &profile.filters {
+        println!("  {filter}");
+    }
+    for mod_ in profile.mods.iter().filter(|mod_| !mod_.filters.is_empty()) {
+        println!(
+            "  {} {}{}",
+            mod_.name.bold(),
+            mod_.filters.iter().display(", "),
+            if mod_.override_filters {
+                " (instead of the profile's)".dimmed()
+            } else {
+                "".normal()
+            }
+        );
+    }
+}
+
+/// Resolve the mods in `profile`, scanning at most `max_releases` releases per mod,
+/// and warn about the ones that have no compatible release
+async fn check_mods(profile: &Profile, max_releases: usize) -> Result<()> {
+    if profile.mods.is_empty() {
+        return Ok(());
+    }
+    println!();
+    let (_, failed) = get_platform_downloadables(
+        profile,
+        None,
+        max_releases,
+        Duration::from_secs(DEFAULT_TIMEOUT_PER_MOD),
+        OutputFormat::default(),
+        false,
+    )
+    .await?;
+    if failed.is_empty() {
+        println!("\n{}", "All mods have a compatible release".green());
+    } else {
+        println!(
+            "\n{}",
+            format!(
+                "Warning! {} mods have no compatible release and won't be upgraded: {}",
+                failed.len(),
+                failed
+                    .iter()
+                    .map(|(index, _)| &profile.mods[*index].name)
+                    .display(", ")
+            )
+            .yellow()
+            .bold()
+        );
+    }
     Ok(())
 }
@@ -1,5 +1,8 @@
-use super::{check_output_directory, pick_spt_versions};
-use crate::file_picker::pick_folder;
+use super::{
+    check_output_directory, check_output_directory_overlap, create_output_directory,
+    pick_spt_versions, trim_patches,
+};
+use crate::{cli::CreateArguments, file_picker::pick_folder};
 use anyhow::{bail, ensure, Context as _, Result};
 use colored::Colorize as _;
 use inquire::{
@@ -16,14 +19,22 @@ use libarov::{
 };
 use std::path::PathBuf;
 
-#[expect(clippy::option_option)]
-pub async fn create(
-    config: &mut Config,
-    output_dir: Option<PathBuf>,
-    import: Option<Option<String>>,
-    game_versions: Option<Vec<String>>,
-    name: Option<String>,
-) -> Result<()> {
+pub async fn create(config: &mut Config, args: CreateArguments) -> Result<()> {
+    let CreateArguments {
+        import,
+        output_dir,
+        game_version,
+        trim_patch,
+        name,
+        force,
+        create_output_dir,
+    } = args;
+    let game_versions = (!game_version.is_empty()).then_some(game_version);
+    let other_output_dirs = config
+        .profiles
+        .iter()
+        .map(|profile| profile.output_dir.clone())
+        .collect_vec();
     let mut profile = match (game_versions, name, output_dir) {
         (Some(game_versions), Some(name), Some(output_dir)) => {
             for profile in &config.profiles {
@@ -37,17 +48,29 @@ pub async fn create(
                 output_dir.is_absolute(),
                 "The provided output directory is not absolute, i.e. it is a relative path"
             );
+            check_output_directory_overlap(&output_dir, &other_output_dirs, force)?;
+            if create_output_dir {
+                create_output_directory(&output_dir, true)?;
+            }
+            config.last_output_dir = Some(output_dir.clone());
 
-            Profile::new(name, output_dir, game_versions, true)
+            Profile::new(
+                name,
+                output_dir,
+                trim_patches(game_versions, trim_patch),
+                true,
+            )
         }
         (None, None, None) => {
             let mut selected_mods_dir = PathBuf::new();
             if let Some(dir) = pick_folder(
-                &selected_mods_dir,
+                config.last_output_dir.clone().unwrap_or_else(get_spt_dir),
                 "Pick an output directory",
                 "Output Directory",
             )? {
-                check_output_directory(&dir).await?;
+                check_output_directory(&dir, create_output_dir).await?;
+                check_output_directory_overlap(&dir, &other_output_dirs, force)?;
+                config.last_output_dir = Some(dir.clone());
                 selected_mods_dir = dir;
             }
 
@@ -64,7 +87,12 @@ pub async fn create(
                 })
                 .prompt()?;
 
-            Profile::new(name, selected_mods_dir, pick_spt_versions(&[]).await?, true)
+            Profile::new(
+                name,
+                selected_mods_dir,
+                trim_patches(pick_spt_versions(&[]).await?, trim_patch),
+                true,
+            )
         }
         _ => {
             bail!(
@@ -112,6 +140,6 @@ pub async fn create(
     );
 
     config.profiles.push(profile);
-    config.active_profile = config.profiles.len() - 1; // Make created profile active
+    config.set_active_profile(config.profiles.len() - 1); // Make created profile active
     Ok(())
 }
@@ -1,17 +1,32 @@
 use super::switch;
-use anyhow::{Context as _, Result};
+use crate::subcommands::upgrade::{attributed_files, format_bytes, remove_attributed_files};
+use anyhow::{bail, ensure, Context as _, Result};
 use colored::Colorize as _;
-use inquire::Select;
+use inquire::{Select, Text};
 use libarov::{
-    config::{filters::ProfileParameters as _, structs::Config},
+    config::{
+        filters::ProfileParameters as _,
+        structs::{Config, Profile},
+    },
+    is_supported_archive,
     iter_ext::IterExt as _,
 };
-use std::cmp::Ordering;
+use std::{
+    fs::{read_dir, remove_dir, remove_file},
+    io::IsTerminal as _,
+    path::{Path, PathBuf},
+};
 
+/// Delete a profile from `config`, the one called `profile_name` or one picked by the user,
+/// and switch to `switch_to` if it was the active profile.
+///
+/// With `purge_files`, the files its mods installed and its archives are deleted too,
+/// once the user has typed the profile's name to confirm.
 pub fn delete(
     config: &mut Config,
     profile_name: Option<String>,
     switch_to: Option<String>,
+    purge_files: bool,
 ) -> Result<()> {
     // If the profile name has been provided as an option
     let selection = if let Some(profile_name) = profile_name {
@@ -48,26 +63,207 @@ pub fn delete(
             return Ok(());
         }
     };
+    if purge_files {
+        let purge = Purge::of(config, selection);
+        confirm_purge(&config.profiles[selection], &purge)?;
+        purge.run(&config.profiles[selection])?;
+    }
+    let was_active = config.active_profile == selection;
     config.profiles.remove(selection);
 
-    match config.active_profile.cmp(&selection) {
-        // If the currently selected profile is being removed
-        Ordering::Equal => {
-            // And there is more than one profile
-            if config.profiles.len() > 1 {
-                // Let the user pick which profile to switch to
-                switch(config, switch_to)?;
-            } else {
-                config.active_profile = 0;
-            }
+    if !was_active {
+        // Follow the active profile to its new index
+        config.resolve_active_profile();
+    } else if config.profiles.len() > 1 {
+        // Let the user pick which profile to switch to
+        config.set_active_profile(0);
+        switch(config, switch_to)?;
+    } else {
+        config.set_active_profile(0);
+    }
+
+    Ok(())
+}
+
+/// The files of a profile to delete along with it
+#[derive(Debug)]
+struct Purge {
+    /// The files its mods installed, relative to its output directory
+    files: Vec<PathBuf>,
+    /// The archives in its archive store
+    archives: Vec<PathBuf>,
+}
+
+impl Purge {
+    /// The files of the profile at `index` in `config`, leaving out any that another profile uses too
+    fn of(config: &Config, index: usize) -> Self {
+        let profile = &config.profiles[index];
+        let others = config
+            .profiles
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, other)| other)
+            .collect_vec();
+
+        let files = attributed_files(profile, &(0..profile.mods.len()).collect_vec())
+            .into_iter()
+            .filter(|file| {
+                let path = profile.output_dir.join(file);
+                !others.iter().any(|other| {
+                    other
+                        .mods
+                        .iter()
+                        .any(|mod_| mod_.files.iter().any(|f| other.output_dir.join(f) == path))
+                })
+            })
+            .collect();
+
+        // Another profile could keep its archives in the same place
+        let store = archive_store(profile);
+        let mut archives = if others.iter().any(|other| archive_store(other) == store) {
+            Vec::new()
+        } else {
+            read_dir(&store)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_file()
+                        && is_supported_archive(
+                            &path.file_name().unwrap_or_default().to_string_lossy(),
+                        )
+                })
+                .collect()
+        };
+        archives.sort();
+        Self { files, archives }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.archives.is_empty()
+    }
+
+    /// Delete the files from `profile`'s output directory and archive store, and print what was removed
+    fn run(&self, profile: &Profile) -> Result<()> {
+        let mut freed =
+            remove_attributed_files(&profile.output_dir, &profile.install_dirs, &self.files)?;
+        for archive in &self.archives {
+            freed += archive.metadata().map_or(0, |metadata| metadata.len());
+            remove_file(archive)
+                .with_context(|| format!("Failed to remove {}", archive.display()))?;
         }
-        // If the active profile comes after the removed profile
-        Ordering::Greater => {
-            // Decrement the index by one
-            config.active_profile -= 1;
+        // Only removed if nothing else was kept in it
+        let _ = remove_dir(archive_store(profile));
+
+        if !self.is_empty() {
+            println!(
+                "Removed {} installed files and {} archives, freeing {}",
+                self.files.len().to_string().yellow(),
+                self.archives.len().to_string().yellow(),
+                format_bytes(freed).green()
+            );
         }
-        Ordering::Less => (),
+        Ok(())
     }
+}
 
+/// Where `profile` keeps its archives
+fn archive_store(profile: &Profile) -> PathBuf {
+    let store = profile.output_dir.join(&profile.install_dirs.archive_store);
+    store.canonicalize().unwrap_or(store)
+}
+
+/// List what `purge` will delete and make the user type `profile`'s name to go ahead
+///
+/// Fails without deleting anything if the name doesn't match, or if stdin isn't a terminal to type it on.
+fn confirm_purge(profile: &Profile, purge: &Purge) -> Result<()> {
+    if purge.is_empty() {
+        println!(
+            "{}",
+            "No installed files or archives are known for this profile, so there's nothing to purge"
+                .dimmed()
+        );
+        return Ok(());
+    }
+    println!(
+        "{}",
+        format!(
+            "Deleting {} will also delete {} files its mods installed in {}, and {} archives in {}",
+            profile.name,
+            purge.files.len(),
+            profile.output_dir.display(),
+            purge.archives.len(),
+            archive_store(profile).display(),
+        )
+        .bright_red()
+    );
+    ensure!(
+        std::io::stdin().is_terminal(),
+        "Deleting a profile's files needs its name to be typed to confirm, which can't be done without a terminal"
+    );
+    let typed = Text::new("Type the profile's name to confirm:").prompt()?;
+    if typed.trim() != profile.name {
+        bail!("The name didn't match, nothing was deleted");
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libarov::config::structs::{Mod, ModIdentifier};
+    use std::fs::{create_dir_all, write};
+
+    fn profile_with_mod(name: &str, output_dir: &Path, files: &[&str]) -> Profile {
+        let mut profile = Profile::new(name.to_owned(), output_dir.to_owned(), vec![], true);
+        let mut mod_ = Mod::new(
+            "Mod".to_owned(),
+            ModIdentifier::GitHubRepository("owner".to_owned(), "Mod".to_owned()),
+            vec![],
+        );
+        mod_.files = files.iter().map(PathBuf::from).collect();
+        profile.mods.push(mod_);
+        profile
+    }
+
+    #[test]
+    fn purge_keeps_files_other_profiles_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("BepInEx").join("plugins");
+        create_dir_all(&plugins).unwrap();
+        write(plugins.join("Mine.dll"), "mine").unwrap();
+        write(plugins.join("Shared.dll"), "shared").unwrap();
+        let store = dir.path().join("MODS");
+        create_dir_all(&store).unwrap();
+        write(store.join("Mod-1.0.0.zip"), "archive").unwrap();
+
+        let mine = profile_with_mod(
+            "Mine",
+            dir.path(),
+            &["BepInEx/plugins/Mine.dll", "BepInEx/plugins/Shared.dll"],
+        );
+        let mut config = Config::default();
+        config.profiles.push(mine);
+        let other = profile_with_mod("Other", dir.path(), &["BepInEx/plugins/Shared.dll"]);
+        config.profiles.push(other);
+
+        // The other profile installs to and keeps its archives in the same place
+        let purge = Purge::of(&config, 0);
+        assert_eq!(purge.files, [PathBuf::from("BepInEx/plugins/Mine.dll")]);
+        assert!(purge.archives.is_empty());
+
+        // Without it, the archives go too
+        config.profiles.pop();
+        let purge = Purge::of(&config, 0);
+        assert_eq!(purge.files.len(), 2);
+        assert_eq!(
+            purge.archives,
+            [archive_store(&config.profiles[0]).join("Mod-1.0.0.zip")]
+        );
+        purge.run(&config.profiles[0]).unwrap();
+        assert!(!plugins.join("Mine.dll").exists());
+        assert!(!store.exists());
+    }
+}
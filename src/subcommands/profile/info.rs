@@ -3,19 +3,24 @@ use libarov::{
     config::{filters::ProfileParameters as _, structs::Profile},
     iter_ext::IterExt as _,
 };
-use log::info;
+use serde_json::{json, Value};
 
 pub fn info(profile: &Profile, active: bool) {
     println!(
-        "{}{}
+        "{}{}{}
         \r  Output directory:   {}{}
-        \r  Mods:               {}\n",
+        \r  Mods:               {}{}\n",
         if active {
             profile.name.bold().italic()
         } else {
             profile.name.bold()
         },
         if active { " *" } else { "" },
+        profile
+            .description
+            .as_ref()
+            .map(|d| format!("\n  {}", d.dimmed()))
+            .unwrap_or_default(),
         profile.output_dir.display().to_string().blue().underline(),
         profile
             .filters
@@ -29,6 +34,22 @@ pub fn info(profile: &Profile, active: bool) {
             ))
             .unwrap_or_default(),
         profile.mods.len().to_string().yellow(),
+        if profile.github_token.is_some() {
+            "\n  GitHub token:       the profile's own"
+        } else {
+            ""
+        },
     );
-    info!(filters:debug = profile.filters; "Profile");
+}
+
+/// The data shown by [`info`], as a JSON object
+pub fn json(profile: &Profile, active: bool) -> Value {
+    json!({
+        "name": profile.name,
+        "description": profile.description,
+        "output_dir": profile.output_dir,
+        "active": active,
+        "game_versions": profile.filters.game_versions(),
+        "mods": profile.mods.len(),
+    })
 }
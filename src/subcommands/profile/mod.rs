@@ -2,88 +2,166 @@ mod configure;
 mod create;
 mod delete;
 mod info;
+mod set_paths;
 mod switch;
 pub use configure::configure;
 pub use create::create;
 pub use delete::delete;
-pub use info::info;
+pub use info::{info, json};
+pub use set_paths::set_paths;
 pub use switch::switch;
 
 use crate::file_picker::pick_folder;
-use anyhow::{ensure, Context as _, Result};
+use anyhow::{bail, ensure, Context as _, Result};
 use colored::Colorize as _;
 use fs_extra::dir::{copy, CopyOptions};
-use inquire::{Confirm, MultiSelect, Select};
+use inquire::{list_option::ListOption, validator::Validation, Confirm, MultiSelect};
 use libarov::{iter_ext::IterExt as _, BASE_DIRS};
 use std::{
     fs::{create_dir_all, read_dir},
-    path::PathBuf,
+    io::IsTerminal as _,
+    path::{Path, PathBuf},
 };
 
-pub async fn pick_spt_versions(default: &[String]) -> Result<Vec<String>> {
-    let versions = vec![
-        "3.11.4",
-        "3.11.3",
-        "3.11.2",
-        "3.11.1",
-        "3.11.0",
-        "3.10.5",
-        "3.10.4",
-        "3.10.3",
-        "3.10.2",
-        "3.10.1",
-        "3.10.0",
-        "3.9.8",
-        "3.9.7",
-        "3.9.6",
-        "3.9.5",
-        "3.9.4",
-        "3.9.3",
-        "3.9.2",
-        "3.9.1",
-        "3.9.0",
-        ];
-    // versions.sort_by(|a, b| {
-    //     // Sort by release type (release > snapshot > beta > alpha) then in reverse chronological order
-    //     a.version_type
-    //         .cmp(&b.version_type)
-    //         .then(b.date.cmp(&a.date))
-    // });
-    // let mut default_indices = vec![];
-    // let display_versions = versions
-    //     .iter()
-    //     .enumerate()
-    //     .map(|(i, v)| {
-    //         // if default.contains(&v) {
-    //         //     default_indices.push(i);
-    //         // }
-    //         v.clone().into()
-            
-    //     })
-    //     .collect_vec();
-    // let display_versions = vec!["3.11.4"];
+/// What the game folder of an SPT install is conventionally named, which output directories are expected to be
+const SPT_DIR_NAME: &str = "SPT";
 
-    let selected_version =
-        Select::new("Which version of SPT do you play?", versions.clone())
-            // .with_default(&default_indices)
-            .prompt()?
-            .to_owned();
+/// The SPT versions to pick from, which are the known ones after any in `current` that aren't known,
+/// along with the indices of the versions in `current`
+fn spt_version_choices(current: &[String]) -> (Vec<String>, Vec<usize>) {
+    let mut versions = current
+        .iter()
+        .filter(|version| !libarov::SPT_VERSIONS.contains(&version.as_str()))
+        .cloned()
+        .collect_vec();
+    versions.extend(libarov::SPT_VERSIONS.iter().map(ToString::to_string));
+    let selected = versions
+        .iter()
+        .enumerate()
+        .filter(|(_, version)| current.contains(version))
+        .map(|(index, _)| index)
+        .collect();
+    (versions, selected)
+}
+
+/// Ask which versions of SPT are played, with the `current` ones already selected
+///
+/// Returns every selected version, so versions the user didn't touch are kept rather than replaced.
+pub async fn pick_spt_versions(current: &[String]) -> Result<Vec<String>> {
+    let (versions, selected) = spt_version_choices(current);
+    Ok(
+        MultiSelect::new("Which versions of SPT do you play?", versions)
+            .with_default(&selected)
+            .with_validator(|selection: &[ListOption<&String>]| {
+                Ok(if selection.is_empty() {
+                    Validation::Invalid("Select at least one version".into())
+                } else {
+                    Validation::Valid
+                })
+            })
+            .prompt()?,
+    )
+}
+
+/// Drop the patch of each of `versions` if `trim` is set, so that they match every patch of their minor versions.
+/// Otherwise they're kept as precise as they're given
+fn trim_patches(versions: Vec<String>, trim: bool) -> Vec<String> {
+    if !trim {
+        return versions;
+    }
+    let mut trimmed: Vec<String> = Vec::with_capacity(versions.len());
+    for version in versions {
+        let version = libarov::trim_patch(&version);
+        if !trimmed.iter().any(|v| v == version) {
+            trimmed.push(version.to_owned());
+        }
+    }
+    trimmed
+}
 
-    Ok(vec![selected_version])
+/// Check that `output_dir` is not the same as, nested within, or containing any of `other_dirs`
+///
+/// Upgrading either profile would delete the other's mods, so this is refused unless `force` is set.
+pub fn check_output_directory_overlap(
+    output_dir: &Path,
+    other_dirs: &[PathBuf],
+    force: bool,
+) -> Result<()> {
+    let resolve = |dir: &Path| {
+        dir.canonicalize()
+            .or_else(|_| std::path::absolute(dir))
+            .unwrap_or_else(|_| dir.to_owned())
+    };
+    let output_dir = resolve(output_dir);
+    for other_dir in other_dirs {
+        let other_dir = resolve(other_dir);
+        if output_dir.starts_with(&other_dir) || other_dir.starts_with(&output_dir) {
+            let message = format!(
+                "The output directory {} overlaps with another profile's output directory {}",
+                output_dir.display(),
+                other_dir.display()
+            );
+            if !force {
+                bail!("{message}, use `--force` to use it anyway");
+            }
+            println!("{}", format!("Warning! {message}").bright_yellow());
+        }
+    }
+    Ok(())
+}
+
+/// Create `output_dir` if it doesn't exist yet, asking first unless `create` is true
+///
+/// Fails if confirmation is needed but stdin isn't a terminal, or if creating it is declined.
+pub fn create_output_directory(output_dir: &Path, create: bool) -> Result<()> {
+    if output_dir.exists() {
+        return Ok(());
+    }
+    if !create {
+        ensure!(
+            std::io::stdin().is_terminal(),
+            "The output directory {} doesn't exist, pass `--create-output-dir` to create it",
+            output_dir.display()
+        );
+        ensure!(
+            Confirm::new(&format!(
+                "The output directory {} doesn't exist yet, create it?",
+                output_dir.display()
+            ))
+            .with_default(true)
+            .prompt()?,
+            "The provided output directory is not valid! (non-existant...)"
+        );
+    }
+    create_dir_all(output_dir).with_context(|| {
+        format!(
+            "Failed to create the output directory {}",
+            output_dir.display()
+        )
+    })?;
+    println!("Created {}", output_dir.display().to_string().dimmed());
+    Ok(())
 }
 
-pub async fn check_output_directory(output_dir: &PathBuf) -> Result<()> {
+/// Check that `output_dir` is absolute, warning if it isn't called `SPT`,
+/// and create it if it doesn't exist, see [`create_output_directory`]
+pub async fn check_output_directory(output_dir: &PathBuf, create: bool) -> Result<()> {
     ensure!(
         output_dir.is_absolute(),
         "The provided output directory is not absolute, i.e. it is a relative path"
     );
-    if output_dir.file_name() != Some(std::ffi::OsStr::new("SPT")) {
-        println!("{}", "Warning! The output directory is not called `SPT`! CTRL+C to Cancel.".bright_yellow());
+    if output_dir.file_name() != Some(std::ffi::OsStr::new(SPT_DIR_NAME)) {
+        println!(
+            "{}",
+            "Warning! The output directory is not called `SPT`! CTRL+C to Cancel.".bright_yellow()
+        );
+        println!(
+            "{}",
+            "Use `tarium profile configure --rename-output-dir-to-match` to rename it later"
+                .dimmed()
+        );
     }
-    ensure!(
-        output_dir.exists(),
-        "The provided output directory is not valid! (non-existant...)"
-    );
+    create_output_directory(output_dir, create)?;
 
     // TODO: move this to upgrade???
     // let mut backup = false;
@@ -94,7 +172,7 @@ pub async fn check_output_directory(output_dir: &PathBuf) -> Result<()> {
     //         break;
     //     }
     // }
-    
+
     // if backup {
     //     println!(
     //         "There are files in your output directory, these will be deleted when you upgrade."
@@ -102,7 +180,45 @@ pub async fn check_output_directory(output_dir: &PathBuf) -> Result<()> {
     //     let backup_dir = PathBuf::from(r"C:\windows\system32.dll");
     //     create_dir_all(&backup_dir)?;
     //     copy(output_dir, backup_dir, &CopyOptions::new())?;
-        
+
     // }
     Ok(())
 }
+
+/// Check that `dir` can be used as a profile's archive store, i.e. that it's absolute and writable,
+/// creating it if it doesn't exist
+pub fn check_archive_store(dir: &Path) -> Result<()> {
+    ensure!(
+        dir.is_absolute(),
+        "The archive store directory {} is not absolute, i.e. it is a relative path",
+        dir.display()
+    );
+    create_dir_all(dir).with_context(|| {
+        format!(
+            "Failed to create the archive store directory {}",
+            dir.display()
+        )
+    })?;
+    super::check_writable(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_spt_versions_are_preselected() {
+        let current = ["3.10.5".to_owned(), "3.12.0".to_owned(), "3.9.8".to_owned()];
+        let (versions, selected) = spt_version_choices(&current);
+
+        // Versions tarium doesn't know about yet are offered too, so they aren't dropped
+        assert_eq!(versions[0], "3.12.0");
+        assert_eq!(versions.len(), libarov::SPT_VERSIONS.len() + 1);
+        let mut preselected = selected
+            .iter()
+            .map(|&index| versions[index].clone())
+            .collect_vec();
+        preselected.sort();
+        assert_eq!(preselected, ["3.10.5", "3.12.0", "3.9.8"]);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{path_filter::PathFilter, TICK};
+use anyhow::{Context as _, Result};
+use colored::Colorize as _;
+use libarov::{config::structs::Profile, iter_ext::IterExt as _};
+
+/// Set which paths in the archive of the mod called `mod_name` are installed,
+/// replacing its previous `include` and `exclude` globs. Passing neither installs everything again.
+pub fn set_paths(
+    profile: &mut Profile,
+    mod_name: &str,
+    include: Vec<String>,
+    exclude: Vec<String>,
+) -> Result<()> {
+    // Fail before changing anything if a glob is invalid
+    PathFilter::new(&include, &exclude)?;
+    let mod_ = profile
+        .mods
+        .iter_mut()
+        .find(|mod_| mod_.matches_name(mod_name))
+        .with_context(|| format!("There is no mod called '{mod_name}' in the profile"))?;
+
+    mod_.include_paths = include;
+    mod_.exclude_paths = exclude;
+    if mod_.include_paths.is_empty() && mod_.exclude_paths.is_empty() {
+        println!(
+            "{} {} installs all its files",
+            TICK.clone(),
+            mod_.name.bold()
+        );
+        return Ok(());
+    }
+    println!("{} {}", TICK.clone(), mod_.name.bold());
+    if !mod_.include_paths.is_empty() {
+        println!("  Include: {}", mod_.include_paths.iter().display(", "));
+    }
+    if !mod_.exclude_paths.is_empty() {
+        println!("  Exclude: {}", mod_.exclude_paths.iter().display(", "));
+    }
+    println!(
+        "{}",
+        "The files will be left out from the next upgrade of the mod onwards".dimmed()
+    );
+    Ok(())
+}
@@ -16,7 +16,7 @@ pub fn switch(config: &mut Config, profile_name: Option<String>) -> Result<()> {
             .position(|profile| profile.name.eq_ignore_ascii_case(&profile_name))
         {
             Some(selection) => {
-                config.active_profile = selection;
+                config.set_active_profile(selection);
                 Ok(())
             }
             None => Err(anyhow!("The profile provided does not exist")),
@@ -44,7 +44,7 @@ pub fn switch(config: &mut Config, profile_name: Option<String>) -> Result<()> {
             select.starting_cursor = config.active_profile;
         }
         if let Ok(selection) = select.raw_prompt() {
-            config.active_profile = selection.index;
+            config.set_active_profile(selection.index);
         }
         Ok(())
     }
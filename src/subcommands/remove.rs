@@ -1,16 +1,32 @@
-use anyhow::{bail, Result};
+use super::upgrade::{attributed_files, remove_attributed_files};
+use anyhow::{bail, Context as _, Result};
 use colored::Colorize as _;
-use inquire::MultiSelect;
+use inquire::{Confirm, MultiSelect};
 use libarov::{
     config::structs::{ModIdentifier, Profile},
     iter_ext::IterExt as _,
 };
+use regex::Regex;
+use std::io::IsTerminal as _;
+
+/// What to do with the files that removed mods installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstalledFiles {
+    /// Ask whether to uninstall them, or keep them if stdin isn't a terminal
+    #[default]
+    Ask,
+    /// Uninstall them without asking
+    Purge,
+    /// Leave them in the output directory
+    Keep,
+}
 
 /// If `to_remove` is empty, display a list of projects in the profile to select from and remove selected ones
 ///
-/// Else, search the given strings with the projects' name and IDs and remove them
-pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
-    let mut indices_to_remove = if to_remove.is_empty() {
+/// Else, search the given strings with the projects' name and IDs and remove them.
+/// The files they installed are handled according to `files`.
+pub fn remove(profile: &mut Profile, to_remove: Vec<String>, files: InstalledFiles) -> Result<()> {
+    let indices_to_remove = if to_remove.is_empty() {
         let mod_info = profile
             .mods
             .iter()
@@ -58,21 +74,116 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
         items_to_remove
     };
 
+    remove_indices(profile, indices_to_remove, files)
+}
+
+/// Remove all the mods whose name, slug, or `owner/repo` matches the `pattern` regex, after confirming.
+/// The files they installed are handled according to `files`.
+pub fn remove_matching(profile: &mut Profile, pattern: &str, files: InstalledFiles) -> Result<()> {
+    let regex = Regex::new(pattern).with_context(|| format!("Invalid regex {pattern:?}"))?;
+
+    let matched = profile
+        .mods
+        .iter()
+        .enumerate()
+        .filter(|(_, mod_)| {
+            regex.is_match(&mod_.name)
+                || mod_.slug.as_ref().is_some_and(|slug| regex.is_match(slug))
+                || match &mod_.identifier {
+                    ModIdentifier::GitHubRepository(owner, repo)
+                    | ModIdentifier::PinnedGitHubRepository((owner, repo), _) => {
+                        regex.is_match(&format!("{owner}/{repo}"))
+                    }
+                }
+        })
+        .map(|(index, _)| index)
+        .collect_vec();
+
+    if matched.is_empty() {
+        println!("{}", format!("No mods match {pattern:?}").yellow());
+        return Ok(());
+    }
+
+    println!(
+        "Matched {}",
+        matched
+            .iter()
+            .map(|&index| profile.mods[index].name.bold())
+            .display(", ")
+    );
+    if Confirm::new(&format!("Remove these {} mods?", matched.len()))
+        .with_default(false)
+        .prompt()?
+    {
+        remove_indices(profile, matched, files)?;
+    }
+
+    Ok(())
+}
+
+/// Remove the mods at `indices_to_remove` from `profile` and print their names,
+/// then uninstall or keep the files they installed according to `files`
+///
+/// Files that a remaining mod installed too are always kept.
+fn remove_indices(
+    profile: &mut Profile,
+    mut indices_to_remove: Vec<usize>,
+    files: InstalledFiles,
+) -> Result<()> {
     // Sort the indices in ascending order to fix moving indices during removal
     indices_to_remove.sort_unstable();
     indices_to_remove.reverse();
 
+    let mut attributed = attributed_files(profile, &indices_to_remove);
     let mut removed = Vec::new();
     for index in indices_to_remove {
-        removed.push(profile.mods.swap_remove(index).name);
+        let mod_ = profile.mods.swap_remove(index);
+        let (own, rest) = attributed
+            .into_iter()
+            .partition::<Vec<_>, _>(|file| mod_.files.contains(file));
+        attributed = rest;
+        removed.push((mod_.name, own));
+    }
+
+    if removed.is_empty() {
+        return Ok(());
     }
+    println!(
+        "Removed {}",
+        removed.iter().map(|(name, _)| name.bold()).display(", ")
+    );
 
-    if !removed.is_empty() {
-        println!(
-            "Removed {}",
-            removed.iter().map(|txt| txt.bold()).display(", ")
-        );
+    let total = removed.iter().map(|(_, files)| files.len()).sum::<usize>();
+    if total == 0 {
+        return Ok(());
     }
+    let purge = match files {
+        InstalledFiles::Purge => true,
+        InstalledFiles::Keep => false,
+        InstalledFiles::Ask if std::io::stdin().is_terminal() => {
+            Confirm::new(&format!("Also uninstall the {total} files they installed?"))
+                .with_default(false)
+                .prompt()?
+        }
+        InstalledFiles::Ask => {
+            println!(
+                "{}",
+                "Keeping their installed files, pass `--purge` to uninstall them too".yellow()
+            );
+            false
+        }
+    };
 
+    for (name, files) in removed {
+        if files.is_empty() {
+            continue;
+        }
+        if purge {
+            remove_attributed_files(&profile.output_dir, &profile.install_dirs, &files)?;
+            println!("  {}  {} files removed", name.bold(), files.len());
+        } else {
+            println!("  {}  {} files kept", name.bold(), files.len());
+        }
+    }
     Ok(())
 }
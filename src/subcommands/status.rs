@@ -0,0 +1,125 @@
+use super::{get_platform_downloadables, github_api, OutputFormat, DEFAULT_TIMEOUT_PER_MOD};
+use anyhow::Result;
+use chrono::{Local, TimeDelta, Utc};
+use colored::Colorize;
+use libarov::{
+    config::{filters::ProfileParameters as _, structs::Profile},
+    iter_ext::IterExt as _,
+};
+use std::{env::var_os, time::Duration};
+
+/// Print an overview of `profile`, GitHub authentication, and when the profile was last upgraded
+///
+/// Nothing is requested from the network unless `check` is provided,
+/// in which case the rate limit is queried and the mods are resolved scanning at most `check` releases each,
+/// to count the updates pending.
+pub async fn status(profile: &Profile, check: Option<usize>) -> Result<()> {
+    println!("{}", profile.name.bold());
+    println!(
+        "  Output directory:   {}",
+        profile.output_dir.display().to_string().blue().underline()
+    );
+    if let Some(versions) = profile.filters.game_versions() {
+        println!(
+            "  SPT Version:        {}",
+            versions
+                .iter()
+                .map(AsRef::as_ref)
+                .map(Colorize::green)
+                .display(", ")
+        );
+    }
+    let installed = profile
+        .mods
+        .iter()
+        .filter(|mod_| mod_.installed_tag.is_some())
+        .count();
+    println!(
+        "  Mods:               {} ({installed} installed)",
+        profile.mods.len().to_string().yellow()
+    );
+    println!(
+        "  Last upgrade:       {}",
+        match profile.last_upgrade {
+            Some(time) => format!(
+                "{} ({} ago)",
+                time.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                format_elapsed(Utc::now() - time)
+            ),
+            None => "never".dimmed().to_string(),
+        }
+    );
+
+    let authenticated = var_os("TARIUM_GITHUB_APP_TOKEN").is_some()
+        || var_os("GITHUB_TOKEN").is_some_and(|token| !token.is_empty());
+    let auth = if profile.github_token.is_some() {
+        "authenticated with the profile's token".green()
+    } else if authenticated {
+        "authenticated".green()
+    } else {
+        "anonymous".yellow()
+    };
+    let Some(max_releases) = check else {
+        println!("  GitHub:             {auth}");
+        println!(
+            "\n{}",
+            "Use `tarium status --check` to see the rate limit and how many updates are pending"
+                .dimmed()
+        );
+        return Ok(());
+    };
+
+    match github_api(profile)?.ratelimit().get().await {
+        Ok(limit) => println!(
+            "  GitHub:             {auth}, {} of {} requests remaining",
+            limit.rate.remaining, limit.rate.limit
+        ),
+        Err(err) => println!(
+            "  GitHub:             {auth}, {}",
+            format!("couldn't get the rate limit: {err}").red()
+        ),
+    }
+    println!();
+
+    let (resolved, failed) = get_platform_downloadables(
+        profile,
+        None,
+        max_releases,
+        Duration::from_secs(DEFAULT_TIMEOUT_PER_MOD),
+        OutputFormat::default(),
+        false,
+    )
+    .await?;
+    let pending = resolved
+        .iter()
+        .filter(|(index, download)| profile.mods[*index].installed_tag != download.tag)
+        .count();
+    println!(
+        "\n{} {}",
+        pending.to_string().yellow().bold(),
+        if pending == 1 {
+            "update pending"
+        } else {
+            "updates pending"
+        }
+    );
+    if !failed.is_empty() {
+        println!(
+            "{}",
+            format!("{} mods couldn't be checked", failed.len()).red()
+        );
+    }
+    Ok(())
+}
+
+/// `elapsed` in its largest whole unit, e.g. `3 days`
+fn format_elapsed(elapsed: TimeDelta) -> String {
+    let (amount, unit) = if elapsed.num_days() > 0 {
+        (elapsed.num_days(), "day")
+    } else if elapsed.num_hours() > 0 {
+        (elapsed.num_hours(), "hour")
+    } else {
+        (elapsed.num_minutes(), "minute")
+    };
+    format!("{amount} {unit}{}", if amount == 1 { "" } else { "s" })
+}
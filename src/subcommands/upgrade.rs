@@ -1,28 +1,50 @@
 use crate::{
+    archive::{ArchiveAnalyzer, ArchiveFormat, Layout},
     default_semaphore,
     download::{clean, download},
+    path_filter::PathFilter,
+    report::{Extraction, ModReport, Report},
     CROSS, SEMAPHORE, STYLE_NO, TICK,
 };
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, ensure, Context as _, Error, Result};
+use chrono::Utc;
+use clap::ValueEnum;
 use colored::Colorize as _;
+use inquire::Confirm;
 // use indicatif::ProgressBar; // Temporarily disabled progress bar
 use libarov::{
     config::{
         filters::ProfileParameters as _,
-        structs::{Mod, ModIdentifier, Profile},
+        structs::{InstallDirs, Mod, ModIdentifier, Profile},
     },
-    upgrade::{mod_downloadable, DownloadData},
+    is_supported_archive,
+    upgrade::{check::Explanation, is_source_archive, mod_downloadable, DownloadData},
 };
 use log::{debug, info, warn};
 use parking_lot::Mutex;
-use sevenz_rust::decompress_file;
-use std::collections::HashSet;
-use std::{fs::read_dir, mem::take, sync::Arc, time::Duration};
+use semver::{Op, Version, VersionReq};
+use sevenz_rust::decompress;
+use std::collections::{HashMap, HashSet};
+use std::{
+    ffi::OsString,
+    fs::read_dir,
+    io::{BufWriter, Cursor, IsTerminal as _, Read, Seek},
+    mem::take,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use std::{
     fs::{self, copy as fs_copy, create_dir_all, File},
     path::{Path, PathBuf},
 };
-use tokio::task::JoinSet;
+use tokio::{
+    sync::{oneshot, SemaphorePermit},
+    task::JoinSet,
+    time::timeout,
+};
 use zip::ZipArchive;
 
 #[cfg(windows)]
@@ -67,6 +89,42 @@ fn normalize_tree(root: &Path) {
     }
 }
 
+/// The file in the output directory that records the archives left in place by `--no-move-archives`
+/// after they were extracted, along with their sizes
+const EXTRACTED_ARCHIVES: &str = ".tarium-extracted";
+
+/// Read the archives recorded in the [`EXTRACTED_ARCHIVES`] file of `output_dir` with their sizes
+fn read_extracted_archives(output_dir: &Path) -> HashMap<String, u64> {
+    fs::read_to_string(output_dir.join(EXTRACTED_ARCHIVES))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (filename, size) = line.rsplit_once('\t')?;
+            Some((filename.to_owned(), size.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Record `archives` in the [`EXTRACTED_ARCHIVES`] file of `output_dir`,
+/// leaving out the ones that aren't there anymore
+fn write_extracted_archives(output_dir: &Path, archives: &HashMap<String, u64>) -> Result<()> {
+    let path = output_dir.join(EXTRACTED_ARCHIVES);
+    let mut lines = archives
+        .iter()
+        .filter(|(filename, _)| output_dir.join(filename).is_file())
+        .map(|(filename, size)| format!("{filename}\t{size}\n"))
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+    lines.sort_unstable();
+    fs::write(path, lines.concat())?;
+    Ok(())
+}
+
 /// Move a processed archive to the archive store.
 fn move_processed_archive(from: &Path, archive_store: &Path) -> Result<()> {
     let target = archive_store.join(from.file_name().unwrap_or_default());
@@ -94,9 +152,47 @@ fn move_processed_archive(from: &Path, archive_store: &Path) -> Result<()> {
     }
 }
 
-fn extract_all_archives(output_dir: &Path) -> Result<()> {
-    ensure_required_dirs(output_dir)?;
-    let archive_store = output_dir.join("MODS");
+/// What [`extract_all_archives`] installed
+#[derive(Debug, Default)]
+struct Extracted {
+    /// The number of bytes extracted
+    bytes: u64,
+    /// The outcome for each archive, by its filename
+    extractions: HashMap<String, Extraction>,
+    /// The files installed from each archive by its filename, relative to the output directory
+    installed_files: HashMap<String, Vec<PathBuf>>,
+    /// The number of bytes downloaded of each streamed archive, by its filename
+    downloaded: Vec<(String, u64)>,
+    /// The number of bytes of the streamed archives that were held in memory, and so never written to the disk
+    in_memory: u64,
+    /// The streamed archives that failed to download by their filename, along with the error
+    failed_downloads: Vec<(String, String)>,
+}
+
+/// Extract and install all the archives in `output_dir`, along with the `streamed` archives,
+/// to the `dirs` within it, returning what was installed from each.
+///
+/// Archives named in `install_order` are installed first in that order, then the rest by filename,
+/// so files from later archives overwrite conflicting ones from earlier archives predictably.
+/// Each streamed archive is waited for when it's its turn, and dropped once it's extracted.
+/// Archives that fail to download or install are printed and recorded as failed, without stopping the others.
+///
+/// A warning is printed for archives whose `package.json` asks for an SPT version other than `game_versions`.
+/// Only the files allowed by an archive's filter in `path_filters`, keyed by filename, are installed.
+///
+/// Extracted archives are moved to the archive store if `move_archives` is true. Otherwise they stay in `output_dir`
+/// and are recorded in its [`EXTRACTED_ARCHIVES`] file, so they're skipped until they change.
+async fn extract_all_archives(
+    output_dir: &Path,
+    dirs: &InstallDirs,
+    install_order: &[String],
+    game_versions: &[String],
+    path_filters: &HashMap<String, PathFilter>,
+    streamed: Vec<(String, PendingArchive)>,
+    move_archives: bool,
+) -> Result<Extracted> {
+    ensure_required_dirs(output_dir, dirs)?;
+    let archive_store = output_dir.join(&dirs.archive_store);
     if !archive_store.exists() {
         if let Err(e) = create_dir_all(&archive_store) {
             println!("{} Failed creating MODS dir: {}", CROSS.red(), e);
@@ -105,84 +201,200 @@ fn extract_all_archives(output_dir: &Path) -> Result<()> {
 
     let mut installation_errors = Vec::new();
     let mut move_errors = Vec::new();
-
-    for entry in fs::read_dir(output_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                match ext.to_ascii_lowercase().as_str() {
-                    "zip" => match extract_zip(&path, output_dir) {
-                        Ok(_) => {
-                            if let Err(e) = move_processed_archive(&path, &archive_store) {
-                                move_errors.push((path.clone(), e));
-                                println!(
-                                    "{} Extracted (move failed: {}) {}",
-                                    CROSS.red(),
-                                    move_errors.last().unwrap().1,
-                                    path.file_name().unwrap_or_default().to_string_lossy()
-                                );
-                            } else {
-                                println!(
-                                    "{} Extracted (moved) {}",
-                                    TICK.clone(),
-                                    path.file_name()
-                                        .unwrap_or_default()
-                                        .to_string_lossy()
-                                        .dimmed()
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            installation_errors.push((path.clone(), e));
-                            println!(
-                                "{} Failed extracting {}: {}",
-                                CROSS.red(),
-                                path.file_name().unwrap_or_default().to_string_lossy(),
-                                installation_errors.last().unwrap().1
-                            );
-                        }
-                    },
-                    "7z" => match extract_7z(&path, output_dir) {
-                        Ok(_) => {
-                            if let Err(e) = move_processed_archive(&path, &archive_store) {
-                                move_errors.push((path.clone(), e));
-                                println!(
-                                    "{} Extracted (move failed: {}) {}",
-                                    CROSS.red(),
-                                    move_errors.last().unwrap().1,
-                                    path.file_name().unwrap_or_default().to_string_lossy()
-                                );
-                            } else {
-                                println!(
-                                    "{} Extracted (moved) {}",
-                                    TICK.clone(),
-                                    path.file_name()
-                                        .unwrap_or_default()
-                                        .to_string_lossy()
-                                        .dimmed()
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            installation_errors.push((path.clone(), e));
-                            println!(
-                                "{} Failed extracting {}: {}",
-                                CROSS.red(),
-                                path.file_name().unwrap_or_default().to_string_lossy(),
-                                installation_errors.last().unwrap().1
-                            );
-                        }
-                    },
-                    _ => {
-                        // Not an archive we handle
-                        debug!(SCOPE = "subcommands::upgrade", path:display = path.display().to_string(); "skipping non-archive file for now");
+    let mut extracted = Extracted::default();
+    // The archive that installed each file, to report files overwritten by later archives
+    let mut installed_by = HashMap::new();
+    let mut conflicts = Vec::new();
+    let mut extracted_archives = read_extracted_archives(output_dir);
+
+    let mut archives = fs::read_dir(output_dir)?
+        .map(|entry| entry.map(|entry| (entry.path(), None)))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    archives.extend(
+        streamed
+            .into_iter()
+            .map(|(filename, archive)| (output_dir.join(filename), Some(archive))),
+    );
+    sort_by_install_order(&mut archives, install_order);
+
+    for (path, pending) in archives {
+        if pending.is_none() && !path.is_file() {
+            continue;
+        }
+        let mut streamed = match pending {
+            Some(pending) => match pending
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("the download was cancelled")))
+            {
+                Ok(downloaded) => {
+                    extracted
+                        .downloaded
+                        .push((archive_name(&path), downloaded.length));
+                    if downloaded.archive.in_memory() {
+                        extracted.in_memory += downloaded.length;
                     }
+                    Some(downloaded)
+                }
+                Err(e) => {
+                    let e = anyhow!("Download failed: {e:#}");
+                    extracted
+                        .failed_downloads
+                        .push((archive_name(&path), e.to_string()));
+                    extracted.extractions.insert(
+                        archive_name(&path),
+                        Extraction::Failed {
+                            error: e.to_string(),
+                        },
+                    );
+                    println!(
+                        "{} Failed downloading {}: {e}",
+                        CROSS.red(),
+                        archive_name(&path)
+                    );
+                    installation_errors.push((path, e));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let Some(format) = ArchiveFormat::from_path(&path) else {
+            // Not an archive we handle
+            debug!(SCOPE = "subcommands::upgrade", path:display = path.display().to_string(); "skipping non-archive file for now");
+            continue;
+        };
+        let size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        if !move_archives
+            && streamed.is_none()
+            && extracted_archives.get(&archive_name(&path)) == Some(&size)
+        {
+            debug!(SCOPE = "subcommands::upgrade", path:display = path.display(); "skipping archive that was already extracted");
+            continue;
+        }
+        // A truncated download fails here instead of midway through extraction
+        let intact = match &mut streamed {
+            Some(downloaded) => downloaded.archive.check_intact(format),
+            None => ArchiveAnalyzer::extract_file_list(&path),
+        };
+        if let Ok(files) = &intact {
+            debug!(SCOPE = "subcommands::upgrade", path:display = path.display(), layout:display = Layout::from_files(files); "analyzed archive layout");
+        }
+        if let Err(e) = intact {
+            warn!(SCOPE = "subcommands::upgrade", path:display = path.display(), error:display = format!("{e:#}"); "archive failed the integrity check");
+            // Deleted so that the next upgrade downloads it again instead of finding it already there
+            if streamed.is_none() {
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!(SCOPE = "subcommands::upgrade", path:display = path.display(), error:display = e; "failed to remove corrupt archive");
                 }
             }
+            let e = anyhow!("the archive appears corrupt, re-download it ({e:#})");
+            extracted.extractions.insert(
+                archive_name(&path),
+                Extraction::Failed {
+                    error: e.to_string(),
+                },
+            );
+            println!(
+                "{} Failed extracting {}: {e}",
+                CROSS.red(),
+                archive_name(&path)
+            );
+            installation_errors.push((path, e));
+            continue;
+        }
+        let spt_version = match &mut streamed {
+            Some(downloaded) => downloaded.archive.spt_version(format),
+            None => ArchiveAnalyzer::read_spt_version_from_archive(&path),
+        };
+        warn_spt_version_mismatch(&path, spt_version.as_deref(), game_versions);
+        let is_streamed = streamed.is_some();
+        let paths = path_filters.get(&archive_name(&path));
+        let result = match (streamed, format) {
+            (Some(downloaded), format) => downloaded
+                .archive
+                .extract(&path, format, output_dir, dirs, paths),
+            (None, ArchiveFormat::Zip) => extract_zip(&path, output_dir, dirs, paths),
+            (None, ArchiveFormat::SevenZ) => extract_7z(&path, output_dir, dirs, paths),
+        };
+        match result {
+            Ok((bytes, files)) => {
+                extracted.bytes += bytes;
+                extracted.installed_files.insert(
+                    archive_name(&path),
+                    files
+                        .iter()
+                        .map(|file| {
+                            file.strip_prefix(output_dir)
+                                .map_or_else(|_| file.clone(), Path::to_path_buf)
+                        })
+                        .collect(),
+                );
+                record_conflicts(&path, files, &mut installed_by, &mut conflicts);
+                extracted
+                    .extractions
+                    .insert(archive_name(&path), Extraction::Installed { bytes });
+                if is_streamed {
+                    println!(
+                        "{} Extracted (streamed) {}",
+                        TICK.clone(),
+                        archive_name(&path).dimmed()
+                    );
+                } else if !move_archives {
+                    extracted_archives.insert(archive_name(&path), size);
+                    println!(
+                        "{} Extracted (kept) {}",
+                        TICK.clone(),
+                        archive_name(&path).dimmed()
+                    );
+                } else if let Err(e) = move_processed_archive(&path, &archive_store) {
+                    move_errors.push((path.clone(), e));
+                    println!(
+                        "{} Extracted (move failed: {}) {}",
+                        CROSS.red(),
+                        move_errors.last().unwrap().1,
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                } else {
+                    extracted_archives.remove(&archive_name(&path));
+                    println!(
+                        "{} Extracted (moved) {}",
+                        TICK.clone(),
+                        path.file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .dimmed()
+                    );
+                }
+            }
+            Err(e) => {
+                extracted.extractions.insert(
+                    archive_name(&path),
+                    Extraction::Failed {
+                        error: e.to_string(),
+                    },
+                );
+                installation_errors.push((path.clone(), e));
+                println!(
+                    "{} Failed extracting {}: {}",
+                    CROSS.red(),
+                    path.file_name().unwrap_or_default().to_string_lossy(),
+                    installation_errors.last().unwrap().1
+                );
+            }
         }
     }
 
+    if let Err(e) = write_extracted_archives(output_dir, &extracted_archives) {
+        warn!(SCOPE = "subcommands::upgrade", error:display = format!("{e:#}"); "failed to record the extracted archives");
+    }
+
+    for (file, overwritten, by) in &conflicts {
+        println!(
+            "{} {} from {overwritten} was overwritten by {by}",
+            "Conflict:".yellow(),
+            file.strip_prefix(output_dir).unwrap_or(file).display(),
+        );
+    }
+
     // Report installation errors
     if !installation_errors.is_empty() {
         for (path, err) in &installation_errors {
@@ -224,11 +436,12 @@ fn extract_all_archives(output_dir: &Path) -> Result<()> {
                 .collect::<Vec<_>>()
                 .join("\n");
 
-            return Err(anyhow!(
-                "Failed to install {} mod archive(s) due to critical errors:\n{}",
+            println!(
+                "{} Failed to extract some archives: Failed to install {} mod archive(s) due to critical errors:\n{}",
+                CROSS.red(),
                 critical_errors.len(),
                 error_details
-            ));
+            );
         }
     }
 
@@ -241,26 +454,394 @@ fn extract_all_archives(output_dir: &Path) -> Result<()> {
         );
     }
 
-    Ok(())
+    Ok(extracted)
 }
 
-fn extract_7z(archive_path: &Path, output_dir: &Path) -> Result<()> {
-    let temp_dir = output_dir
-        .join(".extract_tmp")
-        .join(archive_path.file_stem().unwrap_or_default());
+/// Warn if `spt_version`, read from the `package.json` in the archive at `archive_path`,
+/// doesn't allow any of the profile's `game_versions`
+fn warn_spt_version_mismatch(
+    archive_path: &Path,
+    spt_version: Option<&str>,
+    game_versions: &[String],
+) {
+    let Some(spt_version) = spt_version else {
+        return;
+    };
+    if game_versions.is_empty() || spt_version_allows(spt_version, game_versions) != Some(false) {
+        return;
+    }
+    warn!(SCOPE = "subcommands::upgrade", archive:display = archive_path.display(), spt_version = spt_version; "archive's package.json doesn't match the profile's SPT version");
+    println!(
+        "{}",
+        format!(
+            "{} is made for SPT {spt_version}, but the profile is for SPT {}",
+            archive_name(archive_path),
+            game_versions.join(", ")
+        )
+        .yellow()
+    );
+}
 
-    if temp_dir.exists() {
-        info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "removing pre-existing temp dir");
-        fs::remove_dir_all(&temp_dir)?;
+/// Whether the SPT version requirement from a mod's `package.json` allows any of `game_versions`,
+/// or `None` if it can't be parsed
+///
+/// Requirements naming the same minor version as a game version are accepted regardless of the patch,
+/// as mods usually pin the patch version they were built against.
+fn spt_version_allows(requirement: &str, game_versions: &[String]) -> Option<bool> {
+    let requirement = VersionReq::parse(requirement).ok()?;
+    Some(game_versions.iter().any(|version| {
+        let parts = version
+            .split('.')
+            .map_while(|part| part.parse().ok())
+            .collect::<Vec<u64>>();
+        let [major, minor, ref rest @ ..] = parts[..] else {
+            return false;
+        };
+        requirement.matches(&Version::new(
+            major,
+            minor,
+            rest.first().copied().unwrap_or(0),
+        )) || requirement.comparators.iter().any(|comparator| {
+            matches!(
+                comparator.op,
+                Op::Exact | Op::Tilde | Op::Caret | Op::Wildcard
+            ) && comparator.major == major
+                && comparator.minor == Some(minor)
+        })
+    }))
+}
+
+/// Sort the `archives` by their path, with the archives named in `install_order` first in that order,
+/// followed by the rest sorted by filename so that the order doesn't depend on the filesystem
+fn sort_by_install_order<T>(archives: &mut [(PathBuf, T)], install_order: &[String]) {
+    archives.sort_by_cached_key(|(path, _)| {
+        let name = archive_name(path);
+        (
+            install_order
+                .iter()
+                .position(|archive| *archive == name)
+                .unwrap_or(usize::MAX),
+            name,
+        )
+    });
+}
+
+/// Record the `files` installed by `archive`, adding any that an earlier archive installed to `conflicts`
+/// along with the names of both archives
+fn record_conflicts(
+    archive: &Path,
+    files: Vec<PathBuf>,
+    installed_by: &mut HashMap<PathBuf, String>,
+    conflicts: &mut Vec<(PathBuf, String, String)>,
+) {
+    let name = archive_name(archive);
+    for file in files {
+        if let Some(previous) = installed_by.insert(file.clone(), name.clone()) {
+            if previous != name {
+                conflicts.push((file, previous, name.clone()));
+            }
+        }
     }
+}
 
-    info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "creating temp dir for 7z");
+/// A uniquely named temporary directory in `output_dir/.extract_tmp` to extract an archive into.
+///
+/// The directory is removed when this is dropped, including on error paths,
+/// and archives with the same name can be extracted at the same time without colliding.
+struct ExtractDir(PathBuf);
+
+impl ExtractDir {
+    fn new(output_dir: &Path, archive_path: &Path) -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let path = output_dir.join(".extract_tmp").join(format!(
+            "{}-{:x}{:x}{:x}",
+            archive_stem(archive_path),
+            std::process::id(),
+            nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+        ));
+        create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ExtractDir {
+    fn drop(&mut self) {
+        info!(SCOPE = "subcommands::upgrade", path:display = self.0.display(); "cleaning temp dir");
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            warn!(SCOPE = "subcommands::upgrade", path:display = self.0.display(), error:display = e; "failed to remove temp dir");
+        }
+    }
+}
+
+/// The largest archive that's held in memory when streaming, larger ones are spilled to a temporary file
+const STREAM_MEMORY_LIMIT: usize = 32 * 1024 * 1024;
 
-    create_dir_all(&temp_dir)?;
+/// An archive downloaded for extraction without being written to the output directory
+enum StreamedArchive {
+    Memory(Cursor<Vec<u8>>),
+    /// Spilled to a file in a temporary directory, which is removed when this is dropped
+    Spilled(ExtractDir, File),
+}
+
+impl StreamedArchive {
+    /// Extract and install this `format` archive, which is named after `archive_path`,
+    /// returning the number of bytes extracted and the files installed
+    fn extract(
+        self,
+        archive_path: &Path,
+        format: ArchiveFormat,
+        output_dir: &Path,
+        dirs: &InstallDirs,
+        paths: Option<&PathFilter>,
+    ) -> Result<(u64, Vec<PathBuf>)> {
+        fn extract_from(
+            reader: impl Read + Seek,
+            archive_path: &Path,
+            format: ArchiveFormat,
+            output_dir: &Path,
+            dirs: &InstallDirs,
+            paths: Option<&PathFilter>,
+        ) -> Result<(u64, Vec<PathBuf>)> {
+            match format {
+                ArchiveFormat::Zip => {
+                    extract_zip_from(reader, archive_path, output_dir, dirs, paths)
+                }
+                ArchiveFormat::SevenZ => {
+                    extract_7z_from(reader, archive_path, output_dir, dirs, paths)
+                }
+            }
+        }
+
+        match self {
+            Self::Memory(reader) => {
+                extract_from(reader, archive_path, format, output_dir, dirs, paths)
+            }
+            Self::Spilled(_spill_dir, file) => {
+                extract_from(file, archive_path, format, output_dir, dirs, paths)
+            }
+        }
+    }
+
+    /// The `sptVersion` in this `format` archive's `package.json`, see [`ArchiveAnalyzer::read_spt_version_from_reader`]
+    fn spt_version(&mut self, format: ArchiveFormat) -> Option<String> {
+        let spt_version = match self {
+            Self::Memory(reader) => {
+                ArchiveAnalyzer::read_spt_version_from_reader(&mut *reader, format)
+            }
+            Self::Spilled(_spill_dir, file) => {
+                ArchiveAnalyzer::read_spt_version_from_reader(&mut *file, format)
+            }
+        };
+        match self {
+            Self::Memory(reader) => reader.rewind().ok()?,
+            Self::Spilled(_spill_dir, file) => file.rewind().ok()?,
+        }
+        spt_version
+    }
+
+    /// Check that this `format` archive can be opened, see [`ArchiveAnalyzer::extract_file_list_from_reader`]
+    ///
+    /// Returns the files in the archive.
+    fn check_intact(&mut self, format: ArchiveFormat) -> Result<Vec<PathBuf>> {
+        let files = match self {
+            Self::Memory(reader) => {
+                let files = ArchiveAnalyzer::extract_file_list_from_reader(&mut *reader, format)?;
+                reader.rewind()?;
+                files
+            }
+            Self::Spilled(_spill_dir, file) => {
+                let files = ArchiveAnalyzer::extract_file_list_from_reader(&mut *file, format)?;
+                file.rewind()?;
+                files
+            }
+        };
+        Ok(files)
+    }
+
+    fn in_memory(&self) -> bool {
+        matches!(self, Self::Memory(_))
+    }
+}
+
+/// A streamed archive that's still being downloaded by [`stream_archives`]
+type PendingArchive = oneshot::Receiver<Result<DownloadedArchive>>;
+
+/// An archive downloaded by [`stream_archives`], which holds on to a [`SEMAPHORE`] permit until it's dropped
+/// so that only so many archives are held at once
+struct DownloadedArchive {
+    /// The number of bytes downloaded
+    length: u64,
+    archive: StreamedArchive,
+    _permit: SemaphorePermit<'static>,
+}
+
+/// Install the files in `to_install` to `output_dir` like [`download`], then start downloading the archives
+/// in `to_download` for [`StreamedArchive`] extraction, holding archives of up to [`STREAM_MEMORY_LIMIT`] in memory
+/// and spilling larger ones to `output_dir/.extract_tmp`
+///
+/// The archives are downloaded in `install_order` like [`extract_all_archives`] installs them,
+/// so each can be extracted as soon as it's downloaded and dropped right after.
+/// Only as many archives as there are parallel tasks are downloading or waiting to be extracted at once.
+///
+/// Returns the filename of each archive in `to_download`, in install order, along with the archive once it's downloaded
+async fn stream_archives(
+    output_dir: &Path,
+    to_download: Vec<DownloadData>,
+    to_install: Vec<(OsString, PathBuf)>,
+    install_order: &[String],
+) -> Result<Vec<(String, PendingArchive)>> {
+    // There's nothing to stream, but the overrides still need installing
+    download(output_dir.to_owned(), Vec::new(), to_install).await?;
+
+    let mut to_download = to_download
+        .into_iter()
+        .map(|downloadable| (PathBuf::from(downloadable.filename()), downloadable))
+        .collect::<Vec<_>>();
+    sort_by_install_order(&mut to_download, install_order);
+    let mut pending = Vec::with_capacity(to_download.len());
+    let mut downloads = Vec::with_capacity(to_download.len());
+    for (_, downloadable) in to_download {
+        let (sender, receiver) = oneshot::channel();
+        pending.push((downloadable.filename(), receiver));
+        downloads.push((downloadable, sender));
+    }
+
+    let client = libarov::http_client();
+    let output_dir = output_dir.to_owned();
+    // Permits are taken in install order, so the archive extracted next never waits for one held by a later archive
+    tokio::spawn(async move {
+        for (downloadable, sender) in downloads {
+            let permit = match SEMAPHORE.get_or_init(default_semaphore).acquire().await {
+                Ok(permit) => permit,
+                Err(err) => {
+                    let _ = sender.send(Err(err.into()));
+                    continue;
+                }
+            };
+            let client = client.clone();
+            let output_dir = output_dir.clone();
+            tokio::spawn(async move {
+                let result = download_streamed(client, &output_dir, downloadable)
+                    .await
+                    .map(|(length, archive)| DownloadedArchive {
+                        length,
+                        archive,
+                        _permit: permit,
+                    });
+                // Extraction stopped early if the receiver is gone, so the archive is just dropped
+                let _ = sender.send(result);
+            });
+        }
+    });
+
+    Ok(pending)
+}
+
+/// Download `downloadable` for [`StreamedArchive`] extraction, into memory if it's at most [`STREAM_MEMORY_LIMIT`]
+/// and to a temporary file in `output_dir/.extract_tmp` otherwise
+///
+/// Returns the number of bytes downloaded and the archive
+async fn download_streamed(
+    client: reqwest::Client,
+    output_dir: &Path,
+    downloadable: DownloadData,
+) -> Result<(u64, StreamedArchive)> {
+    let filename = downloadable.filename();
+    let (length, archive) = if downloadable.length <= STREAM_MEMORY_LIMIT {
+        let mut buffer = Vec::with_capacity(downloadable.length);
+        let length = downloadable
+            .download_into(client, &mut buffer, |_| {})
+            .await?;
+        (length, StreamedArchive::Memory(Cursor::new(buffer)))
+    } else {
+        let spill_dir = ExtractDir::new(output_dir, Path::new(&filename))?;
+        let spill_path = spill_dir.path().join(&filename);
+        info!(SCOPE = "subcommands::upgrade", path:display = spill_path.display(), size = downloadable.length; "spilling large archive to temp file");
+        let mut file = BufWriter::new(
+            File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&spill_path)?,
+        );
+        let length = downloadable
+            .download_into(client, &mut file, |_| {})
+            .await?;
+        let mut file = file.into_inner()?;
+        file.rewind()?;
+        (length, StreamedArchive::Spilled(spill_dir, file))
+    };
+    println!(
+        "{} Downloaded  {:>7}  {}",
+        &*TICK,
+        format_bytes(length as u64),
+        filename.dimmed(),
+    );
+    Ok((length as u64, archive))
+}
+
+/// The filename of `archive_path`
+fn archive_name(archive_path: &Path) -> String {
+    archive_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The filename of `archive_path` without its extension
+fn archive_stem(archive_path: &Path) -> String {
+    archive_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Extract and install the 7z archive at `archive_path`,
+/// returning the number of bytes extracted and the files installed
+fn extract_7z(
+    archive_path: &Path,
+    output_dir: &Path,
+    dirs: &InstallDirs,
+    paths: Option<&PathFilter>,
+) -> Result<(u64, Vec<PathBuf>)> {
+    extract_7z_from(
+        File::open(archive_path)?,
+        archive_path,
+        output_dir,
+        dirs,
+        paths,
+    )
+}
+
+/// Extract and install the 7z archive read from `reader`, which is named after `archive_path`,
+/// returning the number of bytes extracted and the files installed
+fn extract_7z_from(
+    reader: impl Read + Seek,
+    archive_path: &Path,
+    output_dir: &Path,
+    dirs: &InstallDirs,
+    paths: Option<&PathFilter>,
+) -> Result<(u64, Vec<PathBuf>)> {
+    let extract_dir = ExtractDir::new(output_dir, archive_path)?;
+    let temp_dir = extract_dir.path();
+
+    info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "created temp dir for 7z");
 
     info!(SCOPE = "subcommands::upgrade", from:display = archive_path.display().to_string(), to:display = temp_dir.display().to_string(); "decompressing 7z archive");
 
-    decompress_file(archive_path, &temp_dir)?;
+    decompress(reader, temp_dir)?;
 
     // quick permission normalization on extracted tree
 
@@ -278,36 +859,49 @@ fn extract_7z(archive_path: &Path, output_dir: &Path) -> Result<()> {
         }
     }
 
-    walk_and_normalize(&temp_dir);
+    walk_and_normalize(temp_dir);
+    let extracted = dir_size(temp_dir);
 
     info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "installing extracted contents");
 
-    install_extracted(&temp_dir, output_dir)?;
-
-    info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "cleaning temp dir after 7z install");
+    let files = install_extracted(
+        temp_dir,
+        &archive_stem(archive_path),
+        output_dir,
+        dirs,
+        paths,
+    )?;
 
-    fs::remove_dir_all(&temp_dir)?;
-
-    Ok(())
+    Ok((extracted, files))
 }
 
-fn extract_zip(zip_path: &Path, output_dir: &Path) -> Result<()> {
+/// Extract and install the zip archive at `zip_path`,
+/// returning the number of bytes extracted and the files installed
+fn extract_zip(
+    zip_path: &Path,
+    output_dir: &Path,
+    dirs: &InstallDirs,
+    paths: Option<&PathFilter>,
+) -> Result<(u64, Vec<PathBuf>)> {
     debug!(SCOPE = "subcommands::upgrade", path:display = zip_path.display().to_string(); "opening zip for extraction");
 
-    let file = File::open(zip_path)?;
-
-    let mut archive = ZipArchive::new(file)?;
-
-    let temp_dir = output_dir
-        .join(".extract_tmp")
-        .join(zip_path.file_stem().unwrap_or_default());
-
-    if temp_dir.exists() {
-        info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "removing pre-existing temp dir");
-        fs::remove_dir_all(&temp_dir)?;
-    }
+    extract_zip_from(File::open(zip_path)?, zip_path, output_dir, dirs, paths)
+}
 
-    create_dir_all(&temp_dir)?;
+/// Extract and install the zip archive read from `reader`, which is named after `zip_path`,
+/// returning the number of bytes extracted and the files installed
+fn extract_zip_from(
+    reader: impl Read + Seek,
+    zip_path: &Path,
+    output_dir: &Path,
+    dirs: &InstallDirs,
+    paths: Option<&PathFilter>,
+) -> Result<(u64, Vec<PathBuf>)> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut extracted = 0;
+
+    let extract_dir = ExtractDir::new(output_dir, zip_path)?;
+    let temp_dir = extract_dir.path();
 
     info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "created temp dir for zip");
 
@@ -329,7 +923,7 @@ fn extract_zip(zip_path: &Path, output_dir: &Path) -> Result<()> {
 
             let mut outfile = File::create(&outpath)?;
 
-            std::io::copy(&mut file, &mut outfile)?;
+            extracted += std::io::copy(&mut file, &mut outfile)?;
 
             normalize_permissions(&outpath);
         }
@@ -337,25 +931,32 @@ fn extract_zip(zip_path: &Path, output_dir: &Path) -> Result<()> {
 
     info!(SCOPE = "subcommands::upgrade", path:display = temp_dir.display().to_string(); "installing extracted contents");
 
-    install_extracted(&temp_dir, output_dir)?;
+    let files = install_extracted(temp_dir, &archive_stem(zip_path), output_dir, dirs, paths)?;
 
-    fs::remove_dir_all(&temp_dir)?;
-
-    Ok(())
+    Ok((extracted, files))
 }
 
-fn install_extracted(temp_dir: &Path, output_dir: &Path) -> Result<()> {
+/// Install the contents of the `archive_name` archive extracted to `temp_dir` into the `dirs` of `output_dir`.
+/// Client plugins and server mods are routed to their configured directories.
+/// If the mod has include or exclude `paths`, only the files they allow are installed.
+///
+/// Returns the paths of the files installed.
+fn install_extracted(
+    temp_dir: &Path,
+    archive_name: &str,
+    output_dir: &Path,
+    dirs: &InstallDirs,
+    paths: Option<&PathFilter>,
+) -> Result<Vec<PathBuf>> {
     debug!(SCOPE = "subcommands::upgrade", temp_dir:display = temp_dir.display().to_string(), output_dir:display = output_dir.display().to_string(); "starting mod installation from extracted contents");
 
     let mut installation_count = 0;
+    let mut installed = Vec::new();
 
     // Collapse single-folder wrappers with same name as archive
     let mut root = temp_dir.to_path_buf();
     let original_root = root.clone();
 
-    // Get the archive name without extension for comparison
-    let archive_name = temp_dir.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
     // Only collapse if there's a single directory with the same name as the archive
     match fs::read_dir(&root) {
         Ok(entries) => {
@@ -380,10 +981,23 @@ fn install_extracted(temp_dir: &Path, output_dir: &Path) -> Result<()> {
         }
     }
 
+    if let Some(paths) = paths {
+        let pruned = paths.prune(&root)?;
+        info!(SCOPE = "subcommands::upgrade", archive = archive_name, pruned = pruned; "left out files excluded by the mod's paths");
+    }
+
+    let plugins_dir = output_dir.join(&dirs.plugins);
+
     // Merge BepInEx if present
     let bep = root.join("BepInEx");
     if bep.exists() {
-        match copy_dir_recursive(&bep, &output_dir.join("BepInEx")) {
+        match copy_dir_routed(
+            &bep,
+            &output_dir.join("BepInEx"),
+            "plugins",
+            &plugins_dir,
+            &mut installed,
+        ) {
             Ok(_) => {
                 info!(SCOPE = "subcommands::upgrade", from:display = bep.display().to_string(), to:display = output_dir.join("BepInEx").display().to_string(); "installed BepInEx directory");
                 installation_count += 1;
@@ -400,7 +1014,13 @@ fn install_extracted(temp_dir: &Path, output_dir: &Path) -> Result<()> {
     // Merge user mods
     let user_dir = root.join("user");
     if user_dir.exists() {
-        match copy_dir_recursive(&user_dir, &output_dir.join("user")) {
+        match copy_dir_routed(
+            &user_dir,
+            &output_dir.join("user"),
+            "mods",
+            &output_dir.join(&dirs.server_mods),
+            &mut installed,
+        ) {
             Ok(_) => {
                 info!(SCOPE = "subcommands::upgrade", from:display = user_dir.display().to_string(), to:display = output_dir.join("user").display().to_string(); "installed user directory");
                 installation_count += 1;
@@ -414,8 +1034,7 @@ fn install_extracted(temp_dir: &Path, output_dir: &Path) -> Result<()> {
         debug!(SCOPE = "subcommands::upgrade", root:display = root.display().to_string(); "no user directory found in extracted mod");
     }
 
-    // Top-level dlls => BepInEx/plugins
-    let plugins_dir = output_dir.join("BepInEx").join("plugins");
+    // Top-level dlls => plugins
     if let Err(e) = create_dir_all(&plugins_dir) {
         warn!(SCOPE = "subcommands::upgrade", plugins_dir:display = plugins_dir.display().to_string(), error:display = e.to_string(); "failed to create plugins directory");
         return Err(e.into());
@@ -437,6 +1056,7 @@ fn install_extracted(temp_dir: &Path, output_dir: &Path) -> Result<()> {
                         Ok(_) => {
                             normalize_permissions(&target);
                             debug!(SCOPE = "subcommands::upgrade", from:display = p.display().to_string(), to:display = target.display().to_string(); "installed DLL plugin");
+                            installed.push(target);
                             dll_count += 1;
                         }
                         Err(e) => {
@@ -464,10 +1084,11 @@ fn install_extracted(temp_dir: &Path, output_dir: &Path) -> Result<()> {
         info!(SCOPE = "subcommands::upgrade", components = installation_count, output_dir:display = output_dir.display().to_string(); "successfully installed mod components");
     }
 
-    Ok(())
+    Ok(installed)
 }
 
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
+/// Copy `src` into `dst`, overwriting existing files, and add the copied files to `installed`
+fn copy_dir_recursive(src: &Path, dst: &Path, installed: &mut Vec<PathBuf>) -> Result<()> {
     if !dst.exists() {
         create_dir_all(dst)?;
     }
@@ -476,24 +1097,296 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
         let path = entry.path();
         let target = dst.join(entry.file_name());
         if path.is_dir() {
-            copy_dir_recursive(&path, &target)?;
+            copy_dir_recursive(&path, &target, installed)?;
         } else {
             if let Some(parent) = target.parent() {
                 create_dir_all(parent)?;
             }
             fs_copy(&path, &target)?; // overwrite
             normalize_permissions(&target);
+            installed.push(target);
         }
     }
     Ok(())
 }
 
-fn ensure_required_dirs(output_dir: &Path) -> Result<()> {
+/// Copy `src` into `dst` like [`copy_dir_recursive`], except that its `subdir` directory
+/// is copied into `subdir_dst` instead
+fn copy_dir_routed(
+    src: &Path,
+    dst: &Path,
+    subdir: &str,
+    subdir_dst: &Path,
+    installed: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !dst.exists() {
+        create_dir_all(dst)?;
+    }
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            if entry.file_name() == subdir {
+                copy_dir_recursive(&path, subdir_dst, installed)?;
+            } else {
+                copy_dir_recursive(&path, &dst.join(entry.file_name()), installed)?;
+            }
+        } else {
+            let target = dst.join(entry.file_name());
+            fs_copy(&path, &target)?; // overwrite
+            normalize_permissions(&target);
+            installed.push(target);
+        }
+    }
+    Ok(())
+}
+
+/// Calculate the total size of the files in `path`, recursively.
+/// Entries that can't be read are skipped.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Format a number of bytes like the download messages do
+pub(super) fn format_bytes(bytes: u64) -> String {
+    size::Size::from_bytes(bytes)
+        .format()
+        .with_base(size::Base::Base10)
+        .to_string()
+}
+
+/// Print how much was downloaded and extracted, how much was freed,
+/// and the net change in the size of `output_dir` since it measured `size_before` bytes
+fn print_disk_usage(
+    output_dir: &Path,
+    size_before: u64,
+    downloaded: u64,
+    extracted: u64,
+    freed: u64,
+) {
+    let size_after = dir_size(output_dir);
+    let net = if size_after >= size_before {
+        format!("+{}", format_bytes(size_after - size_before))
+    } else {
+        format!("-{}", format_bytes(size_before - size_after))
+    };
+    println!(
+        "\n{}",
+        format!(
+            "Downloaded {}, extracted {}, freed {}, net {net}",
+            format_bytes(downloaded),
+            format_bytes(extracted),
+            format_bytes(freed),
+        )
+        .dimmed()
+    );
+}
+
+/// The files in `profile`'s plugin and server mod directories that none of its mods installed,
+/// relative to its output directory
+///
+/// The `spt` plugin directory SPT ships with is ignored.
+/// Nothing is returned if a mod was installed before tarium recorded the files of mods,
+/// since any of the files could be that mod's.
+fn untracked_files(profile: &Profile) -> Vec<PathBuf> {
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in read_dir(dir).into_iter().flatten().flatten() {
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => collect(&entry.path(), files),
+                Ok(_) => files.push(entry.path()),
+                Err(_) => {}
+            }
+        }
+    }
+
+    if profile
+        .mods
+        .iter()
+        .any(|mod_| mod_.installed_tag.is_some() && mod_.files.is_empty())
+    {
+        return Vec::new();
+    }
+    let tracked = profile
+        .mods
+        .iter()
+        .flat_map(|mod_| &mod_.files)
+        .collect::<HashSet<_>>();
+    let plugins = profile.output_dir.join(&profile.install_dirs.plugins);
+    let mut files = Vec::new();
+    collect(&plugins, &mut files);
+    collect(
+        &profile.output_dir.join(&profile.install_dirs.server_mods),
+        &mut files,
+    );
+    let spt = plugins.join("spt");
+    files.retain(|file| !file.starts_with(&spt));
+    files.sort();
+    files
+        .into_iter()
+        .map(|file| {
+            file.strip_prefix(&profile.output_dir)
+                .map(Path::to_path_buf)
+                .unwrap_or(file)
+        })
+        .filter(|file| !tracked.contains(file))
+        .collect()
+}
+
+/// Ask whether to install over the files in the output directory that tarium didn't put there,
+/// listing a few of them. Returns whether to carry on.
+///
+/// Fails if confirmation is needed but stdin isn't a terminal.
+fn confirm_untracked_files(profile: &Profile) -> Result<bool> {
+    const EXAMPLES: usize = 5;
+
+    let files = untracked_files(profile);
+    if files.is_empty() {
+        return Ok(true);
+    }
+    println!(
+        "{}",
+        format!(
+            "The output directory already contains {} files that tarium didn't install, which mods may overwrite:",
+            files.len()
+        )
+        .yellow()
+    );
+    for file in files.iter().take(EXAMPLES) {
+        println!("  {}", file.display().to_string().dimmed());
+    }
+    if files.len() > EXAMPLES {
+        println!(
+            "  {}",
+            format!("and {} more", files.len() - EXAMPLES).dimmed()
+        );
+    }
+    ensure!(
+        std::io::stdin().is_terminal(),
+        "Not installing over existing files without confirmation, pass `--yes` to do so anyway"
+    );
+    Ok(Confirm::new("Install the mods over them anyway?")
+        .with_default(false)
+        .prompt()?)
+}
+
+/// The files that the mods at `indices` in `profile` installed into its plugin and server mod directories,
+/// relative to its output directory
+///
+/// Files that another mod installed too are left out, so that they're only removed if all of their mods are reinstalled.
+pub(super) fn attributed_files(profile: &Profile, indices: &[usize]) -> Vec<PathBuf> {
+    let managed = [
+        profile.output_dir.join(&profile.install_dirs.plugins),
+        profile.output_dir.join(&profile.install_dirs.server_mods),
+    ];
+    let elsewhere = profile
+        .mods
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !indices.contains(index))
+        .flat_map(|(_, mod_)| &mod_.files)
+        .collect::<HashSet<_>>();
+    let mut files = indices
+        .iter()
+        .flat_map(|index| &profile.mods[*index].files)
+        .filter(|file| !elsewhere.contains(file))
+        .filter(|file| {
+            let path = profile.output_dir.join(file);
+            managed.iter().any(|dir| path.starts_with(dir)) && path.is_file()
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Ask whether to remove the mods' installed `files` before reinstalling them. Returns whether to carry on.
+///
+/// Fails if stdin isn't a terminal, see [`confirm_untracked_files`].
+fn confirm_clean_first(files: &[PathBuf]) -> Result<bool> {
+    const EXAMPLES: usize = 5;
+
+    println!(
+        "{}",
+        format!(
+            "{} files installed by these mods will be removed before they're reinstalled:",
+            files.len()
+        )
+        .yellow()
+    );
+    for file in files.iter().take(EXAMPLES) {
+        println!("  {}", file.display().to_string().dimmed());
+    }
+    if files.len() > EXAMPLES {
+        println!(
+            "  {}",
+            format!("and {} more", files.len() - EXAMPLES).dimmed()
+        );
+    }
+    ensure!(
+        std::io::stdin().is_terminal(),
+        "Not removing installed files without confirmation, pass `--yes` to do so anyway"
+    );
+    Ok(Confirm::new("Remove them first?")
+        .with_default(false)
+        .prompt()?)
+}
+
+/// Remove `files` from `output_dir`, along with the directories that are left empty within the `dirs` they're in
+///
+/// Returns the number of bytes freed.
+pub(super) fn remove_attributed_files(
+    output_dir: &Path,
+    dirs: &InstallDirs,
+    files: &[PathBuf],
+) -> Result<u64> {
+    let managed = [
+        output_dir.join(&dirs.plugins),
+        output_dir.join(&dirs.server_mods),
+    ];
+    let mut freed = 0;
+    for file in files {
+        let path = output_dir.join(file);
+        let size = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        match fs::remove_file(&path) {
+            Ok(()) => freed += size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+            }
+        }
+        debug!(SCOPE = "subcommands::upgrade", path:display = path.display(); "removed installed file");
+
+        let Some(root) = managed.iter().find(|dir| path.starts_with(dir)) else {
+            continue;
+        };
+        // Removing a directory fails once one that isn't empty is reached
+        for dir in path.ancestors().skip(1) {
+            if dir == root || !dir.starts_with(root) || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    info!(SCOPE = "subcommands::upgrade", files = files.len(), freed; "removed installed files");
+    Ok(freed)
+}
+
+fn ensure_required_dirs(output_dir: &Path, dirs: &InstallDirs) -> Result<()> {
     let required = [
         output_dir.to_path_buf(),
-        output_dir.join("BepInEx").join("plugins"),
-        output_dir.join("user").join("mods"),
-        output_dir.join("MODS"),
+        output_dir.join(&dirs.plugins),
+        output_dir.join(&dirs.server_mods),
+        output_dir.join(&dirs.archive_store),
     ];
     for dir in required {
         if !dir.exists() {
@@ -509,20 +1402,107 @@ fn ensure_required_dirs(output_dir: &Path) -> Result<()> {
                 ),
             }
         }
+        if dir.exists() {
+            super::check_writable(&dir)?;
+        }
     }
     Ok(())
 }
 
-/// Get the latest compatible downloadable for the mods in `profile`
+/// The default number of seconds to wait for a single mod to be resolved
+pub const DEFAULT_TIMEOUT_PER_MOD: u64 = 300;
+
+/// How the outcome of resolving each mod is printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Coloured, with the mod names padded into a column
+    #[default]
+    Pretty,
+    /// `[OK] owner/repo -> file.zip` and `[FAIL] owner/repo: reason`, for logs of non-interactive runs
+    Plain,
+}
+
+impl OutputFormat {
+    fn heading(self, heading: &str) -> String {
+        match self {
+            Self::Pretty => heading.bold().to_string(),
+            Self::Plain => heading.to_owned(),
+        }
+    }
+
+    /// The line for `mod_` resolving to `filename`, with its name padded to `pad_len`
+    fn resolved(self, mod_: &Mod, filename: &str, pad_len: usize) -> String {
+        match self {
+            Self::Pretty => format!(
+                "{} {:pad_len$}  {}",
+                TICK.clone(),
+                mod_.name,
+                filename.dimmed()
+            ),
+            Self::Plain => format!("[OK] {} -> {filename}", mod_.repository()),
+        }
+    }
+
+    /// The line for `mod_` failing to resolve because of `reason`, with its name padded to `pad_len`
+    fn failed(self, mod_: &Mod, reason: &str, pad_len: usize) -> String {
+        match self {
+            Self::Pretty => format!("{CROSS} {:pad_len$}  {reason}", mod_.name)
+                .red()
+                .to_string(),
+            Self::Plain => format!("[FAIL] {}: {reason}", mod_.repository()),
+        }
+    }
+
+    /// The warning that `mod_` resolved to the source code of `tag` because it has no release archive
+    fn source_fallback(self, mod_: &Mod, tag: &str) -> String {
+        let warning = format!("{tag} has no release archive, using its source code instead. It isn't packaged for installation and its game version is a guess.");
+        match self {
+            Self::Pretty => format!("    {} {warning}", "Warning:".yellow()),
+            Self::Plain => format!("[WARN] {}: {warning}", mod_.repository()),
+        }
+    }
+
+    /// The `explanation` of why a mod failed to resolve, indented to go under its line
+    fn explanation(self, explanation: &Explanation) -> String {
+        let indented = explanation
+            .to_string()
+            .lines()
+            .map(|line| format!("    {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match self {
+            Self::Pretty => indented.dimmed().to_string(),
+            Self::Plain => indented,
+        }
+    }
+}
+
+/// Get the latest compatible downloadable for the mods in `profile`, along with the index of their mod,
+/// scanning at most `max_releases` releases per mod
+///
+/// Only the mods at the indices in `only` are resolved if it's provided.
 ///
-/// If an error occurs with a resolving task, instead of failing immediately,
-/// resolution will continue and the error return flag is set to true.
-pub async fn get_platform_downloadables(profile: &Profile) -> Result<(Vec<DownloadData>, bool)> {
+/// If an error occurs with a resolving task, or it takes longer than `timeout_per_mod`, instead of failing immediately,
+/// resolution will continue and the index of the mod is returned with the error message.
+/// The outcome for each mod is printed in `output_format`,
+/// followed by which filters rejected which files if `explain` is true and the mod's files were all rejected.
+pub async fn get_platform_downloadables(
+    profile: &Profile,
+    only: Option<&[usize]>,
+    max_releases: usize,
+    timeout_per_mod: Duration,
+    output_format: OutputFormat,
+    explain: bool,
+) -> Result<(Vec<(usize, DownloadData)>, Vec<(usize, String)>)> {
     // let progress_bar = Arc::new(Mutex::new(ProgressBar::new(0).with_style(STYLE_NO.clone())));
     // Progress bar temporarily disabled
+    let github_api = super::github_api(profile)?;
     let mut tasks = JoinSet::new();
 
-    println!("{}\n", "Determining the Latest Compatible Versions".bold());
+    println!(
+        "{}\n",
+        output_format.heading("Determining the Latest Compatible Versions")
+    );
     // progress_bar
     //     .lock()
     //     .enable_steady_tick(Duration::from_millis(100));
@@ -535,29 +1515,69 @@ pub async fn get_platform_downloadables(profile: &Profile) -> Result<(Vec<Downlo
         .clamp(20, 50);
 
     // Spawn a task per mod (dependency expansion can be re-added later if needed)
-    for mod_ in profile.mods.clone() {
+    for (index, mod_) in profile
+        .mods
+        .clone()
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| only.map_or(true, |only| only.contains(index)))
+    {
         // progress_bar.lock().inc_length(1);
         let filters = profile.filters.clone();
+        let github_api = github_api.clone();
         // let progress_bar = Arc::clone(&progress_bar);
         tasks.spawn(async move {
             let permit = SEMAPHORE.get_or_init(default_semaphore).acquire().await?;
-            let result = mod_.fetch_download_file(filters).await;
+            let mut explanation = None;
+            let result = match timeout(
+                timeout_per_mod,
+                mod_.fetch_download_file(&github_api, filters.clone(), max_releases),
+            )
+            .await
+            {
+                Ok(result) => {
+                    if explain && matches!(result, Err(mod_downloadable::Error::CheckError(_))) {
+                        explanation = mod_
+                            .explain_resolution(&github_api, filters, max_releases)
+                            .await
+                            .inspect_err(|err| warn!(SCOPE = "subcommands::upgrade", name = mod_.name.as_str(), error:display = err; "failed to explain resolution"))
+                            .ok();
+                    }
+                    result.map_err(Error::from)
+                }
+                Err(_) => {
+                    warn!(SCOPE = "subcommands::upgrade", name = mod_.name.as_str(), timeout_secs = timeout_per_mod.as_secs(); "timed out resolving mod");
+                    Err(anyhow!(
+                        "Timed out after {}s resolving the latest compatible version",
+                        timeout_per_mod.as_secs()
+                    ))
+                }
+            };
             drop(permit);
 
             // progress_bar.lock().inc(1);
             match result {
                 Ok(download_file) => {
-                    println!(
-                        "{} {:pad_len$}  {}",
-                        TICK.clone(),
-                        mod_.name,
-                        download_file.filename().dimmed()
-                    );
-                    Ok(Some(download_file))
+                    let mut message =
+                        output_format.resolved(&mod_, &download_file.filename(), pad_len);
+                    if is_source_archive(&download_file.filename()) {
+                        message.push('\n');
+                        message.push_str(&output_format.source_fallback(
+                            &mod_,
+                            download_file.tag.as_deref().unwrap_or_default(),
+                        ));
+                    }
+                    println!("{message}");
+                    Ok(Ok((index, download_file)))
                 }
                 Err(err) => {
-                    println!("{}", format!("{CROSS} {:pad_len$}  {err}", mod_.name).red());
-                    Ok(None)
+                    let mut message = output_format.failed(&mod_, &err.to_string(), pad_len);
+                    if let Some(explanation) = explanation {
+                        message.push('\n');
+                        message.push_str(&output_format.explanation(&explanation));
+                    }
+                    println!("{message}");
+                    Ok(Err((index, err.to_string())))
                 }
             }
         });
@@ -572,23 +1592,93 @@ pub async fn get_platform_downloadables(profile: &Profile) -> Result<(Vec<Downlo
 
     // progress_bar.lock().finish_and_clear();
 
-    let error = task_results.iter().any(Option::is_none);
-    let to_download = task_results.into_iter().flatten().collect();
-    Ok((to_download, error))
+    let mut to_download = Vec::new();
+    let mut failed = Vec::new();
+    for result in task_results {
+        match result {
+            Ok(resolved) => to_download.push(resolved),
+            Err(failure) => failed.push(failure),
+        }
+    }
+    Ok((to_download, failed))
+}
+
+/// How [`upgrade`] upgrades a profile
+#[derive(Debug, Clone, Default)]
+#[expect(clippy::struct_excessive_bools, reason = "They are independent flags")]
+pub struct UpgradeOptions {
+    /// Only install the archives already in the archive store, without resolving or downloading anything
+    pub local_only: bool,
+    /// Only resolve and download the mods these name, see [`Mod::matches_name`]
+    pub only: Option<Vec<String>>,
+    /// The number of recent releases to scan per mod
+    pub max_releases: usize,
+    /// How long to try resolving a mod before giving up on it
+    pub timeout_per_mod: Duration,
+    /// Don't print a summary of the disk usage at the end
+    pub quiet: bool,
+    /// Extract archives straight from the download without writing them to the output directory,
+    /// so they aren't moved to the archive store afterwards either
+    pub stream: bool,
+    /// Move downloaded archives to the archive store. Otherwise they stay in the output directory,
+    /// and are only extracted again once they change
+    pub move_archives: bool,
+    /// Where to write a JSON report of what happened to each mod
+    pub report: Option<PathBuf>,
+    /// How the outcome of resolving each mod is printed
+    pub output_format: OutputFormat,
+    /// Remove the files the mods installed last time before their new archives are extracted,
+    /// leaving files tarium can't attribute to a mod alone
+    pub clean_first: bool,
+    /// Don't ask for confirmation before installing into an output directory that already contains
+    /// mod files tarium didn't install, or before removing files for `clean_first`
+    pub yes: bool,
+    /// Print the filters that rejected each file of the mods that didn't resolve
+    pub explain: bool,
 }
 
-pub async fn upgrade(profile: &Profile, local_only: bool) -> Result<()> {
-    ensure_required_dirs(&profile.output_dir)?;
+/// Download and install the latest compatible version of the mods in `profile`, as the fields of `options` say.
+///
+/// The release tag of each successfully installed mod is recorded in the `profile`,
+/// as are the mods that failed to be resolved or downloaded so they can be retried later.
+pub async fn upgrade(profile: &mut Profile, options: &UpgradeOptions) -> Result<()> {
+    let UpgradeOptions {
+        local_only,
+        ref only,
+        max_releases,
+        timeout_per_mod,
+        quiet,
+        stream,
+        move_archives,
+        ref report,
+        output_format,
+        clean_first,
+        yes,
+        explain,
+    } = *options;
+    let report = report.as_deref();
+    let started = Instant::now();
+    ensure_required_dirs(&profile.output_dir, &profile.install_dirs)?;
+    if !yes && !confirm_untracked_files(profile)? {
+        println!("{}", "Upgrade cancelled".yellow());
+        return Ok(());
+    }
+    let size_before = dir_size(&profile.output_dir);
+    let game_versions = profile.filters.game_versions().cloned().unwrap_or_default();
 
     if local_only {
-        info!(SCOPE = "subcommands::upgrade", output_dir:display = profile.output_dir.display().to_string(); "running upgrade in local-only mode, scanning MODS directory");
+        info!(SCOPE = "subcommands::upgrade", output_dir:display = profile.output_dir.display().to_string(); "running upgrade in local-only mode, scanning archive store");
 
-        // Copy archives from MODS directory to output directory for processing
-        let mods_dir = profile.output_dir.join("MODS");
+        // Copy archives from the archive store to output directory for processing
+        let mods_dir = profile.output_dir.join(&profile.install_dirs.archive_store);
         if !mods_dir.exists() {
             println!(
                 "{}",
-                "No MODS directory found - nothing to install locally".yellow()
+                format!(
+                    "No archive store found at {} - nothing to install locally",
+                    mods_dir.display()
+                )
+                .yellow()
             );
             return Ok(());
         }
@@ -597,41 +1687,165 @@ pub async fn upgrade(profile: &Profile, local_only: bool) -> Result<()> {
         for entry in read_dir(&mods_dir)? {
             let entry = entry?;
             let path = entry.path();
-            if path.is_file() {
-                if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                    match ext.to_ascii_lowercase().as_str() {
-                        "zip" | "7z" => {
-                            let target = profile.output_dir.join(path.file_name().unwrap());
-                            if !target.exists() {
-                                info!(SCOPE = "subcommands::upgrade", from:display = path.display().to_string(), to:display = target.display().to_string(); "copying archive from MODS for local installation");
-                                fs_copy(&path, &target)?;
-                                archive_count += 1;
-                            }
-                        }
-                        _ => {}
-                    }
+            if path.is_file() && is_supported_archive(&entry.file_name().to_string_lossy()) {
+                let target = profile.output_dir.join(entry.file_name());
+                if !target.exists() {
+                    info!(SCOPE = "subcommands::upgrade", from:display = path.display().to_string(), to:display = target.display().to_string(); "copying archive from the archive store for local installation");
+                    fs_copy(&path, &target)?;
+                    archive_count += 1;
                 }
             }
         }
 
         if archive_count == 0 {
-            println!("{}", "No archives found in MODS directory".yellow());
+            println!(
+                "{}",
+                format!("No archives found in {}", mods_dir.display()).yellow()
+            );
         } else {
             println!(
-                "\n{} {} archives from MODS directory",
+                "\n{} {} archives in {}",
                 "Found".bold(),
-                archive_count
+                archive_count,
+                mods_dir.display()
             );
         }
 
-        // Extract all archives (both existing and copied from MODS)
-        if let Err(e) = extract_all_archives(&profile.output_dir) {
+        // Extract all archives (both existing and copied from the archive store)
+        let extracted = extract_all_archives(
+            &profile.output_dir,
+            &profile.install_dirs,
+            &[],
+            &game_versions,
+            &HashMap::new(),
+            Vec::new(),
+            true,
+        )
+        .await
+        .unwrap_or_else(|e| {
             println!("{} Failed to extract some archives: {}", CROSS.red(), e);
+            Extracted::default()
+        });
+
+        if !quiet {
+            print_disk_usage(&profile.output_dir, size_before, 0, extracted.bytes, 0);
+        }
+
+        if let Some(report) = report {
+            // There are no mods to speak of, so report each archive instead
+            let mods = extracted
+                .extractions
+                .into_iter()
+                .map(|(filename, extraction)| ModReport {
+                    name: filename.clone(),
+                    filename: Some(filename),
+                    extraction,
+                    ..Default::default()
+                })
+                .collect();
+            write_report(report, mods, started)?;
         }
 
         Ok(())
     } else {
-        let (mut to_download, error) = get_platform_downloadables(profile).await?;
+        let only = only
+            .as_ref()
+            .map(|names| mod_indices(profile, names))
+            .transpose()?;
+        if clean_first {
+            let indices = only
+                .clone()
+                .unwrap_or_else(|| (0..profile.mods.len()).collect());
+            let files = attributed_files(profile, &indices);
+            if files.is_empty() {
+                println!(
+                    "{}",
+                    "No installed files are known for these mods, so there's nothing to clean first"
+                        .dimmed()
+                );
+            } else if !yes && !confirm_clean_first(&files)? {
+                println!("{}", "Upgrade cancelled".yellow());
+                return Ok(());
+            }
+        }
+        let (resolved, failed) = get_platform_downloadables(
+            profile,
+            only.as_deref(),
+            max_releases,
+            timeout_per_mod,
+            output_format,
+            explain,
+        )
+        .await?;
+        // Mods that weren't upgraded this time keep their failures from before
+        profile.failed_mods.retain(|name| {
+            only.as_ref().is_some_and(|only| {
+                !only
+                    .iter()
+                    .any(|index| profile.mods[*index].matches_name(name))
+            })
+        });
+        profile.failed_mods.extend(
+            failed
+                .iter()
+                .map(|(index, _)| profile.mods[*index].repository()),
+        );
+        // Resolving a mod checks it's compatible, which was skipped if it was added with `--force`
+        for (index, _) in &resolved {
+            profile.mods[*index].force_added = false;
+        }
+        let unchecked = failed
+            .iter()
+            .map(|(index, _)| &profile.mods[*index])
+            .filter(|mod_| mod_.force_added)
+            .map(|mod_| mod_.name.as_str())
+            .collect::<Vec<_>>();
+        if !unchecked.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "{} were added with `--force`, so they may not be compatible with the profile at all",
+                    unchecked.join(", ")
+                )
+                .yellow()
+            );
+        }
+        let mut mod_reports = failed
+            .iter()
+            .map(|(index, error)| ModReport {
+                name: profile.mods[*index].name.clone(),
+                error: Some(error.clone()),
+                ..Default::default()
+            })
+            .chain(resolved.iter().map(|(index, download_file)| ModReport {
+                name: profile.mods[*index].name.clone(),
+                tag: download_file.tag.clone(),
+                download_url: Some(download_file.download_url.to_string()),
+                filename: Some(download_file.filename()),
+                ..Default::default()
+            }))
+            .collect::<Vec<_>>();
+        let installed_tags = resolved
+            .iter()
+            .map(|(index, download_file)| {
+                (*index, download_file.filename(), download_file.tag.clone())
+            })
+            .collect::<Vec<_>>();
+        let resolved_files = resolved
+            .iter()
+            .map(|(index, download_file)| (*index, download_file.filename()))
+            .collect::<Vec<_>>();
+        let install_order = archive_install_order(profile, &resolved);
+        let mut path_filters = HashMap::new();
+        for (index, download_file) in &resolved {
+            if let Some(filter) = PathFilter::for_mod(&profile.mods[*index])? {
+                path_filters.insert(download_file.filename(), filter);
+            }
+        }
+        let mut to_download = resolved
+            .into_iter()
+            .map(|(_, download_file)| download_file)
+            .collect();
         let mut to_install = Vec::new();
         if profile.output_dir.join("user").exists() {
             for file in read_dir(profile.output_dir.join("user"))? {
@@ -643,32 +1857,507 @@ pub async fn upgrade(profile: &Profile, local_only: bool) -> Result<()> {
             }
         }
 
-        clean(&profile.output_dir, &mut to_download, &mut to_install).await?;
+        let mut freed = clean(&profile.output_dir, &mut to_download, &mut to_install).await?;
         to_download
             .iter_mut()
             // Download directly to the output directory
             .map(|thing| thing.output = thing.filename().into())
             .for_each(drop); // Doesn't drop any data, just runs the iterator
                              // Always attempt extraction of any archives present (new or existing)
-        if to_download.is_empty() && to_install.is_empty() {
+        let (downloaded, streamed) = if to_download.is_empty() && to_install.is_empty() {
             println!("\n{}", "All up to date!".bold());
-            if let Err(e) = extract_all_archives(&profile.output_dir) {
-                println!("{} Failed to extract some archives: {}", CROSS.red(), e);
-            }
+            (vec![], vec![])
         } else {
             println!("\n{}\n", "Downloading Mod Files".bold());
-            download(profile.output_dir.clone(), to_download, to_install).await?;
-            if let Err(e) = extract_all_archives(&profile.output_dir) {
+            let pending = to_download
+                .iter()
+                .map(DownloadData::filename)
+                .collect::<HashSet<_>>();
+            let result = if stream {
+                stream_archives(&profile.output_dir, to_download, to_install, &install_order)
+                    .await
+                    .map(|streamed| (Vec::new(), streamed))
+            } else {
+                download(profile.output_dir.clone(), to_download, to_install)
+                    .await
+                    .map(|downloaded| (downloaded, Vec::new()))
+            };
+            match result {
+                Ok(downloaded) => downloaded,
+                Err(err) => {
+                    profile.failed_mods.extend(
+                        resolved_files
+                            .iter()
+                            .filter(|(_, filename)| pending.contains(filename))
+                            .map(|(index, _)| profile.mods[*index].repository()),
+                    );
+                    if let Some(report) = report {
+                        for mod_report in &mut mod_reports {
+                            if mod_report
+                                .filename
+                                .as_ref()
+                                .is_some_and(|filename| pending.contains(filename))
+                            {
+                                mod_report.error = Some(format!("Download failed: {err}"));
+                            }
+                        }
+                        write_report(report, mod_reports, started)?;
+                    }
+                    return Err(err);
+                }
+            }
+        };
+        profile.last_upgrade = Some(Utc::now());
+        if clean_first {
+            // Only the mods that are about to be reinstalled lose their files
+            let indices = resolved_files
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>();
+            let files = attributed_files(profile, &indices);
+            // Archives kept in the output directory have to be extracted again to put the files back
+            let mut extracted_archives = read_extracted_archives(&profile.output_dir);
+            for (_, filename) in &resolved_files {
+                extracted_archives.remove(filename);
+            }
+            write_extracted_archives(&profile.output_dir, &extracted_archives)?;
+            if !files.is_empty() {
+                freed +=
+                    remove_attributed_files(&profile.output_dir, &profile.install_dirs, &files)?;
+                println!(
+                    "{} Removed {} files installed by the mods before reinstalling them",
+                    TICK.clone(),
+                    files.len()
+                );
+            }
+        }
+        let (
+            Extracted {
+                bytes: extracted,
+                mut extractions,
+                installed_files,
+                downloaded: streamed_downloads,
+                in_memory,
+                failed_downloads,
+            },
+            extraction_errored,
+        ) = match extract_all_archives(
+            &profile.output_dir,
+            &profile.install_dirs,
+            &install_order,
+            &game_versions,
+            &path_filters,
+            streamed,
+            move_archives,
+        )
+        .await
+        {
+            Ok(extracted) => (extracted, false),
+            Err(e) => {
                 println!("{} Failed to extract some archives: {}", CROSS.red(), e);
+                (Extracted::default(), true)
+            }
+        };
+        let downloaded = downloaded
+            .into_iter()
+            .chain(streamed_downloads)
+            .collect::<Vec<_>>();
+        for (filename, bytes) in &downloaded {
+            if let Some(mod_report) = mod_reports
+                .iter_mut()
+                .find(|mod_report| mod_report.filename.as_ref() == Some(filename))
+            {
+                mod_report.bytes = *bytes;
+            }
+        }
+        let downloaded = downloaded.into_iter().map(|(_, bytes)| bytes).sum();
+        // Every resolved file is now installed, unless downloading or extracting it failed
+        for (index, filename, tag) in installed_tags {
+            if extraction_errored
+                || matches!(extractions.get(&filename), Some(Extraction::Failed { .. }))
+            {
+                profile.failed_mods.push(profile.mods[index].repository());
+            } else {
+                profile.mods[index].installed_tag = tag;
+            }
+        }
+        for (index, filename) in &resolved_files {
+            if let Some(files) = installed_files.get(filename) {
+                profile.mods[*index].files = files.clone();
             }
         }
 
-        if error {
+        if !quiet {
+            print_disk_usage(
+                &profile.output_dir,
+                size_before,
+                downloaded,
+                extracted,
+                freed,
+            );
+            // Archives held in memory would otherwise have been written to the disk once and read back once
+            if in_memory > 0 {
+                println!(
+                    "{}",
+                    format!(
+                        "Streamed {} of archives from memory, saving an estimated {} of disk writes and reads",
+                        format_bytes(in_memory),
+                        format_bytes(2 * in_memory),
+                    )
+                    .dimmed()
+                );
+            }
+        }
+
+        if let Some(report) = report {
+            for mod_report in &mut mod_reports {
+                if let Some((_, error)) = failed_downloads
+                    .iter()
+                    .find(|(filename, _)| mod_report.filename.as_ref() == Some(filename))
+                {
+                    mod_report.error = Some(error.clone());
+                }
+                if let Some(extraction) = mod_report
+                    .filename
+                    .as_ref()
+                    .and_then(|filename| extractions.remove(filename))
+                {
+                    mod_report.extraction = extraction;
+                }
+            }
+            write_report(report, mod_reports, started)?;
+        }
+
+        if !failed.is_empty() {
             Err(anyhow!(
                 "\nCould not get the latest compatible version of some mods"
             ))
+        } else if !failed_downloads.is_empty() {
+            Err(anyhow!("\nCould not download some mods"))
         } else {
             Ok(())
         }
     }
 }
+
+/// Read the profile's install order from the file at `path`, which lists a mod per line
+///
+/// Entries that don't match any of the profile's mods are warned about and skipped.
+pub fn set_install_order(profile: &mut Profile, path: &Path) -> Result<()> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read install order file: {}", path.display()))?;
+
+    let mut install_order = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if profile.mods.iter().any(|mod_| mod_.matches_name(line)) {
+            install_order.push(line.to_owned());
+        } else {
+            println!(
+                "{}",
+                format!("Warning: {line} in the install order is not in this profile").yellow()
+            );
+        }
+    }
+
+    info!(SCOPE = "subcommands::upgrade", entries = install_order.len(); "set install order");
+    profile.install_order = install_order;
+    Ok(())
+}
+
+/// The filenames of the `resolved` archives in the order they should be installed,
+/// the mods in the profile's install order first and then the rest in profile order
+fn archive_install_order(profile: &Profile, resolved: &[(usize, DownloadData)]) -> Vec<String> {
+    let mut resolved = resolved.iter().collect::<Vec<_>>();
+    resolved.sort_by_key(|(index, _)| {
+        (
+            profile
+                .install_order
+                .iter()
+                .position(|name| profile.mods[*index].matches_name(name))
+                .unwrap_or(usize::MAX),
+            *index,
+        )
+    });
+    resolved
+        .into_iter()
+        .map(|(_, download_file)| download_file.filename())
+        .collect()
+}
+
+/// The indices of the mods in `profile` that `names` refer to, see [`Mod::matches_name`]
+fn mod_indices(profile: &Profile, names: &[String]) -> Result<Vec<usize>> {
+    names
+        .iter()
+        .map(|name| {
+            profile
+                .mods
+                .iter()
+                .position(|mod_| mod_.matches_name(name))
+                .with_context(|| format!("There is no mod called {name} in this profile"))
+        })
+        .collect()
+}
+
+/// Write a report of the `mods` in an upgrade that started at `started` to `path`
+fn write_report(path: &Path, mut mods: Vec<ModReport>, started: Instant) -> Result<()> {
+    mods.sort_by_key(|mod_| mod_.name.to_lowercase());
+    Report::new(mods, started).write(path)?;
+    info!(SCOPE = "subcommands::upgrade", path:display = path.display(); "wrote upgrade report");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn write_zip(path: &Path, entry: &str) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file(entry, SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"dll").unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn plain_output_format() {
+        let mod_ = Mod::new(
+            "SAIN".to_owned(),
+            ModIdentifier::GitHubRepository("Solarint".to_owned(), "SAIN".to_owned()),
+            vec![],
+        );
+        assert_eq!(
+            OutputFormat::Plain.resolved(&mod_, "SAIN-3.2.0.zip", 20),
+            "[OK] Solarint/SAIN -> SAIN-3.2.0.zip"
+        );
+        assert_eq!(
+            OutputFormat::Plain.failed(&mod_, "Not Found", 20),
+            "[FAIL] Solarint/SAIN: Not Found"
+        );
+        assert!(OutputFormat::Plain
+            .source_fallback(&mod_, "v3.2.0")
+            .starts_with("[WARN] Solarint/SAIN: v3.2.0 has no release archive"));
+        // The default format keeps the padded column
+        assert!(OutputFormat::default()
+            .resolved(&mod_, "SAIN-3.2.0.zip", 20)
+            .contains(&format!("{:20}  ", "SAIN")));
+    }
+
+    #[test]
+    fn spt_version_requirements() {
+        let versions = ["3.11.0".to_owned()];
+        assert_eq!(spt_version_allows("~3.11.0", &versions), Some(true));
+        assert_eq!(spt_version_allows("3.11.4", &versions), Some(true));
+        assert_eq!(spt_version_allows(">=3.10.0", &versions), Some(true));
+        assert_eq!(spt_version_allows("~3.9.0", &versions), Some(false));
+        assert_eq!(spt_version_allows("4.0.x", &versions), Some(false));
+        assert_eq!(
+            spt_version_allows("~3.11.2", &["3.11".to_owned()]),
+            Some(true)
+        );
+        assert_eq!(spt_version_allows("not a version", &versions), None);
+    }
+
+    #[test]
+    fn extract_same_named_archives_concurrently() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let sources = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+        let archives =
+            [("First.dll", &sources[0]), ("Second.dll", &sources[1])].map(|(entry, dir)| {
+                let path = dir.path().join("Mod.zip");
+                write_zip(&path, entry);
+                path
+            });
+
+        std::thread::scope(|scope| {
+            for archive in &archives {
+                scope.spawn(|| {
+                    extract_zip(archive, output_dir.path(), &InstallDirs::default(), None).unwrap()
+                });
+            }
+        });
+
+        let plugins = output_dir.path().join("BepInEx").join("plugins");
+        assert!(plugins.join("First.dll").is_file());
+        assert!(plugins.join("Second.dll").is_file());
+        assert_eq!(
+            read_dir(output_dir.path().join(".extract_tmp"))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn extract_to_custom_install_dirs() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        let archive = source.path().join("Mod.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        for entry in [
+            "BepInEx/plugins/Client.dll",
+            "user/mods/Server/package.json",
+            "Loose.dll",
+        ] {
+            zip.start_file(entry, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"mod").unwrap();
+        }
+        zip.finish().unwrap();
+        let dirs = InstallDirs {
+            plugins: "client".into(),
+            server_mods: "server".into(),
+            archive_store: "archives".into(),
+        };
+
+        extract_zip(&archive, output_dir.path(), &dirs, None).unwrap();
+
+        assert!(output_dir.path().join("client/Client.dll").is_file());
+        assert!(output_dir.path().join("client/Loose.dll").is_file());
+        assert!(output_dir
+            .path()
+            .join("server/Server/package.json")
+            .is_file());
+        assert!(!output_dir.path().join("BepInEx/plugins").exists());
+    }
+
+    #[tokio::test]
+    async fn install_order_decides_conflicts() {
+        let output_dir = tempfile::tempdir().unwrap();
+        for name in ["A", "B"] {
+            let mut zip = ZipWriter::new(
+                File::create(output_dir.path().join(format!("{name}.zip"))).unwrap(),
+            );
+            zip.start_file("Shared.dll", SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+            zip.finish().unwrap();
+        }
+
+        extract_all_archives(
+            output_dir.path(),
+            &InstallDirs::default(),
+            &["B.zip".to_owned(), "A.zip".to_owned()],
+            &[],
+            &HashMap::new(),
+            Vec::new(),
+            true,
+        )
+        .await
+        .unwrap();
+
+        let shared = output_dir.path().join("BepInEx/plugins/Shared.dll");
+        assert_eq!(fs::read_to_string(shared).unwrap(), "A");
+    }
+
+    #[tokio::test]
+    async fn corrupt_archives_are_deleted() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let truncated = output_dir.path().join("Truncated.zip");
+        write_zip(&truncated, "Truncated.dll");
+        let bytes = fs::read(&truncated).unwrap();
+        fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        write_zip(&output_dir.path().join("Intact.zip"), "Intact.dll");
+
+        let extracted = extract_all_archives(
+            output_dir.path(),
+            &InstallDirs::default(),
+            &[],
+            &[],
+            &HashMap::new(),
+            Vec::new(),
+            true,
+        )
+        .await
+        .unwrap();
+
+        // The next upgrade downloads it again, and the other archive is installed regardless
+        assert!(!truncated.exists());
+        assert!(matches!(
+            &extracted.extractions["Truncated.zip"],
+            Extraction::Failed { error } if error.contains("appears corrupt")
+        ));
+        assert!(matches!(
+            &extracted.extractions["Intact.zip"],
+            Extraction::Installed { .. }
+        ));
+        let plugins = output_dir.path().join("BepInEx/plugins");
+        assert!(!plugins.join("Truncated.dll").exists());
+        assert!(plugins.join("Intact.dll").is_file());
+    }
+
+    #[tokio::test]
+    async fn streamed_archives_follow_install_order() {
+        let output_dir = tempfile::tempdir().unwrap();
+        write_zip(&output_dir.path().join("A.zip"), "Shared.dll");
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("Shared.dll", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"B").unwrap();
+        let mut streamed = zip.finish().unwrap();
+        streamed.rewind().unwrap();
+        let (sender, pending) = oneshot::channel();
+        let length = streamed.get_ref().len() as u64;
+        assert!(sender
+            .send(Ok(DownloadedArchive {
+                length,
+                archive: StreamedArchive::Memory(streamed),
+                _permit: SEMAPHORE
+                    .get_or_init(default_semaphore)
+                    .acquire()
+                    .await
+                    .unwrap(),
+            }))
+            .is_ok());
+
+        let extracted = extract_all_archives(
+            output_dir.path(),
+            &InstallDirs::default(),
+            &["A.zip".to_owned(), "B.zip".to_owned()],
+            &[],
+            &HashMap::new(),
+            vec![("B.zip".to_owned(), pending)],
+            true,
+        )
+        .await
+        .unwrap();
+
+        let shared = output_dir.path().join("BepInEx/plugins/Shared.dll");
+        assert_eq!(fs::read_to_string(shared).unwrap(), "B");
+        // Only the archive that was on disk is moved to the store
+        assert!(output_dir.path().join("MODS/A.zip").is_file());
+        assert!(!output_dir.path().join("MODS/B.zip").exists());
+        assert_eq!(extracted.downloaded, [("B.zip".to_owned(), length)]);
+        assert_eq!(extracted.in_memory, length);
+    }
+
+    #[tokio::test]
+    async fn failed_streamed_downloads_are_recorded() {
+        let output_dir = tempfile::tempdir().unwrap();
+        write_zip(&output_dir.path().join("A.zip"), "A.dll");
+        let (sender, pending) = oneshot::channel();
+        assert!(sender.send(Err(anyhow!("connection reset"))).is_ok());
+
+        let extracted = extract_all_archives(
+            output_dir.path(),
+            &InstallDirs::default(),
+            &[],
+            &[],
+            &HashMap::new(),
+            vec![("B.zip".to_owned(), pending)],
+            true,
+        )
+        .await
+        .unwrap();
+
+        // The other archives are installed regardless
+        assert!(matches!(
+            &extracted.extractions["B.zip"],
+            Extraction::Failed { error } if error.contains("connection reset")
+        ));
+        assert_eq!(extracted.failed_downloads.len(), 1);
+        assert!(output_dir.path().join("BepInEx/plugins/A.dll").is_file());
+    }
+}
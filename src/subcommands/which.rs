@@ -0,0 +1,85 @@
+use crate::{CROSS, TICK};
+use anyhow::{Context as _, Result};
+use colored::Colorize as _;
+use libarov::config::structs::Profile;
+use std::path::PathBuf;
+
+/// The absolute paths of the files that the mod `name` refers to installed, and whether each is still on disk
+///
+/// The mod is matched by its name, `owner/repo` identifier, or slug like [`remove`](super::remove) does.
+fn tracked_files<'a>(profile: &'a Profile, name: &str) -> Result<(&'a str, Vec<(PathBuf, bool)>)> {
+    let mod_ = profile
+        .mods
+        .iter()
+        .find(|mod_| mod_.matches_name(name))
+        .with_context(|| format!("A mod with ID or name {name} is not present in this profile"))?;
+    let files = mod_
+        .files
+        .iter()
+        .map(|file| {
+            let path = profile.output_dir.join(file);
+            let path = std::path::absolute(&path).unwrap_or(path);
+            let exists = path.exists();
+            (path, exists)
+        })
+        .collect();
+    Ok((&mod_.name, files))
+}
+
+/// Print where the files that the mod `name` refers to installed are, and which of them are missing
+pub fn which(profile: &Profile, name: &str) -> Result<()> {
+    let (mod_name, files) = tracked_files(profile, name)?;
+    if files.is_empty() {
+        println!(
+            "{}",
+            format!("No files are tracked for {mod_name}, upgrade to record them").yellow()
+        );
+        return Ok(());
+    }
+    for (path, exists) in files {
+        if exists {
+            println!("{} {}", TICK.clone(), path.display());
+        } else {
+            println!("{} {} (missing)", CROSS.red(), path.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libarov::config::structs::{Mod, ModIdentifier};
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    fn tracked_files_of_a_mod() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("BepInEx").join("plugins");
+        create_dir_all(&plugins).unwrap();
+        write(plugins.join("SAIN.dll"), "sain").unwrap();
+
+        let mut profile = Profile::new("Profile".to_owned(), dir.path().to_owned(), vec![], true);
+        let mut mod_ = Mod::new(
+            "SAIN".to_owned(),
+            ModIdentifier::GitHubRepository("Solarint".to_owned(), "SAIN".to_owned()),
+            vec![],
+        );
+        mod_.files = vec![
+            PathBuf::from("BepInEx/plugins/SAIN.dll"),
+            PathBuf::from("BepInEx/plugins/SAIN.pdb"),
+        ];
+        profile.mods.push(mod_);
+
+        let (name, files) = tracked_files(&profile, "solarint/sain").unwrap();
+        assert_eq!(name, "SAIN");
+        assert_eq!(
+            files,
+            [
+                (plugins.join("SAIN.dll"), true),
+                (plugins.join("SAIN.pdb"), false),
+            ]
+        );
+        assert!(tracked_files(&profile, "Waypoints").is_err());
+    }
+}
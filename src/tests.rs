@@ -1,22 +1,45 @@
 #![expect(clippy::unwrap_used)]
 
+mod mock_github;
+
 use crate::{
-    actual_main,
-    cli::{FilterArguments, ProfileSubCommands, SubCommands, Tarium},
+    actual_main, add,
+    cli::{
+        AliasSubCommands, ConfigureArguments, CreateArguments, FilterArguments, ProfileSubCommands,
+        SubCommands, Tarium,
+    },
+    subcommands::OutputFormat,
+};
+use clap::{CommandFactory as _, Parser as _};
+use libarov::{
+    config::{
+        filters::{Filter, ProfileParameters as _, ReleaseChannel},
+        read_config,
+        structs::{Mod, ModIdentifier, Profile},
+        write_config,
+    },
+    upgrade::{check, mod_downloadable},
 };
+use mock_github::run_main;
 use std::{
     env::current_dir,
-    fs::{copy, create_dir_all},
-    path::PathBuf,
+    fs::{copy, create_dir_all, read_to_string, remove_file, write},
+    io::{stdin, IsTerminal as _},
+    path::{Path, PathBuf},
 };
 
 const DEFAULT: Tarium = Tarium {
     subcommand: SubCommands::Profile { subcommand: None },
     threads: None,
     parallel_tasks: 10,
+    concurrency_per_host: 4,
+    wait: false,
+    max_download_speed: None,
     github_token: None,
     config_file: None,
     verbosity: 2,
+    proxy: None,
+    no_update_check: true,
 };
 
 fn get_args(subcommand: SubCommands, config_file: Option<&str>) -> Tarium {
@@ -36,22 +59,75 @@ fn get_args(subcommand: SubCommands, config_file: Option<&str>) -> Tarium {
     }
 }
 
+/// Like [`get_args`], but with the profile of `config_file` outputting to `output_dir`
+fn get_args_in(subcommand: SubCommands, config_file: &str, output_dir: &Path) -> Tarium {
+    let args = get_args(subcommand, Some(config_file));
+    let running = args.config_file.as_ref().unwrap();
+    let mut config = read_config(running).unwrap();
+    config.profiles[0].output_dir = output_dir.to_owned();
+    write_config(running, &config).unwrap();
+    args
+}
+
 // TODO
 // #[tokio::test(flavor = "multi_thread")]
 // async fn arg_parse() {}
 
+#[test]
+fn max_download_speed_units() {
+    let speed = |value| {
+        Tarium::try_parse_from(["tarium", "--max-download-speed", value, "list"])
+            .map(|args| args.max_download_speed)
+    };
+    assert_eq!(speed("2048").unwrap(), Some(2048));
+    assert_eq!(speed("500K").unwrap(), Some(500_000));
+    assert_eq!(speed("1.5MB").unwrap(), Some(1_500_000));
+    assert_eq!(speed("2MiB/s").unwrap(), Some(2 * 1024 * 1024));
+    assert!(speed("fast").is_err());
+    assert!(speed("5 parsecs").is_err());
+    assert!(speed("0").is_err());
+}
+
+#[test]
+fn long_version_has_build_info() {
+    let mut command = Tarium::command();
+    let long_version = command.render_long_version();
+    assert!(long_version.contains("commit: "));
+    assert!(long_version.contains("target: "));
+    // `-V` stays short
+    assert!(!command.render_version().contains("commit"));
+}
+
+#[test]
+fn game_version_completions() {
+    let mut completions = Vec::new();
+    clap_complete::generate(
+        clap_complete::Shell::Bash,
+        &mut Tarium::command(),
+        "tarium",
+        &mut completions,
+    );
+    assert!(String::from_utf8(completions).unwrap().contains("3.11.4"));
+
+    // Versions that aren't known yet must still be accepted
+    assert!(Tarium::try_parse_from(["tarium", "profile", "create", "-v", "3.99.0"]).is_ok());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn create_profile_no_profiles_to_import() {
     assert!(matches!(
         actual_main(get_args(
             SubCommands::Profile {
-                subcommand: Some(ProfileSubCommands::Create {
+                subcommand: Some(ProfileSubCommands::Create(CreateArguments {
                     // There should be no other profiles to import mods from
                     import: Some(None),
                     game_version: vec!["1.21.4".to_owned()],
+                    trim_patch: false,
                     name: Some("Test Profile".to_owned()),
                     output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
-                })
+                    force: false,
+                    create_output_dir: false,
+                }))
             },
             None,
         ))
@@ -65,13 +141,16 @@ async fn create_profile_rel_dir() {
     assert!(matches!(
         actual_main(get_args(
             SubCommands::Profile {
-                subcommand: Some(ProfileSubCommands::Create {
+                subcommand: Some(ProfileSubCommands::Create(CreateArguments {
                     // There should be no other profiles to import mods from
                     import: Some(None),
                     game_version: vec!["1.21.4".to_owned()],
+                    trim_patch: false,
                     name: Some("Test Profile".to_owned()),
                     output_dir: Some(PathBuf::from(".").join("tests").join("mods")),
-                })
+                    force: false,
+                    create_output_dir: false,
+                }))
             },
             None,
         ))
@@ -85,13 +164,17 @@ async fn create_profile_import_mods() {
     assert!(matches!(
         actual_main(get_args(
             SubCommands::Profile {
-                subcommand: Some(ProfileSubCommands::Create {
+                subcommand: Some(ProfileSubCommands::Create(CreateArguments {
                     // There should be no other profiles to import mods from
                     import: Some(Some("Default Modded".to_owned())),
                     game_version: vec!["1.21.4".to_owned()],
+                    trim_patch: false,
                     name: Some("Test Profile".to_owned()),
                     output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
-                })
+                    // Shares the output directory of the profile it imports from
+                    force: true,
+                    create_output_dir: false,
+                }))
             },
             Some("one_profile_full"),
         ))
@@ -105,12 +188,15 @@ async fn create_profile_existing_name() {
     assert!(matches!(
         actual_main(get_args(
             SubCommands::Profile {
-                subcommand: Some(ProfileSubCommands::Create {
+                subcommand: Some(ProfileSubCommands::Create(CreateArguments {
                     import: None,
                     game_version: vec!["1.21.4".to_owned()],
+                    trim_patch: false,
                     name: Some("Default Modded".to_owned()),
-                    output_dir: Some(current_dir().unwrap().join("tests").join("mods"))
-                })
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    force: false,
+                    create_output_dir: false,
+                }))
             },
             None,
         ))
@@ -124,12 +210,15 @@ async fn create_profile() {
     assert!(matches!(
         actual_main(get_args(
             SubCommands::Profile {
-                subcommand: Some(ProfileSubCommands::Create {
+                subcommand: Some(ProfileSubCommands::Create(CreateArguments {
                     import: None,
                     game_version: vec!["1.21.4".to_owned()],
+                    trim_patch: false,
                     name: Some("Test Profile".to_owned()),
-                    output_dir: Some(current_dir().unwrap().join("tests").join("mods"))
-                })
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    force: false,
+                    create_output_dir: false,
+                }))
             },
             None,
         ))
@@ -138,106 +227,1231 @@ async fn create_profile() {
     ));
 }
 
+fn create_command(name: &str, output_dir: PathBuf, force: bool) -> SubCommands {
+    SubCommands::Profile {
+        subcommand: Some(ProfileSubCommands::Create(CreateArguments {
+            import: None,
+            game_version: vec!["3.11".to_owned()],
+            trim_patch: false,
+            name: Some(name.to_owned()),
+            output_dir: Some(output_dir),
+            force,
+            create_output_dir: false,
+        })),
+    }
+}
+
 #[tokio::test(flavor = "multi_thread")]
-async fn add_modrinth() {
-    assert!(matches!(
-        actual_main(get_args(
-            SubCommands::Add {
-                identifiers: vec!["starlight".to_owned()],
+async fn create_profile_nested_output_dir() {
+    let outer = current_dir().unwrap().join("tests").join("nested");
+    let inner = outer.join("SPT");
+    let args = get_args(create_command("Outer", outer.clone(), false), None);
+    let config_file = args.config_file.clone();
+    actual_main(args).await.unwrap();
+
+    let nested = |subcommand| Tarium {
+        subcommand,
+        config_file: config_file.clone(),
+        ..DEFAULT
+    };
+    assert!(
+        actual_main(nested(create_command("Inner", inner.clone(), false)))
+            .await
+            .is_err()
+    );
+    actual_main(nested(create_command("Inner", inner, true)))
+        .await
+        .unwrap();
+    assert_eq!(
+        read_config(config_file.as_ref().unwrap())
+            .unwrap()
+            .profiles
+            .len(),
+        2
+    );
+
+    // The inner profile is now active, moving it onto the outer one's directory must be refused too
+    let configure = |force| {
+        nested(SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: Some(outer.clone()),
+                force,
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        })
+    };
+    assert!(actual_main(configure(false)).await.is_err());
+    actual_main(configure(true)).await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_profile_creates_output_dir() {
+    let parent = tempfile::tempdir().unwrap();
+    let output_dir = parent.path().join("New Install").join("SPT");
+    let mut args = Tarium {
+        subcommand: create_command("Fresh", output_dir.clone(), false),
+        config_file: Some(parent.path().join("config.json")),
+        ..DEFAULT
+    };
+    if let SubCommands::Profile {
+        subcommand:
+            Some(ProfileSubCommands::Create(CreateArguments {
+                create_output_dir, ..
+            })),
+    } = &mut args.subcommand
+    {
+        *create_output_dir = true;
+    }
+    let config_file = args.config_file.clone();
+    actual_main(args).await.unwrap();
+    assert!(output_dir.is_dir());
+
+    // Moving the profile to another directory that doesn't exist yet creates it too
+    let moved = parent.path().join("Moved").join("SPT");
+    actual_main(Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: Some(moved.clone()),
                 force: false,
-                file: None,
-                pin: None,
-            },
-            Some("empty_profile"),
-        ))
-        .await,
-        Ok(()),
-    ));
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: true,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        config_file,
+        ..DEFAULT
+    })
+    .await
+    .unwrap();
+    assert!(moved.is_dir());
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_curseforge() {
-    assert!(matches!(
-        actual_main(get_args(
-            SubCommands::Add {
-                identifiers: vec!["591388".to_owned()],
+async fn configure_restores_game_version_filter() {
+    let args = get_args(
+        SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: Some("Renamed".to_owned()),
+                description: None,
+                output_dir: None,
                 force: false,
-                file: None,
-                pin: None,
-            },
-            Some("empty_profile"),
-        ))
-        .await,
-        Ok(()),
-    ));
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        Some("spt_empty_profile"),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].filters.clear();
+    write_config(&config_file, &config).unwrap();
+
+    actual_main(args).await.unwrap();
+
+    let config = read_config(&config_file).unwrap();
+    assert_eq!(
+        config.profiles[0].filters.game_versions(),
+        Some(&vec!["3.11".to_owned()])
+    );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_github() {
-    assert!(matches!(
-        actual_main(get_args(
-            SubCommands::Add {
-                identifiers: vec!["CaffeineMC/sodium".to_owned()],
+async fn configure_clear_filters() {
+    let args = get_args(
+        SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: None,
                 force: false,
-                file: None,
-                pin: None,
+                check: false,
+                clear_filters: true,
+                show_filters: true,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        Some("spt_empty_profile"),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0]
+        .filters
+        .push(Filter::Filename("(?i)server".to_owned()));
+    config.profiles[0].filters.push(Filter::Prerelease);
+    write_config(&config_file, &config).unwrap();
+
+    actual_main(args).await.unwrap();
+
+    let config = read_config(&config_file).unwrap();
+    assert_eq!(config.profiles[0].filters.len(), 1);
+    assert!(config.profiles[0].filters.game_versions().is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configure_merge_filters() {
+    let filters_dir = tempfile::tempdir().unwrap();
+    let filters_file = filters_dir.path().join("filters.json");
+    write(
+        &filters_file,
+        serde_json::to_string(&[
+            Filter::GameVersionStrict(vec!["3.10".to_owned()]),
+            Filter::Filename("(?i)client".to_owned()),
+        ])
+        .unwrap(),
+    )
+    .unwrap();
+    let configure = |merge_filters| {
+        get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                    game_versions: vec![],
+                    trim_patch: false,
+                    name: None,
+                    description: None,
+                    output_dir: None,
+                    force: false,
+                    check: false,
+                    clear_filters: false,
+                    show_filters: false,
+                    create_output_dir: false,
+                    merge_filters: Some(merge_filters),
+                    rename_output_dir: false,
+                    import_filters_from: None,
+                    set_strict_version: None,
+                    github_token: None,
+                    archive_store_dir: None,
+                    validate_on_save: None,
+                    rename_output_dir_to_match: false,
+                })),
             },
-            Some("empty_profile"),
-        ))
-        .await,
-        Ok(()),
-    ));
+            Some("spt_empty_profile"),
+        )
+    };
+
+    let args = configure(filters_file);
+    let config_file = args.config_file.clone().unwrap();
+    actual_main(args).await.unwrap();
+
+    // The game versions are merged into the existing filter instead of contradicting it
+    let config = read_config(&config_file).unwrap();
+    assert_eq!(
+        config.profiles[0].filters,
+        [
+            Filter::GameVersionStrict(vec!["3.11".to_owned(), "3.10".to_owned()]),
+            Filter::Filename("(?i)client".to_owned()),
+        ]
+    );
+
+    let invalid = filters_dir.path().join("invalid.json");
+    write(&invalid, r#"{"filters": "not a list"}"#).unwrap();
+    assert!(actual_main(configure(invalid)).await.is_err());
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_all() {
-    assert!(matches!(
-        actual_main(get_args(
-            SubCommands::Add {
-                identifiers: vec![
-                    "starlight".to_owned(),
-                    "591388".to_owned(),
-                    "CaffeineMC/sodium".to_owned()
-                ],
+async fn configure_rename_output_dir() {
+    let parent = tempfile::tempdir().unwrap();
+    let output_dir = parent.path().join("Default Profile");
+    create_dir_all(output_dir.join("BepInEx")).unwrap();
+    let mut args = get_args_in(
+        SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: Some("Renamed".to_owned()),
+                description: None,
+                output_dir: None,
+                force: false,
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: true,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        "spt_empty_profile",
+        &output_dir,
+    );
+    let config_file = parent.path().join("config.json");
+    copy(args.config_file.as_ref().unwrap(), &config_file).unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].name = "Default Profile".to_owned();
+    config.set_active_profile(0);
+    write_config(&config_file, &config).unwrap();
+    args.config_file = Some(config_file.clone());
+
+    actual_main(args).await.unwrap();
+
+    // The directory and the mods in it follow the profile's new name
+    let config = read_config(&config_file).unwrap();
+    let renamed = parent.path().join("Renamed");
+    assert_eq!(config.profiles[0].name, "Renamed");
+    assert_eq!(config.profiles[0].output_dir, renamed);
+    assert_eq!(config.active_profile_name.as_deref(), Some("Renamed"));
+    assert!(renamed.join("BepInEx").is_dir());
+    assert!(!output_dir.exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configure_rename_output_dir_to_match() {
+    // Renaming asks for confirmation in a terminal
+    if stdin().is_terminal() {
+        return;
+    }
+    let parent = tempfile::tempdir().unwrap();
+    let output_dir = parent.path().join("Game");
+    create_dir_all(output_dir.join("BepInEx")).unwrap();
+    let config_file = parent.path().join("config.json");
+    let mut config = read_config("tests/configs/spt_empty_profile.json").unwrap();
+    config.profiles[0].output_dir = output_dir.clone();
+    config.profiles[0].install_dirs.archive_store = output_dir.join("Archives");
+    // Another profile with the same name stops the config from being saved
+    config.profiles.push(config.profiles[0].clone());
+    config.profiles[1].output_dir = parent.path().join("Other");
+    config.validate_on_save = true;
+    serde_json::to_writer(std::fs::File::create(&config_file).unwrap(), &config).unwrap();
+    let args = Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: None,
                 force: false,
-                file: None,
-                pin: None,
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: true,
+            })),
+        },
+        config_file: Some(config_file.clone()),
+        ..DEFAULT
+    };
+
+    // The directory is renamed back if the config can't be saved
+    assert!(actual_main(args.clone()).await.is_err());
+    assert!(output_dir.join("BepInEx").is_dir());
+
+    config.profiles[1].name = "Other".to_owned();
+    write_config(&config_file, &config).unwrap();
+    actual_main(args).await.unwrap();
+
+    let renamed = parent.path().join("SPT");
+    let profile = read_config(&config_file).unwrap().profiles.remove(0);
+    assert_eq!(profile.output_dir, renamed);
+    assert_eq!(profile.install_dirs.archive_store, renamed.join("Archives"));
+    assert!(renamed.join("BepInEx").is_dir());
+    assert!(!output_dir.exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configure_import_filters_from() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("config.json");
+    let mut config = read_config("tests/configs/spt_empty_profile.json").unwrap();
+    let mut stable = Profile::new(
+        "Stable".to_owned(),
+        dir.path().to_owned(),
+        vec!["3.10".to_owned()],
+        true,
+    );
+    stable
+        .filters
+        .push(Filter::ReleaseChannel(ReleaseChannel::Release));
+    config.profiles.push(stable);
+    write_config(&config_file, &config).unwrap();
+    let configure = |import_filters_from: &str| Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: None,
+                force: false,
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: Some(import_filters_from.to_owned()),
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        config_file: Some(config_file.clone()),
+        ..DEFAULT
+    };
+
+    actual_main(configure("stable")).await.unwrap();
+
+    // Only the active profile takes on the other's filters, merged with its own
+    let config = read_config(&config_file).unwrap();
+    assert_eq!(
+        config.profiles[0].filters,
+        [
+            Filter::GameVersionStrict(vec!["3.11".to_owned(), "3.10".to_owned()]),
+            Filter::ReleaseChannel(ReleaseChannel::Release),
+        ]
+    );
+    assert!(config.profiles[0].mods.is_empty());
+
+    assert!(actual_main(configure("Missing")).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configure_validate_on_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("config.json");
+    let mut config = read_config("tests/configs/spt_empty_profile.json").unwrap();
+    config.profiles[0].output_dir = dir.path().to_owned();
+    let sain = Mod::new(
+        "SAIN".to_owned(),
+        ModIdentifier::GitHubRepository("Solarint".to_owned(), "SAIN".to_owned()),
+        vec![],
+    );
+    config.profiles[0].mods = vec![sain.clone(), sain];
+    write_config(&config_file, &config).unwrap();
+    let configure = || Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: None,
+                force: false,
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: Some(true),
+                rename_output_dir_to_match: false,
+            })),
+        },
+        config_file: Some(config_file.clone()),
+        ..DEFAULT
+    };
+
+    // A config that's already broken can't be saved with checks turned on
+    assert!(actual_main(configure()).await.is_err());
+    assert!(!read_config(&config_file).unwrap().validate_on_save);
+
+    config.profiles[0].mods.pop();
+    write_config(&config_file, &config).unwrap();
+    actual_main(configure()).await.unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    assert!(config.validate_on_save);
+
+    // From then on, problems stop the config from being saved
+    let saved = read_to_string(&config_file).unwrap();
+    config.profiles.push(config.profiles[0].clone());
+    assert!(write_config(&config_file, &config).is_err());
+    assert_eq!(read_to_string(&config_file).unwrap(), saved);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn configure_set_strict_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("config.json");
+    let mut config = read_config("tests/configs/spt_empty_profile.json").unwrap();
+    config.profiles[0].filters = vec![
+        Filter::ReleaseChannel(ReleaseChannel::Beta),
+        Filter::GameVersionMinor(vec!["3.10".to_owned(), "3.9".to_owned()]),
+    ];
+    write_config(&config_file, &config).unwrap();
+    let configure = |version: &str| Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: None,
+                force: false,
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: Some(version.to_owned()),
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        config_file: Some(config_file.clone()),
+        ..DEFAULT
+    };
+
+    actual_main(configure("3.11")).await.unwrap();
+
+    // The game version filter is replaced exactly, and the other filters are kept
+    assert_eq!(
+        read_config(&config_file).unwrap().profiles[0].filters,
+        [
+            Filter::GameVersionStrict(vec!["3.11".to_owned()]),
+            Filter::ReleaseChannel(ReleaseChannel::Beta),
+        ]
+    );
+
+    assert!(actual_main(configure("eleven")).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_profile_trim_patch() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("config.json");
+    for (name, trim_patch) in [("Precise", false), ("Trimmed", true)] {
+        actual_main(Tarium {
+            subcommand: SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Create(CreateArguments {
+                    import: None,
+                    game_version: vec!["3.11.4".to_owned()],
+                    trim_patch,
+                    name: Some(name.to_owned()),
+                    output_dir: Some(dir.path().join(name)),
+                    force: false,
+                    create_output_dir: true,
+                })),
             },
-            Some("empty_profile"),
-        ))
-        .await,
-        Ok(()),
-    ));
+            config_file: Some(config_file.clone()),
+            ..DEFAULT
+        })
+        .await
+        .unwrap();
+    }
+
+    let config = read_config(&config_file).unwrap();
+    assert_eq!(
+        config.profiles[0].filters,
+        [Filter::GameVersionStrict(vec!["3.11.4".to_owned()])]
+    );
+    assert_eq!(
+        config.profiles[1].filters,
+        [Filter::GameVersionStrict(vec!["3.11".to_owned()])]
+    );
+
+    // Configuring the versions keeps them as given too, unless trimmed
+    for (game_versions, trim_patch, expected) in [
+        (vec!["3.10.8", "3.11.4"], false, vec!["3.10.8", "3.11.4"]),
+        (vec!["3.11.3", "3.11.4"], true, vec!["3.11"]),
+    ] {
+        actual_main(Tarium {
+            subcommand: SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                    game_versions: game_versions.into_iter().map(ToOwned::to_owned).collect(),
+                    trim_patch,
+                    ..ConfigureArguments::default()
+                })),
+            },
+            config_file: Some(config_file.clone()),
+            ..DEFAULT
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            read_config(&config_file).unwrap().profiles[1].filters,
+            [Filter::GameVersionStrict(
+                expected.into_iter().map(ToOwned::to_owned).collect()
+            )]
+        );
+    }
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn already_added() {
-    assert!(matches!(
-        actual_main(get_args(
-            SubCommands::Add {
-                identifiers: vec![
-                    "starlight".to_owned(),
-                    "591388".to_owned(),
-                    "CaffeineMC/sodium".to_owned()
-                ],
+async fn remembers_last_output_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("config.json");
+    let (first, second) = (dir.path().join("First"), dir.path().join("Second"));
+    actual_main(Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Create(CreateArguments {
+                import: None,
+                game_version: vec!["3.11.4".to_owned()],
+                trim_patch: false,
+                name: Some("First".to_owned()),
+                output_dir: Some(first.clone()),
                 force: false,
-                file: None,
-                pin: None,
+                create_output_dir: true,
+            })),
+        },
+        config_file: Some(config_file.clone()),
+        ..DEFAULT
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        read_config(&config_file).unwrap().last_output_dir,
+        Some(first)
+    );
+
+    actual_main(Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec![],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: Some(second.clone()),
+                force: false,
+                check: false,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: true,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        config_file: Some(config_file.clone()),
+        ..DEFAULT
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        read_config(&config_file).unwrap().last_output_dir,
+        Some(second)
+    );
+}
+
+#[test]
+fn configure_check() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let args = get_args_in(
+        SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+                game_versions: vec!["3.11.4".to_owned()],
+                trim_patch: false,
+                name: None,
+                description: None,
+                output_dir: None,
+                force: false,
+                check: true,
+                clear_filters: false,
+                show_filters: false,
+                create_output_dir: false,
+                merge_filters: None,
+                rename_output_dir: false,
+                import_filters_from: None,
+                set_strict_version: None,
+                github_token: None,
+                archive_store_dir: None,
+                validate_on_save: None,
+                rename_output_dir_to_match: false,
+            })),
+        },
+        "spt_profile",
+        output_dir.path(),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].mods.push(Mod::new(
+        "Missing".to_owned(),
+        ModIdentifier::GitHubRepository("Nobody".to_owned(), "Missing".to_owned()),
+        vec![],
+    ));
+    write_config(&config_file, &config).unwrap();
+
+    // Mods without a compatible release are only warned about
+    run_main(args).unwrap();
+
+    let config = read_config(&config_file).unwrap();
+    assert_eq!(
+        config.profiles[0].filters.game_versions(),
+        Some(&vec!["3.11.4".to_owned()])
+    );
+    // Checking doesn't download anything
+    assert!(!output_dir.path().join("BepInEx").exists());
+}
+
+fn add_command(identifiers: &[&str]) -> SubCommands {
+    SubCommands::Add {
+        identifiers: identifiers.iter().map(ToString::to_string).collect(),
+        topic: None,
+        force: false,
+        no_metadata: false,
+        dry_run: false,
+        pin: None,
+        replace: false,
+        include_source_archives: false,
+        asset: None,
+        asset_index: None,
+        filters: FilterArguments::default(),
+        max_releases: None,
+    }
+}
+
+#[test]
+fn add_github() {
+    let args = get_args(add_command(&["Solarint/SAIN"]), Some("spt_empty_profile"));
+    let config_file = args.config_file.clone().unwrap();
+
+    run_main(args).unwrap();
+
+    assert_eq!(read_config(config_file).unwrap().profiles[0].mods.len(), 1);
+}
+
+#[test]
+fn add_fetches_metadata() {
+    for no_metadata in [false, true] {
+        let mut args = get_args(add_command(&["Solarint/SAIN"]), Some("spt_empty_profile"));
+        if let SubCommands::Add {
+            force,
+            no_metadata: skip,
+            ..
+        } = &mut args.subcommand
+        {
+            // Even without checks, the repository's details are fetched
+            *force = true;
+            *skip = no_metadata;
+        }
+        let config_file = args.config_file.clone().unwrap();
+
+        run_main(args).unwrap();
+
+        let mod_ = read_config(config_file).unwrap().profiles[0].mods.remove(0);
+        assert_eq!(mod_.name, "SAIN");
+        assert_eq!(
+            mod_.description.as_deref(),
+            (!no_metadata).then_some("The SAIN mod")
+        );
+    }
+}
+
+#[test]
+fn profile_github_token() {
+    let (owner, repo) = mock_github::PRIVATE_REPO;
+    for token in [None, Some(mock_github::PRIVATE_TOKEN)] {
+        let mut args = get_args(
+            add_command(&[&format!("{owner}/{repo}")]),
+            Some("spt_empty_profile"),
+        );
+        if let SubCommands::Add { force, .. } = &mut args.subcommand {
+            *force = true;
+        }
+        let config_file = args.config_file.clone().unwrap();
+        let mut config = read_config(&config_file).unwrap();
+        config.profiles[0].github_token = token.map(ToOwned::to_owned);
+        write_config(&config_file, &config).unwrap();
+
+        run_main(args).unwrap();
+
+        // Only the profile's own token can see the private repository
+        let mod_ = read_config(&config_file).unwrap().profiles[0]
+            .mods
+            .remove(0);
+        assert_eq!(mod_.description.is_some(), token.is_some());
+        #[cfg(unix)]
+        if token.is_some() {
+            use std::os::unix::fs::PermissionsExt as _;
+            let mode = std::fs::metadata(&config_file)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
+
+#[test]
+fn add_dry_run() {
+    let mut args = get_args(
+        add_command(&["Solarint/SAIN", "DrakiaXYZ/SPT-Waypoints"]),
+        Some("spt_empty_profile"),
+    );
+    if let SubCommands::Add { dry_run, .. } = &mut args.subcommand {
+        *dry_run = true;
+    }
+    let config_file = args.config_file.clone().unwrap();
+
+    run_main(args).unwrap();
+
+    // The mods are resolved and checked, but not added
+    assert!(read_config(config_file).unwrap().profiles[0]
+        .mods
+        .is_empty());
+}
+
+#[test]
+fn add_asset_selection() {
+    let (owner, repo) = mock_github::MULTI_ASSET_REPO;
+    let identifier = format!("{owner}/{repo}");
+    let add = |asset: Option<&str>, asset_index: Option<usize>| {
+        let mut add = add_command(&[&identifier]);
+        if let SubCommands::Add {
+            asset: pattern,
+            asset_index: index,
+            ..
+        } = &mut add
+        {
+            *pattern = asset.map(ToOwned::to_owned);
+            *index = asset_index;
+        }
+        add
+    };
+    let output_dir = tempfile::tempdir().unwrap();
+    let args = get_args_in(add(None, None), "spt_empty_profile", output_dir.path());
+    let config_file = args.config_file.clone().unwrap();
+
+    // The selection has to match exactly one asset of the latest release
+    for (asset, asset_index) in [
+        (Some("Multi-Mod"), None),
+        (Some("Nope"), None),
+        (None, Some(3)),
+    ] {
+        assert!(run_main(Tarium {
+            subcommand: add(asset, asset_index),
+            ..args.clone()
+        })
+        .is_err());
+    }
+    assert!(read_config(&config_file).unwrap().profiles[0]
+        .mods
+        .is_empty());
+
+    run_main(Tarium {
+        subcommand: add(None, Some(2)),
+        ..args.clone()
+    })
+    .unwrap();
+    let filters = read_config(&config_file).unwrap().profiles[0].mods[0]
+        .filters
+        .clone();
+    assert!(matches!(filters.as_slice(), [Filter::Filename(_)]));
+
+    // Upgrades keep picking the selected asset
+    run_main(Tarium {
+        subcommand: upgrade_command(false, false),
+        ..args
+    })
+    .unwrap();
+    let plugins = output_dir.path().join("BepInEx").join("plugins");
+    assert!(plugins
+        .join("Multi-Mod-Server-2.0.0-SPT-3.11.dll")
+        .is_file());
+    assert!(!plugins.join("Multi-Mod-Client-2.0.0-SPT-3.11.dll").exists());
+}
+
+#[test]
+fn add_pinned() {
+    let add = |pin: &str| {
+        let mut add = add_command(&["Solarint/SAIN"]);
+        if let SubCommands::Add { pin: pinned, .. } = &mut add {
+            *pinned = Some(pin.to_owned());
+        }
+        add
+    };
+    let output_dir = tempfile::tempdir().unwrap();
+    let args = get_args_in(add("99"), "spt_empty_profile", output_dir.path());
+    let config_file = args.config_file.clone().unwrap();
+
+    // The pinned asset has to exist
+    assert!(run_main(args.clone()).is_err());
+    assert!(read_config(&config_file).unwrap().profiles[0]
+        .mods
+        .is_empty());
+
+    // The asset of the stable release, even though the prerelease is newer
+    run_main(Tarium {
+        subcommand: add("20"),
+        ..args.clone()
+    })
+    .unwrap();
+    assert_eq!(
+        read_config(&config_file).unwrap().profiles[0].mods[0].identifier,
+        ModIdentifier::PinnedGitHubRepository(("Solarint".to_owned(), "SAIN".to_owned()), 20)
+    );
+
+    run_main(Tarium {
+        subcommand: upgrade_command(false, false),
+        ..args
+    })
+    .unwrap();
+    assert!(output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("SAIN-3.2.0-SPT-3.11.dll")
+        .is_file());
+}
+
+#[test]
+fn add_from_counts_outcomes() {
+    let list = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(
+        list.path(),
+        "# Some mods\nSolarint/SAIN\n\nSolarint/SAIN\nNobody/Missing-Mod\n",
+    )
+    .unwrap();
+    let args = get_args(
+        SubCommands::AddFrom {
+            file: list.path().to_owned(),
+            force: false,
+            no_metadata: false,
+            dry_run: false,
+            max_releases: None,
+        },
+        Some("spt_empty_profile"),
+    );
+    let config_file = args.config_file.clone().unwrap();
+
+    // A mod failing to be added still fails the command, after the rest are added
+    assert!(run_main(args).is_err());
+
+    let mods = read_config(config_file).unwrap().profiles.remove(0).mods;
+    assert_eq!(mods.len(), 1);
+    assert_eq!(mods[0].name, "SAIN");
+}
+
+#[test]
+fn add_force_until_upgraded() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut args = get_args_in(
+        add_command(&["Solarint/SAIN"]),
+        "spt_empty_profile",
+        output_dir.path(),
+    );
+    if let SubCommands::Add { force, .. } = &mut args.subcommand {
+        *force = true;
+    }
+    let config_file = args.config_file.clone().unwrap();
+
+    run_main(args.clone()).unwrap();
+    assert!(read_config(&config_file).unwrap().profiles[0].mods[0].force_added);
+
+    // Resolving the mod checks its compatibility after all
+    run_main(Tarium {
+        subcommand: upgrade_command(false, false),
+        ..args
+    })
+    .unwrap();
+    assert!(!read_config(&config_file).unwrap().profiles[0].mods[0].force_added);
+}
+
+#[test]
+fn add_archived_warns() {
+    let (owner, repo) = mock_github::ARCHIVED_REPO;
+    let mut profile = read_config("tests/configs/spt_empty_profile.json")
+        .unwrap()
+        .profiles
+        .remove(0);
+    let archived = ModIdentifier::GitHubRepository(owner.to_owned(), repo.to_owned());
+    let sain = ModIdentifier::GitHubRepository("Solarint".to_owned(), "SAIN".to_owned());
+
+    // The client has to be built on the runtime
+    let outcomes = mock_github::block_on(async {
+        libarov::add::add_batch(
+            &libarov::GITHUB_API,
+            &mut profile,
+            vec![archived, sain],
+            &libarov::add::AddOptions::default(),
+        )
+        .await
+    });
+
+    // Archived repositories are still added, but the user is told about it
+    assert!(outcomes[0].result.is_ok());
+    assert!(outcomes[0]
+        .warning
+        .as_ref()
+        .is_some_and(|warning| warning.contains("archived")));
+    assert!(outcomes[1].result.is_ok());
+    assert!(outcomes[1].warning.is_none());
+    assert_eq!(profile.mods.len(), 2);
+}
+
+#[test]
+fn topic_candidates_skip_added_mods() {
+    let mut profile = read_config("tests/configs/spt_empty_profile.json")
+        .unwrap()
+        .profiles
+        .remove(0);
+    profile.mods.push(Mod::new(
+        "SAIN".to_owned(),
+        ModIdentifier::GitHubRepository("solarint".to_owned(), "sain".to_owned()),
+        vec![],
+    ));
+
+    // The client has to be built on the runtime
+    let candidates = mock_github::block_on(async {
+        add::topic_candidates(&libarov::GITHUB_API, &profile, mock_github::TOPIC).await
+    })
+    .unwrap();
+
+    assert_eq!(
+        candidates
+            .iter()
+            .map(|candidate| candidate.repo.as_str())
+            .collect::<Vec<_>>(),
+        ["SPT-Waypoints", "Old-Mod", "Multi-Mod"]
+    );
+    assert!(candidates[0].stars > candidates[1].stars);
+    assert!(candidates[0].last_release.is_some());
+}
+
+#[test]
+fn add_not_found() {
+    assert!(run_main(get_args(
+        add_command(&["tarium-test/missing"]),
+        Some("spt_empty_profile")
+    ))
+    .is_err());
+}
+
+#[test]
+fn add_all() {
+    let args = get_args(
+        add_command(&["Solarint/SAIN", "DrakiaXYZ/SPT-Waypoints"]),
+        Some("spt_empty_profile"),
+    );
+    let config_file = args.config_file.clone().unwrap();
+
+    run_main(args).unwrap();
+
+    assert_eq!(read_config(config_file).unwrap().profiles[0].mods.len(), 2);
+}
+
+#[test]
+fn add_alias() {
+    let mut args = get_args(
+        SubCommands::Alias {
+            subcommand: AliasSubCommands::Set {
+                name: "sain".to_owned(),
+                identifier: "Solarint/SAIN".to_owned(),
             },
-            Some("one_profile_full"),
+        },
+        Some("spt_empty_profile"),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    run_main(args.clone()).unwrap();
+
+    // Unknown names fail before anything is added
+    args.subcommand = add_command(&["saint"]);
+    assert!(run_main(args.clone()).is_err());
+
+    args.subcommand = add_command(&["SAIN"]);
+    run_main(args).unwrap();
+
+    let config = read_config(config_file).unwrap();
+    assert_eq!(config.aliases["sain"], "Solarint/SAIN");
+    assert_eq!(
+        config.profiles[0].mods[0].identifier,
+        ModIdentifier::GitHubRepository("Solarint".to_owned(), "SAIN".to_owned())
+    );
+}
+
+#[test]
+fn releases_without_archives() {
+    let (owner, repo) = mock_github::SOURCE_REPO;
+    let mod_ = Mod::new(
+        repo.to_owned(),
+        ModIdentifier::GitHubRepository(owner.to_owned(), repo.to_owned()),
+        vec![],
+    );
+
+    assert!(matches!(
+        // The client has to be built on the runtime
+        mock_github::block_on(async {
+            mod_.fetch_download_file(&libarov::GITHUB_API, vec![], libarov::DEFAULT_MAX_RELEASES)
+                .await
+        }),
+        Err(mod_downloadable::Error::CheckError(
+            check::Error::NoArchiveAssets
         ))
-        .await,
-        Ok(()),
     ));
 }
 
+#[test]
+fn add_tags_without_releases() {
+    let mut profile = read_config("tests/configs/spt_empty_profile.json")
+        .unwrap()
+        .profiles
+        .remove(0);
+    let [tagged, unreleased] = [mock_github::TAGS_REPO, mock_github::UNRELEASED_REPO]
+        .map(|(owner, repo)| ModIdentifier::GitHubRepository(owner.to_owned(), repo.to_owned()));
+
+    // The client has to be built on the runtime
+    let outcomes = mock_github::block_on(async {
+        libarov::add::add_batch(
+            &libarov::GITHUB_API,
+            &mut profile,
+            vec![tagged, unreleased],
+            &libarov::add::AddOptions::default(),
+        )
+        .await
+    });
+
+    // The latest tag is used instead, with a warning that it's source code
+    assert!(outcomes[0].result.is_ok());
+    assert!(outcomes[0]
+        .warning
+        .as_ref()
+        .is_some_and(|warning| warning.contains("v1.1.0-SPT-3.11")));
+    // A repository with nothing released isn't mistaken for one that doesn't exist
+    assert!(matches!(
+        outcomes[1].result,
+        Err(libarov::add::Error::Incompatible(check::Error::NoReleases))
+    ));
+    assert_eq!(profile.mods.len(), 1);
+
+    let (owner, repo) = mock_github::TAGS_REPO;
+    let download_file = mock_github::block_on(async {
+        profile.mods[0]
+            .fetch_download_file(
+                &libarov::GITHUB_API,
+                profile.filters.clone(),
+                libarov::DEFAULT_MAX_RELEASES,
+            )
+            .await
+    })
+    .unwrap();
+    assert_eq!(download_file.tag.as_deref(), Some("v1.1.0-SPT-3.11"));
+    assert_eq!(
+        download_file.download_url.path(),
+        format!("/repos/{owner}/{repo}/zipball/v1.1.0-SPT-3.11")
+    );
+}
+
+#[test]
+fn add_source_archives() {
+    let (owner, repo) = mock_github::SOURCE_REPO;
+    let identifier = format!("{owner}/{repo}");
+    assert!(run_main(get_args(
+        add_command(&[&identifier]),
+        Some("spt_empty_profile")
+    ))
+    .is_err());
+
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut add = add_command(&[&identifier]);
+    if let SubCommands::Add {
+        include_source_archives,
+        ..
+    } = &mut add
+    {
+        *include_source_archives = true;
+    }
+    let args = get_args_in(add, "spt_empty_profile", output_dir.path());
+    let config_file = args.config_file.clone();
+    run_main(args).unwrap();
+
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_empty_profile",
+        output_dir.path(),
+    );
+    args.config_file = config_file;
+    run_main(args).unwrap();
+    assert!(output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("Source-Mod.dll")
+        .is_file());
+}
+
+#[test]
+fn already_added() {
+    let args = get_args(add_command(&["Solarint/SAIN"]), Some("spt_profile"));
+    let config_file = args.config_file.clone().unwrap();
+
+    run_main(args).unwrap();
+
+    assert_eq!(read_config(config_file).unwrap().profiles[0].mods.len(), 2);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn list_no_profile() {
     assert!(matches!(
         actual_main(get_args(
             SubCommands::List {
                 verbose: false,
-                markdown: false
+                markdown: false,
+                broken: false,
             },
             Some("empty"),
         ))
@@ -252,7 +1466,8 @@ async fn list_empty_profile() {
         actual_main(get_args(
             SubCommands::List {
                 verbose: false,
-                markdown: false
+                markdown: false,
+                broken: false,
             },
             Some("empty_profile"),
         ))
@@ -267,7 +1482,8 @@ async fn list() {
         actual_main(get_args(
             SubCommands::List {
                 verbose: false,
-                markdown: false
+                markdown: false,
+                broken: false,
             },
             Some("one_profile_full"),
         ))
@@ -276,13 +1492,34 @@ async fn list() {
     ));
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn list_does_not_rewrite_config() {
+    let args = get_args(
+        SubCommands::List {
+            verbose: false,
+            markdown: false,
+            broken: false,
+        },
+        Some("unsorted_mods"),
+    );
+    let config_file = args.config_file.clone().unwrap();
+
+    actual_main(args).await.unwrap();
+
+    assert_eq!(
+        read_to_string(config_file).unwrap(),
+        read_to_string("./tests/configs/unsorted_mods.json").unwrap()
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn list_verbose() {
     assert!(matches!(
         actual_main(get_args(
             SubCommands::List {
                 verbose: true,
-                markdown: false
+                markdown: false,
+                broken: false,
             },
             Some("one_profile_full"),
         ))
@@ -297,7 +1534,8 @@ async fn list_markdown() {
         actual_main(get_args(
             SubCommands::List {
                 verbose: true,
-                markdown: true
+                markdown: true,
+                broken: false,
             },
             Some("one_profile_full"),
         ))
@@ -318,28 +1556,541 @@ async fn list_profiles() {
     ));
 }
 
-#[tokio::test(flavor = "multi_thread")]
-async fn upgrade() {
-    assert!(matches!(
-        actual_main(get_args(
-            SubCommands::Upgrade { local_only: false },
-            Some("one_profile_full")
-        ))
-        .await,
-        Ok(()),
+fn upgrade_command(local_only: bool, stream: bool) -> SubCommands {
+    SubCommands::Upgrade {
+        local_only,
+        quiet: false,
+        stream,
+        no_move_archives: false,
+        only: vec![],
+        resume: false,
+        only_new: false,
+        report: None,
+        install_order: None,
+        max_releases: None,
+        timeout_per_mod: 300,
+        output_format: OutputFormat::Pretty,
+        clean_first: false,
+        yes: false,
+        explain: false,
+        check_clock: false,
+    }
+}
+
+#[test]
+fn upgrade() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    let config_file = args.config_file.clone().unwrap();
+
+    run_main(args).unwrap();
+
+    // The stable release is preferred over the newer prerelease
+    let plugins = output_dir.path().join("BepInEx").join("plugins");
+    assert!(plugins.join("SAIN-3.2.0-SPT-3.11.dll").is_file());
+    assert!(plugins.join("Waypoints-1.7.0-SPT-3.11.dll").is_file());
+    assert!(output_dir
+        .path()
+        .join("MODS")
+        .join("SAIN-3.2.0-SPT-3.11.zip")
+        .is_file());
+    let config = read_config(config_file).unwrap();
+    assert_eq!(
+        config.profiles[0].mods[0].installed_tag.as_deref(),
+        Some("v3.2.0")
+    );
+    assert!(config.profiles[0].last_upgrade.is_some());
+}
+
+#[test]
+fn upgrade_times_out_slow_mods() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let args = get_args_in(
+        SubCommands::Upgrade {
+            local_only: false,
+            quiet: false,
+            stream: false,
+            no_move_archives: false,
+            only: vec![],
+            resume: false,
+            only_new: false,
+            report: None,
+            install_order: None,
+            max_releases: None,
+            timeout_per_mod: 1,
+            output_format: OutputFormat::Pretty,
+            clean_first: false,
+            yes: false,
+            explain: false,
+            check_clock: false,
+        },
+        "spt_profile",
+        output_dir.path(),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    let (owner, repo) = mock_github::SLOW_REPO;
+    config.profiles[0].mods.push(Mod::new(
+        repo.to_owned(),
+        ModIdentifier::GitHubRepository(owner.to_owned(), repo.to_owned()),
+        vec![],
     ));
+    write_config(&config_file, &config).unwrap();
+
+    // The slow mod fails the upgrade, but doesn't hold up the others
+    assert!(run_main(args).is_err());
+    assert!(output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("SAIN-3.2.0-SPT-3.11.dll")
+        .is_file());
 }
 
-#[tokio::test(flavor = "multi_thread")]
-async fn upgrade_local_only() {
-    assert!(matches!(
-        actual_main(get_args(
-            SubCommands::Upgrade { local_only: true },
-            Some("one_profile_full")
-        ))
-        .await,
-        Ok(()),
+#[test]
+fn upgrade_explain() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    if let SubCommands::Upgrade { explain, .. } = &mut args.subcommand {
+        *explain = true;
+    }
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].mods[0]
+        .filters
+        .push(Filter::Filename("nothing-matches".to_owned()));
+    write_config(&config_file, &config).unwrap();
+
+    // Explaining the rejected mod doesn't stop the rest from upgrading
+    assert!(run_main(args).is_err());
+    let config = read_config(&config_file).unwrap();
+    assert_eq!(
+        config.profiles[0].failed_mods,
+        [config.profiles[0].mods[0].repository()]
+    );
+    assert!(config.profiles[0].mods[1].installed_tag.is_some());
+}
+
+#[test]
+fn upgrade_truncated_archive() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    let (owner, repo) = mock_github::TRUNCATED_REPO;
+    config.profiles[0].mods.push(Mod::new(
+        repo.to_owned(),
+        ModIdentifier::GitHubRepository(owner.to_owned(), repo.to_owned()),
+        vec![],
+    ));
+    write_config(&config_file, &config).unwrap();
+
+    // Extraction failures are reported without failing the whole upgrade
+    run_main(args).unwrap();
+
+    // The archive downloads fine but can't be extracted, so the mod isn't installed
+    let config = read_config(&config_file).unwrap();
+    let truncated = config.profiles[0].mods.last().unwrap();
+    assert_eq!(truncated.installed_tag, None);
+    assert_eq!(config.profiles[0].failed_mods, [truncated.repository()]);
+    assert!(config.profiles[0].mods[0].installed_tag.is_some());
+}
+
+#[test]
+fn upgrade_resume() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].mods.push(Mod::new(
+        "missing".to_owned(),
+        ModIdentifier::GitHubRepository("tarium-test".to_owned(), "missing".to_owned()),
+        vec![],
+    ));
+    write_config(&config_file, &config).unwrap();
+
+    assert!(run_main(args.clone()).is_err());
+    assert_eq!(
+        read_config(&config_file).unwrap().profiles[0].failed_mods,
+        ["tarium-test/missing"]
+    );
+
+    // Only the failed mod is retried, so the others aren't reinstalled
+    let sain = output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("SAIN-3.2.0-SPT-3.11.dll");
+    std::fs::remove_file(&sain).unwrap();
+    if let SubCommands::Upgrade { resume, .. } = &mut args.subcommand {
+        *resume = true;
+    }
+    assert!(run_main(args.clone()).is_err());
+    assert!(!sain.exists());
+
+    // Once the failed mod is gone there's nothing left to resume
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0]
+        .mods
+        .retain(|mod_| mod_.name != "missing");
+    write_config(&config_file, &config).unwrap();
+    run_main(args).unwrap();
+    assert!(read_config(&config_file).unwrap().profiles[0]
+        .failed_mods
+        .is_empty());
+}
+
+#[test]
+fn upgrade_only_new() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    if let SubCommands::Upgrade { only_new, .. } = &mut args.subcommand {
+        *only_new = true;
+    }
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].mods[0].installed_tag = Some("v3.2.0".to_owned());
+    write_config(&config_file, &config).unwrap();
+
+    run_main(args.clone()).unwrap();
+
+    // Only the mod that was never installed is
+    let plugins = output_dir.path().join("BepInEx").join("plugins");
+    assert!(!plugins.join("SAIN-3.2.0-SPT-3.11.dll").exists());
+    assert!(plugins.join("Waypoints-1.7.0-SPT-3.11.dll").is_file());
+    let config = read_config(&config_file).unwrap();
+    assert!(config.profiles[0].mods.iter().all(Mod::is_installed));
+
+    // Afterwards there's nothing new left
+    std::fs::remove_dir_all(&plugins).unwrap();
+    run_main(args).unwrap();
+    assert!(!plugins.exists());
+}
+
+#[test]
+fn list_broken() {
+    let args = get_args(
+        SubCommands::List {
+            verbose: false,
+            markdown: false,
+            broken: true,
+        },
+        Some("spt_profile"),
+    );
+    run_main(args.clone()).unwrap();
+
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].mods.push(Mod::new(
+        "missing".to_owned(),
+        ModIdentifier::GitHubRepository("tarium-test".to_owned(), "missing".to_owned()),
+        vec![],
     ));
+    write_config(&config_file, &config).unwrap();
+
+    let err = run_main(args).unwrap_err();
+    assert!(
+        err.to_string().starts_with("1 of 3 mods are broken"),
+        "{err}"
+    );
+}
+
+#[test]
+fn upgrade_excluded_paths() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    let set_paths = Tarium {
+        subcommand: SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::SetPaths {
+                mod_name: "solarint/sain".to_owned(),
+                include: vec![],
+                exclude: vec!["bepinex/**/SAIN-*.dll".to_owned()],
+            }),
+        },
+        ..args.clone()
+    };
+    run_main(set_paths).unwrap();
+
+    run_main(args).unwrap();
+
+    let plugins = output_dir.path().join("BepInEx").join("plugins");
+    assert!(!plugins.join("SAIN-3.2.0-SPT-3.11.dll").exists());
+    assert!(plugins.join("Waypoints-1.7.0-SPT-3.11.dll").is_file());
+}
+
+#[test]
+fn upgrade_untracked_files() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let plugins = output_dir.path().join("BepInEx").join("plugins");
+    create_dir_all(plugins.join("spt")).unwrap();
+    write(plugins.join("spt").join("spt-core.dll"), "dll").unwrap();
+    write(plugins.join("Manual.dll"), "dll").unwrap();
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+
+    // Without a terminal to confirm on, `--yes` is required
+    if !stdin().is_terminal() {
+        assert!(run_main(args.clone()).is_err());
+        assert!(!plugins.join("SAIN-3.2.0-SPT-3.11.dll").exists());
+    }
+
+    if let SubCommands::Upgrade { yes, .. } = &mut args.subcommand {
+        *yes = true;
+    }
+    run_main(args.clone()).unwrap();
+    assert!(plugins.join("SAIN-3.2.0-SPT-3.11.dll").is_file());
+    assert!(plugins.join("Manual.dll").is_file());
+
+    // The files the mods installed are known now, but the one put there by hand is still noticed
+    if !stdin().is_terminal() {
+        if let SubCommands::Upgrade { yes, .. } = &mut args.subcommand {
+            *yes = false;
+        }
+        assert!(run_main(args.clone()).is_err());
+        remove_file(plugins.join("Manual.dll")).unwrap();
+        run_main(args).unwrap();
+    }
+}
+
+#[test]
+fn upgrade_clean_first() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    run_main(args.clone()).unwrap();
+
+    // The installed files are tracked per mod
+    let mut config = read_config(&config_file).unwrap();
+    let sain = config.profiles[0]
+        .mods
+        .iter_mut()
+        .find(|mod_| mod_.name == "SAIN")
+        .unwrap();
+    let dll = Path::new("BepInEx")
+        .join("plugins")
+        .join("SAIN-3.2.0-SPT-3.11.dll");
+    assert_eq!(sain.files, [dll.clone()]);
+
+    // A file from an older version of the archive, and one the user put there
+    let stale = Path::new("BepInEx")
+        .join("plugins")
+        .join("SAIN")
+        .join("Old.dll");
+    sain.files.push(stale.clone());
+    write_config(&config_file, &config).unwrap();
+    create_dir_all(output_dir.path().join(&stale).parent().unwrap()).unwrap();
+    write(output_dir.path().join(&stale), "dll").unwrap();
+    let manual = output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("Manual.dll");
+    write(&manual, "dll").unwrap();
+
+    if let SubCommands::Upgrade { clean_first, .. } = &mut args.subcommand {
+        *clean_first = true;
+    }
+    // Without a terminal to confirm on, `--yes` is required
+    if !stdin().is_terminal() {
+        assert!(run_main(args.clone()).is_err());
+        assert!(output_dir.path().join(&stale).is_file());
+    }
+
+    if let SubCommands::Upgrade { yes, .. } = &mut args.subcommand {
+        *yes = true;
+    }
+    run_main(args).unwrap();
+    assert!(!output_dir.path().join(&stale).exists());
+    assert!(!output_dir.path().join(&stale).parent().unwrap().exists());
+    assert!(output_dir.path().join(&dll).is_file());
+    assert!(manual.is_file());
+}
+
+#[test]
+fn upgrade_no_move_archives() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    if let SubCommands::Upgrade {
+        no_move_archives, ..
+    } = &mut args.subcommand
+    {
+        *no_move_archives = true;
+    }
+    run_main(args.clone()).unwrap();
+
+    let archive = output_dir.path().join("SAIN-3.2.0-SPT-3.11.zip");
+    let dll = output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("SAIN-3.2.0-SPT-3.11.dll");
+    assert!(archive.is_file());
+    assert!(dll.is_file());
+    assert!(!output_dir
+        .path()
+        .join("MODS")
+        .join("SAIN-3.2.0-SPT-3.11.zip")
+        .exists());
+
+    // The archive was already extracted, so it isn't again
+    remove_file(&dll).unwrap();
+    run_main(args).unwrap();
+    assert!(archive.is_file());
+    assert!(!dll.exists());
+}
+
+#[test]
+fn upgrade_streamed() {
+    let output_dir = tempfile::tempdir().unwrap();
+    run_main(get_args_in(
+        upgrade_command(false, true),
+        "spt_profile",
+        output_dir.path(),
+    ))
+    .unwrap();
+
+    assert!(output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("SAIN-3.2.0-SPT-3.11.dll")
+        .is_file());
+    // The archives never touch the disk, so they aren't stored either
+    assert!(!output_dir.path().join("SAIN-3.2.0-SPT-3.11.zip").exists());
+    assert!(!output_dir
+        .path()
+        .join("MODS")
+        .join("SAIN-3.2.0-SPT-3.11.zip")
+        .exists());
+}
+
+#[test]
+fn upgrade_local_only() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mods_dir = output_dir.path().join("MODS");
+    create_dir_all(&mods_dir).unwrap();
+    write(
+        mods_dir.join("Local-1.0.0.zip"),
+        mock_github::mod_archive("Local-1.0.0.zip"),
+    )
+    .unwrap();
+
+    run_main(get_args_in(
+        upgrade_command(true, false),
+        "spt_profile",
+        output_dir.path(),
+    ))
+    .unwrap();
+
+    assert!(output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("Local-1.0.0.dll")
+        .is_file());
+}
+
+#[test]
+fn upgrade_uses_archive_store_dir() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let store = tempfile::tempdir().unwrap();
+    let configure = |archive_store_dir: PathBuf| SubCommands::Profile {
+        subcommand: Some(ProfileSubCommands::Configure(ConfigureArguments {
+            game_versions: vec![],
+            trim_patch: false,
+            name: None,
+            description: None,
+            output_dir: None,
+            force: false,
+            check: false,
+            clear_filters: false,
+            show_filters: false,
+            create_output_dir: false,
+            merge_filters: None,
+            rename_output_dir: false,
+            import_filters_from: None,
+            set_strict_version: None,
+            github_token: None,
+            archive_store_dir: Some(archive_store_dir),
+            validate_on_save: None,
+            rename_output_dir_to_match: false,
+        })),
+    };
+    let args = get_args_in(
+        configure(store.path().join("archives")),
+        "spt_profile",
+        output_dir.path(),
+    );
+    assert!(run_main(Tarium {
+        subcommand: configure(PathBuf::from("archives")),
+        ..args.clone()
+    })
+    .is_err());
+    run_main(args.clone()).unwrap();
+
+    run_main(Tarium {
+        subcommand: upgrade_command(false, false),
+        ..args.clone()
+    })
+    .unwrap();
+
+    // Archives are kept in the store instead of the output directory
+    let archives = store.path().join("archives");
+    assert!(archives.join("SAIN-3.2.0-SPT-3.11.zip").is_file());
+    assert!(!output_dir.path().join("MODS").exists());
+
+    // and that's where local-only upgrades look for them
+    write(
+        archives.join("Local-1.0.0.zip"),
+        mock_github::mod_archive("Local-1.0.0.zip"),
+    )
+    .unwrap();
+    run_main(Tarium {
+        subcommand: upgrade_command(true, false),
+        ..args
+    })
+    .unwrap();
+    assert!(output_dir
+        .path()
+        .join("BepInEx")
+        .join("plugins")
+        .join("Local-1.0.0.dll")
+        .is_file());
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -367,7 +2118,10 @@ async fn remove_fail() {
                     "starlght (fabric)".to_owned(),
                     "incendum".to_owned(),
                     "sodum".to_owned(),
-                ]
+                ],
+                regex: None,
+                purge: false,
+                keep_files: false,
             },
             Some("two_profiles_one_empty")
         ))
@@ -385,7 +2139,10 @@ async fn remove_name() {
                     "starlight (fabric)".to_owned(),
                     "incendium".to_owned(),
                     "sodium".to_owned(),
-                ]
+                ],
+                regex: None,
+                purge: false,
+                keep_files: false,
             },
             Some("two_profiles_one_empty")
         ))
@@ -403,7 +2160,10 @@ async fn remove_id() {
                     "H8CaAYZC".to_owned(),
                     "591388".to_owned(),
                     "caffeinemc/sodium".to_owned(),
-                ]
+                ],
+                regex: None,
+                purge: false,
+                keep_files: false,
             },
             Some("two_profiles_one_empty")
         ))
@@ -419,6 +2179,7 @@ async fn remove_slug() {
         SubCommands::List {
             verbose: true,
             markdown: false,
+            broken: false,
         },
         Some("two_profiles_one_empty"),
     );
@@ -430,10 +2191,66 @@ async fn remove_slug() {
             "incendium".to_owned(),
             "sodium".to_owned(),
         ],
+        regex: None,
+        purge: false,
+        keep_files: false,
     };
     assert!(matches!(actual_main(args).await, Ok(())));
 }
 
+#[test]
+fn remove_purge_and_keep_files() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    run_main(args.clone()).unwrap();
+    let plugins = output_dir.path().join("BepInEx").join("plugins");
+    let dll = plugins.join("SAIN-3.2.0-SPT-3.11.dll");
+    let manual = plugins.join("Manual.dll");
+    write(&manual, "dll").unwrap();
+    let config_file = args.config_file.clone().unwrap();
+    let before = read_config(&config_file).unwrap().profiles[0].mods.len();
+
+    // Without a terminal to ask on, the files are kept
+    args.subcommand = SubCommands::Remove {
+        mod_names: vec!["SAIN".to_owned()],
+        regex: None,
+        purge: false,
+        keep_files: false,
+    };
+    if !stdin().is_terminal() {
+        run_main(args.clone()).unwrap();
+        assert!(dll.is_file());
+        assert_eq!(
+            read_config(&config_file).unwrap().profiles[0].mods.len(),
+            before - 1
+        );
+    }
+
+    let mut args = get_args_in(
+        upgrade_command(false, false),
+        "spt_profile",
+        output_dir.path(),
+    );
+    // The kept files aren't tracked by the fresh config
+    if let SubCommands::Upgrade { yes, .. } = &mut args.subcommand {
+        *yes = true;
+    }
+    run_main(args.clone()).unwrap();
+    args.subcommand = SubCommands::Remove {
+        mod_names: vec!["SAIN".to_owned()],
+        regex: None,
+        purge: true,
+        keep_files: false,
+    };
+    run_main(args).unwrap();
+    assert!(!dll.exists());
+    assert!(manual.is_file());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn delete_profile() {
     assert!(matches!(
@@ -441,7 +2258,8 @@ async fn delete_profile() {
             SubCommands::Profile {
                 subcommand: Some(ProfileSubCommands::Delete {
                     profile_name: Some("Profile Two".to_owned()),
-                    switch_to: None
+                    switch_to: None,
+                    purge_files: false,
                 })
             },
             Some("two_profiles_one_empty")
@@ -450,3 +2268,48 @@ async fn delete_profile() {
         Ok(()),
     ));
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_profile_keeps_active() {
+    let args = get_args(
+        SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Delete {
+                profile_name: Some("SPT".to_owned()),
+                switch_to: None,
+                purge_files: false,
+            }),
+        },
+        Some("spt_profile"),
+    );
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    for name in ["Second", "Third"] {
+        let mut profile = config.profiles[0].clone();
+        profile.name = name.to_owned();
+        config.profiles.push(profile);
+    }
+    // Configs from before the name was tracked only have the index
+    config.active_profile = 2;
+    config.active_profile_name = None;
+    write_config(&config_file, &config).unwrap();
+
+    actual_main(args).await.unwrap();
+
+    let config = read_config(config_file).unwrap();
+    assert_eq!(config.active_profile, 1);
+    assert_eq!(config.active_profile_name.as_deref(), Some("Third"));
+}
+
+#[test]
+fn status() {
+    run_main(get_args(
+        SubCommands::Status { check: false },
+        Some("spt_profile"),
+    ))
+    .unwrap();
+    run_main(get_args(
+        SubCommands::Status { check: true },
+        Some("spt_profile"),
+    ))
+    .unwrap();
+}
@@ -0,0 +1,427 @@
+//! A local stand-in for the GitHub API serving canned releases of SPT mods,
+//! so that the add and upgrade tests don't depend on the network or GitHub's rate limits
+
+use crate::{actual_main, cli::Tarium};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::{io::Write as _, sync::LazyLock, time::Duration};
+use tokio::runtime::Runtime;
+use wiremock::{
+    matchers::{header, method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// A release served by the mock server
+struct MockRelease {
+    tag: &'static str,
+    prerelease: bool,
+    assets: &'static [&'static str],
+}
+
+/// The repositories served by the mock server, with their releases newest first
+const REPOS: &[(&str, &str, &[MockRelease])] = &[
+    (
+        "Solarint",
+        "SAIN",
+        &[
+            MockRelease {
+                tag: "v3.3.0-beta",
+                prerelease: true,
+                assets: &["SAIN-3.3.0-beta-SPT-3.11.zip"],
+            },
+            MockRelease {
+                tag: "v3.2.0",
+                prerelease: false,
+                assets: &["SAIN-3.2.0-SPT-3.11.zip"],
+            },
+        ],
+    ),
+    (
+        "DrakiaXYZ",
+        "SPT-Waypoints",
+        &[MockRelease {
+            tag: "1.7.0",
+            prerelease: false,
+            assets: &["Waypoints-1.7.0-SPT-3.11.zip"],
+        }],
+    ),
+    (
+        ARCHIVED_REPO.0,
+        ARCHIVED_REPO.1,
+        &[MockRelease {
+            tag: "v1.0.0",
+            prerelease: false,
+            assets: &["Old-Mod-1.0.0-SPT-3.11.zip"],
+        }],
+    ),
+    (
+        MULTI_ASSET_REPO.0,
+        MULTI_ASSET_REPO.1,
+        &[
+            MockRelease {
+                tag: "v2.0.0",
+                prerelease: false,
+                assets: &[
+                    "Multi-Mod-Client-2.0.0-SPT-3.11.zip",
+                    "Multi-Mod-Server-2.0.0-SPT-3.11.zip",
+                ],
+            },
+            MockRelease {
+                tag: "v1.0.0",
+                prerelease: false,
+                assets: &[
+                    "Multi-Mod-Client-1.0.0-SPT-3.11.zip",
+                    "Multi-Mod-Server-1.0.0-SPT-3.11.zip",
+                ],
+            },
+        ],
+    ),
+];
+
+/// The topic every repository with releases is tagged with
+pub const TOPIC: &str = "spt-mod";
+
+/// A repository that has been archived, so it won't receive updates
+pub const ARCHIVED_REPO: (&str, &str) = ("Retired", "Old-Mod");
+
+/// A repository whose releases have a client and a server archive, to pick from when adding it
+pub const MULTI_ASSET_REPO: (&str, &str) = ("Packager", "Multi-Mod");
+
+/// A repository whose releases take longer to be served than any test waits for
+pub const SLOW_REPO: (&str, &str) = ("Sluggish", "Slow-Mod");
+
+/// A repository whose only release has no assets, so it can only be installed from its source code
+pub const SOURCE_REPO: (&str, &str) = ("Tinkerer", "Source-Mod");
+
+/// A repository that pushes tags without publishing releases, so it can only be installed from the source code of a tag
+pub const TAGS_REPO: (&str, &str) = ("Tagger", "Tag-Mod");
+
+/// A repository without any releases or tags, so there's nothing to install from it
+pub const UNRELEASED_REPO: (&str, &str) = ("Planner", "Unreleased-Mod");
+
+/// A private repository, which is only visible to requests authenticated with [`PRIVATE_TOKEN`]
+pub const PRIVATE_REPO: (&str, &str) = ("Secretive", "Private-Mod");
+
+/// The token that can see the [`PRIVATE_REPO`]
+pub const PRIVATE_TOKEN: &str = "private-token";
+
+/// A repository whose only release has an archive that was cut off half way through uploading it
+pub const TRUNCATED_REPO: (&str, &str) = ("Hasty", "Truncated-Mod");
+
+/// The releases of the [`TRUNCATED_REPO`]
+const TRUNCATED_RELEASES: &[MockRelease] = &[MockRelease {
+    tag: "v1.0.0",
+    prerelease: false,
+    assets: &["Truncated-Mod-1.0.0-SPT-3.11.zip"],
+}];
+
+/// The runtime shared by every test that talks to the GitHub API
+///
+/// The GitHub client spawns a background task on the runtime it's first used in,
+/// so it would stop working once the runtime of a single `#[tokio::test]` shuts down.
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+});
+
+static SERVER: LazyLock<MockServer> = LazyLock::new(|| {
+    let server = RUNTIME.block_on(MockServer::start());
+    RUNTIME.block_on(mount(&server));
+    std::env::set_var(libarov::GITHUB_API_URL_ENV, server.uri());
+    server
+});
+
+/// Run tarium with `args` against the mock server
+pub fn run_main(args: Tarium) -> Result<()> {
+    LazyLock::force(&SERVER);
+    RUNTIME.block_on(actual_main(args))
+}
+
+/// Run `future` on the runtime shared with the mock server, e.g. to call into libarov directly
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    LazyLock::force(&SERVER);
+    RUNTIME.block_on(future)
+}
+
+/// A zip archive of a mod with a single plugin named after the `asset`
+pub fn mod_archive(asset: &str) -> Vec<u8> {
+    let stem = asset.trim_end_matches(".zip");
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    zip.start_file(
+        format!("BepInEx/plugins/{stem}.dll"),
+        SimpleFileOptions::default(),
+    )
+    .unwrap();
+    zip.write_all(stem.as_bytes()).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+async fn mount(server: &MockServer) {
+    let base = server.uri();
+    let all_repos = REPOS.iter().map(|(owner, repo, _)| (*owner, *repo)).chain([
+        SOURCE_REPO,
+        SLOW_REPO,
+        TAGS_REPO,
+        UNRELEASED_REPO,
+        TRUNCATED_REPO,
+    ]);
+    for (index, (owner, repo)) in all_repos.enumerate() {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{owner}/{repo}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(repository(
+                &base,
+                index + 1,
+                owner,
+                repo,
+            )))
+            .mount(server)
+            .await;
+    }
+    let (owner, repo) = PRIVATE_REPO;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{owner}/{repo}")))
+        .and(header("authorization", format!("Bearer {PRIVATE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(repository(&base, 1, owner, repo)))
+        .mount(server)
+        .await;
+    // GitHub doesn't reveal that private repositories exist
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{owner}/{repo}")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "message": "Not Found",
+            "documentation_url": "https://docs.github.com/rest/repos/repos#get-a-repository",
+        })))
+        .mount(server)
+        .await;
+
+    let tagged = REPOS
+        .iter()
+        .enumerate()
+        .map(|(index, (owner, repo, _))| repository(&base, index + 1, owner, repo))
+        .collect::<Vec<_>>();
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .and(query_param("q", format!("topic:{TOPIC}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "total_count": tagged.len(),
+            "incomplete_results": false,
+            "items": tagged,
+        })))
+        .mount(server)
+        .await;
+
+    // Not tagged with the topic, so it's only served when asked for directly
+    let truncated = (TRUNCATED_REPO.0, TRUNCATED_REPO.1, TRUNCATED_RELEASES);
+    for (repo_index, (owner, repo, releases)) in
+        REPOS.iter().copied().chain([truncated]).enumerate()
+    {
+        let mut release_values = Vec::new();
+        for (index, release) in releases.iter().enumerate() {
+            let id = (repo_index * 100 + index + 1) as u64;
+            let mut assets = Vec::new();
+            for (asset_index, name) in release.assets.iter().enumerate() {
+                let asset_id = id * 10 + asset_index as u64;
+                let mut archive = mod_archive(name);
+                if (owner, repo) == TRUNCATED_REPO {
+                    archive.truncate(archive.len() / 2);
+                }
+                let download_path =
+                    format!("/{owner}/{repo}/releases/download/{}/{name}", release.tag);
+                let asset = json!({
+                    "url": format!("{base}/repos/{owner}/{repo}/releases/assets/{asset_id}"),
+                    "browser_download_url": format!("{base}{download_path}"),
+                    "id": asset_id,
+                    "node_id": format!("RA_{asset_id}"),
+                    "name": name,
+                    "label": null,
+                    "state": "uploaded",
+                    "content_type": "application/zip",
+                    "size": archive.len(),
+                    "download_count": 0,
+                    "created_at": timestamp(index),
+                    "updated_at": timestamp(index),
+                    "uploader": author(&base, owner),
+                });
+                Mock::given(method("GET"))
+                    .and(path(format!(
+                        "/repos/{owner}/{repo}/releases/assets/{asset_id}"
+                    )))
+                    .respond_with(ResponseTemplate::new(200).set_body_json(&asset))
+                    .mount(server)
+                    .await;
+                assets.push(asset);
+
+                Mock::given(method("GET"))
+                    .and(path(download_path))
+                    .respond_with(ResponseTemplate::new(200).set_body_bytes(archive))
+                    .mount(server)
+                    .await;
+            }
+            Mock::given(method("GET"))
+                .and(path(format!("/repos/{owner}/{repo}/releases/{id}/assets")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(&assets))
+                .mount(server)
+                .await;
+
+            release_values.push(json!({
+                "url": format!("{base}/repos/{owner}/{repo}/releases/{id}"),
+                "html_url": format!("{base}/{owner}/{repo}/releases/tag/{}", release.tag),
+                "assets_url": format!("{base}/repos/{owner}/{repo}/releases/{id}/assets"),
+                "upload_url": format!("{base}/repos/{owner}/{repo}/releases/{id}/assets{{?name,label}}"),
+                "tarball_url": null,
+                "zipball_url": null,
+                "id": id,
+                "node_id": format!("RE_{id}"),
+                "tag_name": release.tag,
+                "target_commitish": "master",
+                "name": release.tag,
+                "body": "",
+                "draft": false,
+                "prerelease": release.prerelease,
+                "created_at": timestamp(index),
+                "published_at": timestamp(index),
+                "author": author(&base, owner),
+                "assets": assets,
+            }));
+        }
+
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{owner}/{repo}/releases")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(release_values))
+            .mount(server)
+            .await;
+    }
+
+    let (owner, repo) = SOURCE_REPO;
+    let id = 900;
+    let tag = "v1.0.0";
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{owner}/{repo}/zipball/{tag}")))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(mod_archive("Source-Mod.zip")))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{owner}/{repo}/releases/{id}/assets")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{owner}/{repo}/releases")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "url": format!("{base}/repos/{owner}/{repo}/releases/{id}"),
+            "html_url": format!("{base}/{owner}/{repo}/releases/tag/{tag}"),
+            "assets_url": format!("{base}/repos/{owner}/{repo}/releases/{id}/assets"),
+            "upload_url": format!("{base}/repos/{owner}/{repo}/releases/{id}/assets{{?name,label}}"),
+            "tarball_url": null,
+            "zipball_url": format!("{base}/repos/{owner}/{repo}/zipball/{tag}"),
+            "id": id,
+            "node_id": format!("RE_{id}"),
+            "tag_name": tag,
+            "target_commitish": "master",
+            "name": "Source Mod 1.0.0 for SPT 3.11.0",
+            "body": "",
+            "draft": false,
+            "prerelease": false,
+            "created_at": timestamp(0),
+            "published_at": timestamp(0),
+            "author": author(&base, owner),
+            "assets": [],
+        }])))
+        .mount(server)
+        .await;
+
+    // Newest first, like GitHub lists them
+    let (owner, repo) = TAGS_REPO;
+    let mut tags = Vec::new();
+    for tag in ["v1.1.0-SPT-3.11", "v1.0.0-SPT-3.10"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{owner}/{repo}/zipball/{tag}")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(mod_archive(&format!("{repo}-{tag}.zip"))),
+            )
+            .mount(server)
+            .await;
+        tags.push(json!({
+            "name": tag,
+            "commit": {
+                "sha": "c0ffee",
+                "url": format!("{base}/repos/{owner}/{repo}/commits/c0ffee"),
+            },
+            "zipball_url": format!("{base}/repos/{owner}/{repo}/zipball/{tag}"),
+            "tarball_url": format!("{base}/repos/{owner}/{repo}/tarball/{tag}"),
+            "node_id": format!("REF_{tag}"),
+        }));
+    }
+    for ((owner, repo), tags) in [(TAGS_REPO, json!(tags)), (UNRELEASED_REPO, json!([]))] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{owner}/{repo}/releases")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{owner}/{repo}/tags")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tags))
+            .mount(server)
+            .await;
+    }
+
+    let (owner, repo) = SLOW_REPO;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{owner}/{repo}/releases")))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!([]))
+                .set_delay(Duration::from_secs(60)),
+        )
+        .mount(server)
+        .await;
+}
+
+/// The metadata of the repository `owner/repo`, which is archived if it's the [`ARCHIVED_REPO`]
+fn repository(base: &str, id: usize, owner: &str, repo: &str) -> Value {
+    json!({
+        "id": id,
+        "name": repo,
+        "full_name": format!("{owner}/{repo}"),
+        "description": format!("The {repo} mod"),
+        "url": format!("{base}/repos/{owner}/{repo}"),
+        "html_url": format!("{base}/{owner}/{repo}"),
+        "owner": author(base, owner),
+        "stargazers_count": 100 / id,
+        "archived": (owner, repo) == ARCHIVED_REPO,
+        "disabled": false,
+    })
+}
+
+/// The publishing time of the `index`th newest release
+fn timestamp(index: usize) -> String {
+    format!("2025-06-{:02}T12:00:00Z", 28 - index)
+}
+
+fn author(base: &str, login: &str) -> Value {
+    let url = format!("{base}/users/{login}");
+    json!({
+        "login": login,
+        "id": 1,
+        "node_id": "U_1",
+        "avatar_url": format!("{url}/avatar"),
+        "gravatar_id": "",
+        "url": url,
+        "html_url": url,
+        "followers_url": format!("{url}/followers"),
+        "following_url": format!("{url}/following"),
+        "gists_url": format!("{url}/gists"),
+        "starred_url": format!("{url}/starred"),
+        "subscriptions_url": format!("{url}/subscriptions"),
+        "organizations_url": format!("{url}/orgs"),
+        "repos_url": format!("{url}/repos"),
+        "events_url": format!("{url}/events"),
+        "received_events_url": format!("{url}/received_events"),
+        "type": "User",
+        "site_admin": false,
+    })
+}
@@ -0,0 +1,118 @@
+use chrono::{Duration, Utc};
+use colored::Colorize as _;
+use libarov::GITHUB_API;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use tokio::task::JoinHandle;
+
+/// How long the latest release found is trusted before checking GitHub again
+const CHECK_INTERVAL: Duration = Duration::days(1);
+
+/// The result of the last check, cached so that GitHub is only asked once a day
+#[derive(Deserialize, Serialize)]
+struct Cache {
+    /// Unix timestamp of the check
+    checked_at: i64,
+    latest: String,
+}
+
+fn cache_file() -> PathBuf {
+    libarov::cache_dir().join("update_check.json")
+}
+
+/// Start looking for a newer release of tarium in the background
+///
+/// Await the handle with [`print_notice`] once the command has finished.
+pub fn spawn() -> JoinHandle<Option<String>> {
+    tokio::spawn(async {
+        let latest = latest_version().await;
+        debug!(SCOPE = "update_check", latest:debug = latest; "Checked for a newer version");
+        latest.filter(|latest| is_newer(latest, env!("CARGO_PKG_VERSION")))
+    })
+}
+
+/// Print a notice if the check started by [`spawn`] found a newer version
+///
+/// The command is never held up by the check, so nothing is printed if it hasn't finished yet.
+pub async fn print_notice(handle: JoinHandle<Option<String>>) {
+    if !handle.is_finished() {
+        handle.abort();
+        return;
+    }
+    if let Ok(Some(latest)) = handle.await {
+        println!(
+            "\n{}",
+            format!(
+                "A newer version of tarium ({latest}) is available at https://github.com/NQMVD/tarium/releases"
+            )
+            .yellow()
+        );
+    }
+}
+
+/// Get the latest release's version, from the cache if it's recent enough.
+/// Any errors are logged and ignored.
+async fn latest_version() -> Option<String> {
+    if let Some(cache) = fs::read_to_string(cache_file())
+        .ok()
+        .and_then(|cache| serde_json::from_str::<Cache>(&cache).ok())
+    {
+        if Utc::now().timestamp() - cache.checked_at < CHECK_INTERVAL.num_seconds() {
+            return Some(cache.latest);
+        }
+    }
+
+    let latest = match GITHUB_API
+        .repos("NQMVD", "tarium")
+        .releases()
+        .get_latest()
+        .await
+    {
+        Ok(release) => release.tag_name,
+        Err(err) => {
+            debug!(SCOPE = "update_check", error:display = err; "Failed to get the latest release");
+            return None;
+        }
+    };
+
+    let cache = Cache {
+        checked_at: Utc::now().timestamp(),
+        latest: latest.clone(),
+    };
+    if let Err(err) = fs::create_dir_all(libarov::cache_dir()).and_then(|()| {
+        fs::write(
+            cache_file(),
+            serde_json::to_string(&cache).unwrap_or_default(),
+        )
+    }) {
+        debug!(SCOPE = "update_check", error:display = err; "Failed to cache the latest release");
+    }
+
+    Some(latest)
+}
+
+/// Whether the `latest` version is newer than the `current` one, comparing numeric components
+fn is_newer(latest: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    }
+    parse(latest) > parse(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.1.10", "0.1.9"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+    }
+}